@@ -0,0 +1,3 @@
+import { hello } from "./script.js";
+
+hello();
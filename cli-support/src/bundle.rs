@@ -0,0 +1,496 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use manganis_common::{
+    chunk_specifier_mut, is_bare_js_specifier, js_module_imports, parse_js_module,
+    resolve_js_module, AssetSource, JsOptions, JsReference,
+};
+use swc::{config::IsModule, try_with_handler};
+use swc_atoms::JsWord;
+use swc_bundler::{Bundler, Hook, Load, ModuleData, ModuleRecord, Resolve};
+use swc_common::{
+    comments::SingleThreadedComments, sync::Lrc, FileName, Globals, Mark, SourceFile, SourceMap,
+    Span, SyntaxContext, GLOBALS,
+};
+use swc_ecma_ast::{
+    Expr, IdentName, KeyValueProp, MemberExpr, MemberProp, MetaPropExpr, MetaPropKind, Module,
+    Program, PropName,
+};
+use swc_ecma_loader::resolve::Resolution;
+use swc_ecma_parser::Syntax;
+use swc_ecma_transforms_base::{helpers::Helpers, resolver};
+use swc_ecma_visit::{VisitMut, VisitMutWith};
+
+use crate::file::compile_options;
+
+/// Bundles a js module and every local module it imports into a single module. The modules are hoisted into one scope so unused exports can be removed when the bundle is minified. Modules that are imported with a url or with one of the external bare specifiers in the options are loaded at runtime. Returns the js and the source map for it if `source_map` is true
+pub(crate) fn bundle_js(
+    source: &AssetSource,
+    options: &JsOptions,
+    source_map: bool,
+) -> anyhow::Result<(String, Option<String>)> {
+    let AssetSource::Local(path) = source else {
+        anyhow::bail!("Only local js can be bundled");
+    };
+    let entry = path.canonicalize()?;
+
+    let cm = Arc::<SourceMap>::default();
+    let c = swc::Compiler::new(cm.clone());
+    let globals = Globals::default();
+    let output = GLOBALS.set(&globals, || {
+        try_with_handler(cm.clone(), Default::default(), |handler| {
+            let mut loader = Loader {
+                cm: cm.clone(),
+                root: entry.parent().map(Path::to_path_buf).unwrap_or_default(),
+                source_map,
+                references: options.references(),
+                externals: options.externals(),
+                modules: HashMap::new(),
+            };
+            let external_modules = loader.load_graph(&entry)?;
+            let entry_span = loader.modules[&entry].1.span;
+
+            let mut bundler = Bundler::new(
+                &globals,
+                cm.clone(),
+                &loader,
+                Resolver,
+                swc_bundler::Config {
+                    external_modules,
+                    ..Default::default()
+                },
+                Box::new(ImportMetaHook),
+            );
+            let mut bundles = bundler
+                .bundle(HashMap::from([(
+                    "main".to_string(),
+                    FileName::Real(entry.clone()),
+                )]))
+                .map_err(|err| anyhow::anyhow!("failed to bundle {}: {err:?}", entry.display()))?;
+            let bundle = bundles
+                .pop()
+                .context("the bundler didn't create a bundle")?;
+
+            // The bundle takes the span of the entry so it is compiled as the entry file
+            let mut program = Program::Module(Module {
+                span: entry_span,
+                ..bundle.module
+            });
+            // The bindings have unique names now. The contexts are reset so the bundle is resolved again as a single module when it is compiled
+            program.visit_mut_with(&mut ResetSyntaxContext);
+
+            c.process_js(
+                handler,
+                program,
+                &compile_options(
+                    Syntax::Es(Default::default()),
+                    IsModule::Bool(true),
+                    options,
+                    source_map,
+                )?,
+            )
+            .context("failed to bundle javascript")
+        })
+    })?;
+
+    Ok((output.code, output.map))
+}
+
+/// Loads the modules in the bundle. Every module is parsed and compiled to plain js before the bundle is created, so the modules that are loaded at runtime are known up front
+struct Loader<'a> {
+    cm: Lrc<SourceMap>,
+    root: PathBuf,
+    source_map: bool,
+    references: &'a [JsReference],
+    /// The bare specifiers that are loaded from the import map
+    externals: &'a [String],
+    modules: HashMap<PathBuf, (Lrc<SourceFile>, Module)>,
+}
+
+impl Loader<'_> {
+    /// Loads a module and every module it imports. Returns the specifiers of the modules that are loaded at runtime
+    fn load_graph(&mut self, entry: &Path) -> anyhow::Result<Vec<JsWord>> {
+        let mut external = Vec::new();
+        let mut stack = vec![entry.to_path_buf()];
+        while let Some(path) = stack.pop() {
+            if self.modules.contains_key(&path) {
+                continue;
+            }
+            let (fm, module) = self.parse(&path)?;
+            for specifier in js_module_imports(&module) {
+                let import = if self.externals.contains(&specifier) {
+                    None
+                } else {
+                    match resolve_js_module(&path, &specifier) {
+                        Ok(import) => import,
+                        Err(err) if is_bare_js_specifier(&specifier) => {
+                            return Err(err.context(format!(
+                                "{specifier} must be installed in node_modules or mapped to js with import_map"
+                            )))
+                        }
+                        Err(err) => return Err(err),
+                    }
+                };
+                match import {
+                    Some(import) => stack.push(import),
+                    None => {
+                        let specifier = JsWord::from(specifier);
+                        if !external.contains(&specifier) {
+                            external.push(specifier);
+                        }
+                    }
+                }
+            }
+            self.modules.insert(path, (fm, module));
+        }
+
+        Ok(external)
+    }
+
+    /// Parses a module and compiles TypeScript and jsx to plain js. Chunk specifiers are rewritten to the location the chunks are served at
+    fn parse(&self, path: &Path) -> anyhow::Result<(Lrc<SourceFile>, Module)> {
+        let name = self.file_name(path);
+        let module = parse_js_module(&self.cm, path, name.clone())?;
+        let fm = self
+            .cm
+            .get_source_file(&name)
+            .context("the parsed module is missing from the source map")?;
+
+        // The bundler resolves the module again, so these marks are only used to compile the module
+        let unresolved_mark = Mark::new();
+        let top_level_mark = Mark::new();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let typescript = matches!(extension, Some("ts" | "tsx"));
+        let mut program = Program::Module(module);
+        program.visit_mut_with(&mut resolver(unresolved_mark, top_level_mark, typescript));
+        if typescript {
+            program.visit_mut_with(&mut swc_ecma_transforms_typescript::strip(
+                unresolved_mark,
+                top_level_mark,
+            ));
+        }
+        if matches!(extension, Some("jsx" | "tsx")) {
+            program.visit_mut_with(&mut swc_ecma_transforms_react::react(
+                self.cm.clone(),
+                None::<SingleThreadedComments>,
+                Default::default(),
+                top_level_mark,
+                unresolved_mark,
+            ));
+        }
+        program.visit_mut_with(&mut RewriteChunks {
+            references: self.references,
+        });
+        let Program::Module(module) = program else {
+            unreachable!("modules are parsed as modules");
+        };
+
+        Ok((fm, module))
+    }
+
+    /// Source maps name the modules relative to the entry so they don't contain paths from the machine the js was built on
+    fn file_name(&self, path: &Path) -> FileName {
+        if !self.source_map {
+            return FileName::Real(path.to_path_buf());
+        }
+        let name = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        FileName::Custom(name)
+    }
+}
+
+impl Load for Loader<'_> {
+    fn load(&self, file: &FileName) -> anyhow::Result<ModuleData> {
+        let FileName::Real(path) = file else {
+            anyhow::bail!("{file} is not a local module");
+        };
+        let (fm, module) = match self.modules.get(path) {
+            Some((fm, module)) => (fm.clone(), module.clone()),
+            None => self.parse(path)?,
+        };
+
+        Ok(ModuleData {
+            fm,
+            module,
+            helpers: Helpers::new(false),
+        })
+    }
+}
+
+/// Resolves the modules in the bundle the same way their dependencies are collected
+struct Resolver;
+
+impl Resolve for Resolver {
+    fn resolve(&self, base: &FileName, specifier: &str) -> anyhow::Result<Resolution> {
+        let FileName::Real(importer) = base else {
+            anyhow::bail!("{base} is not a local module");
+        };
+        let path = resolve_js_module(importer, specifier)?.with_context(|| {
+            format!(
+                "{} imports {specifier}, which is loaded at runtime",
+                importer.display()
+            )
+        })?;
+
+        Ok(Resolution {
+            filename: FileName::Real(path),
+            slug: None,
+        })
+    }
+}
+
+/// Points `import.meta.url` in every bundled module to the url of the bundle, so urls relative to the module still resolve to the served assets
+struct ImportMetaHook;
+
+impl Hook for ImportMetaHook {
+    fn get_import_meta_props(
+        &self,
+        span: Span,
+        _: &ModuleRecord,
+    ) -> anyhow::Result<Vec<KeyValueProp>> {
+        let import_meta = Expr::MetaProp(MetaPropExpr {
+            span,
+            kind: MetaPropKind::ImportMeta,
+        });
+
+        Ok(vec![KeyValueProp {
+            key: PropName::Ident(IdentName::new("url".into(), span)),
+            value: Box::new(Expr::Member(MemberExpr {
+                span,
+                obj: Box::new(import_meta),
+                prop: MemberProp::Ident(IdentName::new("url".into(), span)),
+            })),
+        }])
+    }
+}
+
+/// Rewrites the specifiers of chunks and web workers to the location the chunks are served at
+struct RewriteChunks<'a> {
+    references: &'a [JsReference],
+}
+
+impl VisitMut for RewriteChunks<'_> {
+    fn visit_mut_expr(&mut self, expr: &mut Expr) {
+        if let Some(specifier) = chunk_specifier_mut(expr) {
+            let reference = self
+                .references
+                .iter()
+                .find(|reference| reference.specifier() == &*specifier.value);
+            if let Some(reference) = reference {
+                specifier.value = reference.location().into();
+                specifier.raw = None;
+            }
+        }
+        expr.visit_mut_children_with(self);
+    }
+}
+
+struct ResetSyntaxContext;
+
+impl VisitMut for ResetSyntaxContext {
+    fn visit_mut_syntax_context(&mut self, ctxt: &mut SyntaxContext) {
+        *ctxt = SyntaxContext::empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use manganis_common::JsType;
+
+    use super::*;
+    use crate::test_util::write_files;
+
+    /// Writes the modules into a new temporary folder and bundles `main.js`
+    fn bundle(name: &str, files: &[(&str, &str)], references: Vec<JsReference>) -> String {
+        let folder = write_files(name, files);
+        let mut options = JsOptions::new(JsType::Js);
+        options.set_bundle(true);
+        options.set_minify(false);
+        options.set_references(references);
+        options.set_externals(vec!["lodash".to_string()]);
+        bundle_js(&AssetSource::Local(folder.join("main.js")), &options, false)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn default_and_named_imports_are_hoisted_into_the_bundle() {
+        let js = bundle(
+            "bundle-imports",
+            &[
+                (
+                    "main.js",
+                    "import greet, { name as who } from './greet.js';\nconsole.log(greet(who));",
+                ),
+                (
+                    "greet.js",
+                    "export default function greet(name) { return 'hi ' + name; }\nexport const name = 'world';",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        assert!(js.contains("function greet(name)"), "{js}");
+        assert!(js.contains("'world'"), "{js}");
+        assert!(!js.contains("import"), "{js}");
+    }
+
+    #[test]
+    fn star_exports_and_namespaces_are_bundled() {
+        let js = bundle(
+            "bundle-star",
+            &[
+                (
+                    "main.js",
+                    "import * as math from './math.js';\nexport * from './math.js';\nconsole.log(math);",
+                ),
+                (
+                    "math.js",
+                    "export const add = (a, b) => a + b;\nexport const sub = (a, b) => a - b;",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        // Everything the module exports is re-exported from the bundle
+        assert!(js.contains("export { add as add }"), "{js}");
+        assert!(js.contains("export { sub as sub }"), "{js}");
+        // The namespace is an object with every export of the module
+        assert!(js.contains("add: add"), "{js}");
+        assert!(js.contains("sub: sub"), "{js}");
+        assert!(!js.contains("./math.js"), "{js}");
+    }
+
+    #[test]
+    fn cyclic_imports_are_bundled() {
+        let js = bundle(
+            "bundle-cycle",
+            &[
+                (
+                    "main.js",
+                    "import { isEven } from './even.js';\nconsole.log(isEven(10));",
+                ),
+                (
+                    "even.js",
+                    "import { isOdd } from './odd.js';\nexport function isEven(n) { return n === 0 || isOdd(n - 1); }",
+                ),
+                (
+                    "odd.js",
+                    "import { isEven } from './even.js';\nexport function isOdd(n) { return n !== 0 && isEven(n - 1); }",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        assert!(js.contains("function isEven(n)"), "{js}");
+        assert!(js.contains("function isOdd(n)"), "{js}");
+        assert!(!js.contains("import"), "{js}");
+    }
+
+    #[test]
+    fn typescript_modules_are_compiled_before_they_are_bundled() {
+        let js = bundle(
+            "bundle-typescript",
+            &[
+                (
+                    "main.js",
+                    "import { double } from './double';\nconsole.log(double(2));",
+                ),
+                (
+                    "double.ts",
+                    "import type { Options } from './options';\ninterface Input { value: number }\nexport const double = (x: number): number => x * 2;",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        assert!(js.contains("x * 2"), "{js}");
+        // Type only imports don't need to exist
+        assert!(!js.contains("./options"), "{js}");
+        assert!(!js.contains("interface"), "{js}");
+    }
+
+    #[test]
+    fn modules_that_are_loaded_at_runtime_are_kept() {
+        let js = bundle(
+            "bundle-external",
+            &[
+                (
+                    "main.js",
+                    "import { html } from 'https://esm.sh/lit';\nimport { log } from './log.js';\nlog(html);",
+                ),
+                (
+                    "log.js",
+                    "import { send } from '/analytics.js';\nexport const log = (value) => send(value);",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        assert!(
+            js.contains("import { html } from 'https://esm.sh/lit'"),
+            "{js}"
+        );
+        assert!(js.contains("import { send } from '/analytics.js'"), "{js}");
+        assert!(!js.contains("./log.js"), "{js}");
+    }
+
+    #[test]
+    fn external_specifiers_are_loaded_at_runtime() {
+        let js = bundle(
+            "bundle-import-map",
+            &[
+                (
+                    "main.js",
+                    "import { debounce } from './debounce.js';\ndebounce();",
+                ),
+                (
+                    "debounce.js",
+                    "import { debounce } from 'lodash';\nexport { debounce };",
+                ),
+            ],
+            Vec::new(),
+        );
+
+        assert!(js.contains("import { debounce } from 'lodash'"), "{js}");
+    }
+
+    #[test]
+    fn chunks_and_workers_point_to_their_served_location() {
+        let js = bundle(
+            "bundle-chunks",
+            &[
+                (
+                    "main.js",
+                    "import { start } from './start.js';\nstart();",
+                ),
+                (
+                    "start.js",
+                    "export function start() {\n    import('./chunk.js');\n    new Worker(new URL('./worker.js', import.meta.url));\n}",
+                ),
+                ("chunk.js", "console.log('chunk');"),
+                ("worker.js", "console.log('worker');"),
+            ],
+            vec![
+                JsReference::new("./chunk.js", "/assets/chunk-1.js"),
+                JsReference::new("./worker.js", "/assets/worker-2.js"),
+            ],
+        );
+
+        assert!(js.contains(r#"import("/assets/chunk-1.js")"#), "{js}");
+        assert!(js.contains(r#"new URL("/assets/worker-2.js""#), "{js}");
+        // Modules in the bundle see the url of the bundle
+        assert!(js.contains("url: import.meta.url"), "{js}");
+        // Chunks are bundled separately
+        assert!(!js.contains("console.log('chunk')"), "{js}");
+    }
+}
@@ -1,18 +1,42 @@
 use anyhow::Context;
-use image::{DynamicImage, EncodableLayout};
-use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use image::{
+    codecs::gif::{GifDecoder, GifEncoder, Repeat},
+    AnimationDecoder, DynamicImage, EncodableLayout, Frame, ImageFormat,
+};
+use lightningcss::{
+    bundler::{Bundler, FileProvider, SourceProvider},
+    rules::CssRule,
+    stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet},
+    targets::{Browsers, Targets},
+    values::url::Url,
+    visit_types,
+    visitor::{Visit, VisitTypes, Visitor},
+};
 use manganis_common::{
-    AssetSource, CssOptions, FileAsset, FileOptions, ImageOptions, ImageType, JsOptions,
+    css_module_config, escape_html_attribute, read_stylesheet, tokenize_html, verify_js_define,
+    AssetSource, Config, CssOptions, CssReference, EsVersion, FileAsset, FileOptions, HtmlOptions,
+    HtmlReference, HtmlTag, HtmlToken, ImageMetadata, ImageOptions, ImageType, JsOptions, JsType,
     JsonOptions,
 };
+use parcel_sourcemap::SourceMap as CssSourceMap;
 use std::{
-    io::{BufWriter, Write},
-    path::Path,
+    collections::HashSet,
+    io::BufWriter,
+    path::{Path, PathBuf},
     sync::Arc,
 };
-use swc::{config::JsMinifyOptions, try_with_handler, BoolOrDataConfig};
+use swc::{
+    config::{
+        GlobalInliningPassEnvs, GlobalPassOption, IsModule, JsMinifyOptions, JscConfig,
+        OptimizerConfig, SourceMapsConfig, TransformConfig,
+    },
+    try_with_handler, BoolConfig, BoolOrDataConfig,
+};
 use swc_common::{sync::Lrc, FileName};
 use swc_common::{SourceMap, GLOBALS};
+use swc_ecma_parser::{Syntax, TsSyntax};
+
+use crate::bundle::bundle_js;
 
 pub trait Process {
     fn process(&self, source: &AssetSource, output_path: &Path) -> anyhow::Result<()>;
@@ -34,6 +58,12 @@ impl Process for FileOptions {
         match self {
             Self::Other { .. } => {
                 let bytes = source.read_to_bytes()?;
+                // Images that are not processed still shouldn't leak sensitive metadata like the location a photo was taken
+                let bytes = manganis_common::strip_metadata(&bytes)
+                    .with_context(|| {
+                        format!("Failed to strip the metadata from {source}. The file is not copied so the metadata can't leak")
+                    })?
+                    .unwrap_or(bytes);
                 std::fs::write(output_path, bytes).with_context(|| {
                     format!(
                         "Failed to write file to output location: {}",
@@ -53,6 +83,12 @@ impl Process for FileOptions {
             Self::Image(options) => {
                 options.process(source, output_path)?;
             }
+            Self::SvgSprite(options) => {
+                options.process(source, output_path)?;
+            }
+            Self::Html(options) => {
+                options.process(source, output_path)?;
+            }
             _ => todo!(),
         }
 
@@ -62,61 +98,246 @@ impl Process for FileOptions {
 
 impl Process for ImageOptions {
     fn process(&self, source: &AssetSource, output_path: &Path) -> anyhow::Result<()> {
-        let mut image = image::ImageReader::new(std::io::Cursor::new(&*source.read_to_bytes()?))
-            .with_guessed_format()?
-            .decode()?;
+        process_image(self, source, output_path)?;
+        Ok(())
+    }
+}
 
-        if let Some(size) = self.size() {
-            image = image.resize_exact(size.0, size.1, image::imageops::FilterType::Lanczos3);
-        }
+/// Processes an image asset. If the image has a minimum quality, returns the encoder quality that was picked and the quality the encoded image reached
+pub(crate) fn process_image(
+    options: &ImageOptions,
+    source: &AssetSource,
+    output_path: &Path,
+) -> anyhow::Result<Option<(u8, f64)>> {
+    let bytes = source.read_to_bytes()?;
 
-        match self.ty() {
-            ImageType::Png => {
-                compress_png(image, output_path);
+    // Sprite sheets are drawn from every image in the atlas instead of the source image
+    let mut image = match options.atlas() {
+        Some(atlas) => atlas.render()?,
+        None => {
+            // Decoding an animated gif as a single image would drop every frame except the first
+            if options.ty() == &ImageType::Gif && save_animated_gif(options, &bytes, output_path)? {
+                return Ok(None);
             }
-            ImageType::Jpg => {
-                compress_jpg(image, output_path)?;
+            options.decode(&bytes)?
+        }
+    };
+
+    if let Some(icon) = options.icon() {
+        crate::icon::save_icon(image, icon, options.size(), output_path)?;
+        return Ok(None);
+    }
+
+    // Metadata is stripped by default because it can contain sensitive information like the location a photo was taken
+    let exif = if options.keep_metadata() {
+        ImageMetadata::read(&bytes).exif()
+    } else {
+        None
+    };
+
+    // Auto images are resolved by the macro, but we still need to pick a type if the options were created some other way
+    let ty = match options.ty() {
+        ImageType::Auto => ImageType::auto_for(&image),
+        ty => *ty,
+    };
+
+    if let Some(size) = options.size() {
+        image = image.resize_exact(size.0, size.1, image::imageops::FilterType::Lanczos3);
+    }
+
+    // Only png and jpg images can be decoded again to measure their quality
+    if options.min_quality().is_some() && !matches!(ty, ImageType::Png | ImageType::Jpg) {
+        tracing::warn!(
+            "The quality of encoded images can only be checked for png and jpg images. {} will be encoded with the default quality",
+            output_path.display()
+        );
+    }
+
+    if exif.is_some() && !matches!(ty, ImageType::Png | ImageType::Jpg) {
+        tracing::warn!(
+            "Metadata can only be kept in png and jpg images. The metadata of {} will be stripped",
+            output_path.display()
+        );
+    }
+
+    let mut quality = None;
+    match ty {
+        ImageType::Png => {
+            quality = encode_lossy(options, &image, output_path, |quality| {
+                compress_png(&image, quality, exif.as_deref())
+            })?;
+        }
+        ImageType::Jpg => {
+            quality = encode_lossy(options, &image, output_path, |quality| {
+                compress_jpg(&image, quality, exif.as_deref())
+            })?;
+        }
+        ImageType::Avif => {
+            if let Err(error) = image.save(output_path) {
+                tracing::error!("Failed to save avif image: {} with path {}. You must have the avif feature enabled to use avif assets", error, output_path.display());
             }
-            ImageType::Avif => {
-                if let Err(error) = image.save(output_path) {
-                    tracing::error!("Failed to save avif image: {} with path {}. You must have the avif feature enabled to use avif assets", error, output_path.display());
-                }
+        }
+        ImageType::Webp => {
+            if let Err(err) = image.save(output_path) {
+                tracing::error!("Failed to save webp image: {}. You must have the avif feature enabled to use webp assets", err);
             }
-            ImageType::Webp => {
-                if let Err(err) = image.save(output_path) {
-                    tracing::error!("Failed to save webp image: {}. You must have the avif feature enabled to use webp assets", err);
-                }
+        }
+        ImageType::Gif => {
+            save_rgb_image(image, output_path, ImageFormat::Gif)?;
+        }
+        ImageType::Bmp => {
+            save_rgb_image(image, output_path, ImageFormat::Bmp)?;
+        }
+        ImageType::Tiff => {
+            save_rgb_image(image, output_path, ImageFormat::Tiff)?;
+        }
+        ImageType::Ico => {
+            // Ico images can be at most 256x256
+            if image.width() > MAX_ICO_SIZE || image.height() > MAX_ICO_SIZE {
+                tracing::warn!(
+                    "Ico images can be at most {MAX_ICO_SIZE}x{MAX_ICO_SIZE}. {} will be scaled down",
+                    output_path.display()
+                );
+                image = image.resize(
+                    MAX_ICO_SIZE,
+                    MAX_ICO_SIZE,
+                    image::imageops::FilterType::Lanczos3,
+                );
             }
+            // Png images embedded in an ico must be rgba
+            let image = DynamicImage::ImageRgba8(image.into_rgba8());
+            save_rgb_image(image, output_path, ImageFormat::Ico)?;
+        }
+        ImageType::Qoi => {
+            save_rgb_image(image, output_path, ImageFormat::Qoi)?;
         }
+        ImageType::Auto => unreachable!("auto images always resolve to a concrete image type"),
+    }
 
-        Ok(())
+    Ok(quality)
+}
+
+/// Saves an image in a format that may not support grayscale or high bit depth images by converting it to 8 bit rgb(a) first
+fn save_rgb_image(
+    image: DynamicImage,
+    output_path: &Path,
+    format: ImageFormat,
+) -> anyhow::Result<()> {
+    let image = match image {
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => image,
+        image if image.color().has_alpha() => DynamicImage::ImageRgba8(image.into_rgba8()),
+        image => DynamicImage::ImageRgb8(image.into_rgb8()),
+    };
+    image.save_with_format(output_path, format)?;
+    Ok(())
+}
+
+/// The largest width or height of an ico image
+const MAX_ICO_SIZE: u32 = 256;
+
+/// Re-encodes every frame of an animated gif with the transforms and size of the image options. Returns `false` without writing anything if the source is not an animated gif
+fn save_animated_gif(
+    options: &ImageOptions,
+    bytes: &[u8],
+    output_path: &Path,
+) -> anyhow::Result<bool> {
+    if image::guess_format(bytes).ok() != Some(ImageFormat::Gif) {
+        return Ok(false);
     }
+    let frames = GifDecoder::new(std::io::Cursor::new(bytes))?
+        .into_frames()
+        .collect_frames()?;
+    if frames.len() <= 1 {
+        return Ok(false);
+    }
+
+    let file = std::fs::File::create(output_path)?;
+    let mut encoder = GifEncoder::new(BufWriter::new(file));
+    encoder.set_repeat(Repeat::Infinite)?;
+    for frame in frames {
+        let delay = frame.delay();
+        // The decoder composites each frame onto the full canvas, so each frame can be transformed like a normal image
+        let mut image = options.apply_transforms(DynamicImage::ImageRgba8(frame.into_buffer()))?;
+        if let Some((width, height)) = options.size() {
+            image = image.resize_exact(width, height, image::imageops::FilterType::Lanczos3);
+        }
+        encoder.encode_frame(Frame::from_parts(image.into_rgba8(), 0, 0, delay))?;
+    }
+
+    Ok(true)
+}
+
+/// Encodes a lossy image. If the options have a minimum quality, the lowest encoder quality that meets it is used and the encoder quality and the quality it reached are returned. Otherwise the default quality of the encoder is used
+fn encode_lossy(
+    options: &ImageOptions,
+    image: &DynamicImage,
+    output_path: &Path,
+    encode: impl Fn(Option<u8>) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Option<(u8, f64)>> {
+    let (encoded, quality) = match options.min_quality() {
+        Some(min_quality) => {
+            let (encoded, quality, score) =
+                crate::quality::encode_with_min_quality(image, min_quality, |quality| {
+                    encode(Some(quality))
+                })?;
+            if score < min_quality as f64 {
+                tracing::warn!(
+                    "{} only reached a quality of {score:.4} at the highest encoder quality, which is below the minimum quality of {min_quality}",
+                    output_path.display()
+                );
+            }
+            (encoded, Some((quality, score)))
+        }
+        None => (encode(None)?, None),
+    };
+
+    std::fs::write(output_path, encoded).with_context(|| {
+        format!(
+            "Failed to write image to output location: {}",
+            output_path.display()
+        )
+    })?;
+
+    Ok(quality)
 }
 
-fn compress_jpg(image: DynamicImage, output_location: &Path) -> anyhow::Result<()> {
+fn compress_jpg(
+    image: &DynamicImage,
+    quality: Option<u8>,
+    exif: Option<&[u8]>,
+) -> anyhow::Result<Vec<u8>> {
     let mut comp = mozjpeg::Compress::new(mozjpeg::ColorSpace::JCS_EXT_RGBX);
     let width = image.width() as usize;
     let height = image.height() as usize;
 
     comp.set_size(width, height);
+    if let Some(quality) = quality {
+        comp.set_quality(quality as f32);
+    }
     let mut comp = comp.start_compress(Vec::new())?; // any io::Write will work
 
-    comp.write_scanlines(image.to_rgba8().as_bytes())?;
+    if let Some(exif) = exif {
+        comp.write_marker(mozjpeg::Marker::APP(1), &[b"Exif\0\0", exif].concat());
+    }
 
-    let jpeg_bytes = comp.finish()?;
+    comp.write_scanlines(image.to_rgba8().as_bytes())?;
 
-    let file = std::fs::File::create(output_location)?;
-    let w = &mut BufWriter::new(file);
-    w.write_all(&jpeg_bytes)?;
-    Ok(())
+    Ok(comp.finish()?)
 }
 
-fn compress_png(image: DynamicImage, output_location: &Path) {
+/// The highest quality imagequant targets when no quality is set
+const DEFAULT_PNG_QUALITY: u8 = 99;
+
+fn compress_png(
+    image: &DynamicImage,
+    quality: Option<u8>,
+    exif: Option<&[u8]>,
+) -> anyhow::Result<Vec<u8>> {
     // Image loading/saving is outside scope of this library
     let width = image.width() as usize;
     let height = image.height() as usize;
     let bitmap: Vec<_> = image
-        .into_rgba8()
+        .to_rgba8()
         .pixels()
         .map(|px| imagequant::RGBA::new(px[0], px[1], px[2], px[3]))
         .collect();
@@ -124,7 +345,8 @@ fn compress_png(image: DynamicImage, output_location: &Path) {
     // Configure the library
     let mut liq = imagequant::new();
     liq.set_speed(5).unwrap();
-    liq.set_quality(0, 99).unwrap();
+    liq.set_quality(0, quality.unwrap_or(DEFAULT_PNG_QUALITY))
+        .unwrap();
 
     // Describe the bitmap
     let mut img = liq.new_image(&bitmap[..], width, height, 0.0).unwrap();
@@ -137,10 +359,8 @@ fn compress_png(image: DynamicImage, output_location: &Path) {
 
     let (palette, pixels) = res.remapped(&mut img).unwrap();
 
-    let file = std::fs::File::create(output_location).unwrap();
-    let w = &mut BufWriter::new(file);
-
-    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width as u32, height as u32);
     encoder.set_color(png::ColorType::Rgba);
     let mut flattened_palette = Vec::new();
     let mut alpha_palette = Vec::new();
@@ -156,51 +376,341 @@ fn compress_png(image: DynamicImage, output_location: &Path) {
     encoder.set_color(png::ColorType::Indexed);
     encoder.set_compression(png::Compression::Best);
     let mut writer = encoder.write_header().unwrap();
+    if let Some(exif) = exif {
+        writer
+            .write_chunk(png::chunk::ChunkType(*b"eXIf"), exif)
+            .unwrap();
+    }
     writer.write_image_data(&pixels).unwrap();
     writer.finish().unwrap();
+
+    Ok(bytes)
 }
 
 impl Process for CssOptions {
     fn process(&self, source: &AssetSource, output_path: &Path) -> anyhow::Result<()> {
-        let css = source.read_to_string()?;
+        process_css(self, source, output_path, None)
+    }
+}
 
-        let css = if self.minify() { minify_css(&css) } else { css };
+/// Processes a css asset. If the css is purged, style rules that use classes that are not in `used_classes` or the safelist are removed
+pub(crate) fn process_css(
+    options: &CssOptions,
+    source: &AssetSource,
+    output_path: &Path,
+    used_classes: Option<&HashSet<&str>>,
+) -> anyhow::Result<()> {
+    let targets = css_targets(options.targets())?;
 
-        std::fs::write(output_path, css).with_context(|| {
-            format!(
-                "Failed to write css to output location: {}",
-                output_path.display()
-            )
-        })?;
+    let purge = match (options.purge(), used_classes) {
+        (true, Some(used_classes)) => {
+            let mut used_classes = used_classes.clone();
+            used_classes.extend(options.safelist().iter().map(String::as_str));
+            Some(used_classes)
+        }
+        (true, None) => {
+            tracing::warn!("{source} can only be purged when it is processed with the other assets in the manifest. The css will not be purged");
+            None
+        }
+        (false, _) => None,
+    };
 
-        Ok(())
+    let source_map = options.source_map() || Config::current().source_maps();
+    let (mut css, map) = if options.bundle() {
+        let AssetSource::Local(path) = source else {
+            anyhow::bail!("Only local css can be bundled");
+        };
+        bundle_css(path, options, targets, purge.as_ref(), source_map)?
+    } else {
+        let (css, _) = read_stylesheet(source, options.ty())?;
+        if options.minify()
+            || targets.browsers.is_some()
+            || options.module_scope().is_some()
+            || !options.references().is_empty()
+            || purge.is_some()
+        {
+            let filename = match source {
+                AssetSource::Local(path) => path.display().to_string(),
+                AssetSource::Remote(url) => url.to_string(),
+            };
+            transform_css(
+                &css,
+                &filename,
+                options,
+                targets,
+                purge.as_ref(),
+                source_map,
+            )?
+        } else {
+            (css, None)
+        }
+    };
+
+    if let Some(map) = map {
+        if let Some(url) = write_source_map(&map, output_path)? {
+            css.push_str(&format!("\n/*# sourceMappingURL={url} */\n"));
+        }
     }
+
+    std::fs::write(output_path, css).with_context(|| {
+        format!(
+            "Failed to write css to output location: {}",
+            output_path.display()
+        )
+    })?;
+
+    Ok(())
 }
 
 pub(crate) fn minify_css(css: &str) -> String {
-    let mut stylesheet = StyleSheet::parse(css, ParserOptions::default()).unwrap();
-    stylesheet.minify(MinifyOptions::default()).unwrap();
+    let targets = css_targets(None).unwrap_or_else(|err| {
+        tracing::error!("Failed to read the css targets from the config: {err}");
+        Targets::default()
+    });
+    transform_css(css, "", &CssOptions::new(), targets, None, false)
+        .unwrap()
+        .0
+}
+
+/// Resolves the browserslist query of a css asset, or the query in the config if the asset doesn't have one
+fn css_targets(query: Option<&str>) -> anyhow::Result<Targets> {
+    let config = Config::current();
+    let Some(query) = query.or(config.css_targets()) else {
+        return Ok(Targets::default());
+    };
+    let browsers = Browsers::from_browserslist([query])
+        .with_context(|| format!("Invalid browserslist query for css targets: {query}"))?;
+    Ok(Targets::from(browsers))
+}
+
+/// Adds vendor prefixes and lowers modern syntax like nesting for the targets and rewrites the references to other files. The css is minified if the options enable it. Returns the css and the source map for it if `source_map` is true
+fn transform_css(
+    css: &str,
+    filename: &str,
+    options: &CssOptions,
+    targets: Targets,
+    purge: Option<&HashSet<&str>>,
+    source_map: bool,
+) -> anyhow::Result<(String, Option<String>)> {
+    let stylesheet = StyleSheet::parse(
+        css,
+        ParserOptions {
+            filename: filename.to_string(),
+            ..css_parser_options(options)
+        },
+    )
+    .map_err(|err| anyhow::anyhow!("Failed to parse css: {err}"))?;
+    let source_map = match source_map {
+        true => Some(css_source_map(&stylesheet.sources, &[css])?),
+        false => None,
+    };
+    print_css(stylesheet, options, targets, purge, source_map)
+}
+
+/// Inlines every stylesheet the css imports into one stylesheet. Imports with media queries, supports conditions and layers are wrapped in the matching rules
+fn bundle_css(
+    path: &Path,
+    options: &CssOptions,
+    targets: Targets,
+    purge: Option<&HashSet<&str>>,
+    source_map: bool,
+) -> anyhow::Result<(String, Option<String>)> {
+    let (css, _) = read_stylesheet(&AssetSource::Local(path.to_path_buf()), options.ty())?;
+    let fs = StylesheetProvider {
+        root: path,
+        css,
+        files: FileProvider::new(),
+    };
+    let mut bundler = Bundler::new(&fs, None, css_parser_options(options));
+    let stylesheet = bundler
+        .bundle(path)
+        .map_err(|err| anyhow::anyhow!("Failed to bundle css: {err}"))?;
+    let source_map = match source_map {
+        true => {
+            let contents = stylesheet
+                .sources
+                .iter()
+                .map(|source| fs.read(Path::new(source)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(css_source_map(&stylesheet.sources, &contents)?)
+        }
+        false => None,
+    };
+    print_css(stylesheet, options, targets, purge, source_map)
+}
+
+/// Provides the stylesheets to the bundler. The root stylesheet is read with [`read_stylesheet`] so Sass is compiled before the imports are bundled
+struct StylesheetProvider<'a> {
+    root: &'a Path,
+    css: String,
+    files: FileProvider,
+}
+
+impl SourceProvider for StylesheetProvider<'_> {
+    type Error = std::io::Error;
+
+    fn read<'a>(&'a self, file: &Path) -> Result<&'a str, Self::Error> {
+        if file == self.root {
+            Ok(&self.css)
+        } else {
+            self.files.read(file)
+        }
+    }
+
+    fn resolve(&self, specifier: &str, originating_file: &Path) -> Result<PathBuf, Self::Error> {
+        self.files.resolve(specifier, originating_file)
+    }
+}
+
+fn css_parser_options(options: &CssOptions) -> ParserOptions<'_, '_> {
+    ParserOptions {
+        css_modules: options.module_scope().map(css_module_config),
+        ..Default::default()
+    }
+}
+
+/// Creates a source map with the sources of a stylesheet and their contents. The sources are relative to the directory of the first stylesheet so the map doesn't contain paths from the machine the css was built on
+fn css_source_map(sources: &[String], contents: &[&str]) -> anyhow::Result<CssSourceMap> {
+    let root = sources
+        .first()
+        .and_then(|source| Path::new(source).parent())
+        .map_or_else(
+            || "/".to_string(),
+            |root| root.to_string_lossy().into_owned(),
+        );
+    let mut source_map = CssSourceMap::new(&root);
+    for (source, content) in sources.iter().zip(contents) {
+        let index = source_map.add_source(source);
+        source_map.set_source_content(index as usize, content)?;
+    }
+    Ok(source_map)
+}
+
+fn print_css(
+    mut stylesheet: StyleSheet,
+    options: &CssOptions,
+    targets: Targets,
+    purge: Option<&HashSet<&str>>,
+    mut source_map: Option<CssSourceMap>,
+) -> anyhow::Result<(String, Option<String>)> {
+    let references = options.references();
+    if let Some(used_classes) = purge {
+        crate::purge::purge_css(&mut stylesheet, used_classes);
+    }
+    // The references are rewritten in the stylesheet instead of the printed css so the source map stays correct
+    if !references.is_empty() {
+        stylesheet
+            .visit(&mut RewriteReferences { references })
+            .unwrap_or_else(|never| match never {});
+    }
+    stylesheet.minify(MinifyOptions {
+        targets,
+        ..Default::default()
+    })?;
     let printer = PrinterOptions {
-        minify: true,
+        minify: options.minify(),
+        targets,
+        source_map: source_map.as_mut(),
         ..Default::default()
     };
-    let res = stylesheet.to_css(printer).unwrap();
-    res.code
+    let code = stylesheet.to_css(printer)?.code;
+    let source_map = source_map
+        .map(|mut source_map| source_map.to_json(None))
+        .transpose()?;
+
+    Ok((code, source_map))
 }
 
-pub(crate) fn minify_js(source: &AssetSource) -> anyhow::Result<String> {
-    let cm = Arc::<SourceMap>::default();
+/// Rewrites the urls and imports in a stylesheet to the locations the files they reference are served at
+struct RewriteReferences<'a> {
+    references: &'a [CssReference],
+}
+
+impl RewriteReferences<'_> {
+    fn location(&self, url: &str) -> Option<&str> {
+        self.references
+            .iter()
+            .find(|reference| reference.url() == url)
+            .map(|reference| reference.location())
+    }
+}
+
+impl<'i> Visitor<'i> for RewriteReferences<'_> {
+    type Error = std::convert::Infallible;
+
+    fn visit_types(&self) -> VisitTypes {
+        visit_types!(URLS | RULES)
+    }
+
+    fn visit_url(&mut self, url: &mut Url<'i>) -> Result<(), Self::Error> {
+        if let Some(location) = self.location(&url.url) {
+            url.url = location.to_string().into();
+        }
+        Ok(())
+    }
+
+    fn visit_rule(&mut self, rule: &mut CssRule<'i>) -> Result<(), Self::Error> {
+        if let CssRule::Import(import) = rule {
+            if let Some(location) = self.location(&import.url) {
+                import.url = location.to_string().into();
+            }
+        }
+        rule.visit_children(self)
+    }
+}
 
+/// Writes the source map of an asset and returns the url the asset should link to it with. The map is written next to the asset, so a link with the name of the map points at the location the map is served at. If the config has a source map directory, the map is written there instead and the asset doesn't link to it
+fn write_source_map(source_map: &str, output_path: &Path) -> anyhow::Result<Option<String>> {
+    let Some(file_name) = output_path.file_name() else {
+        return Ok(None);
+    };
+    let map_name = format!("{}.map", file_name.to_string_lossy());
+    let config = Config::current();
+    let (map_path, url) = match config.source_map_dir() {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)?;
+            (dir.join(&map_name), None)
+        }
+        None => (output_path.with_file_name(&map_name), Some(map_name)),
+    };
+    std::fs::write(&map_path, source_map).with_context(|| {
+        format!(
+            "Failed to write source map to output location: {}",
+            map_path.display()
+        )
+    })?;
+    Ok(url)
+}
+
+/// Minifies js. Returns the js and the source map for it if `source_map` is true
+pub(crate) fn minify_js(
+    source: &AssetSource,
+    is_module: IsModule,
+    source_map: bool,
+) -> anyhow::Result<(String, Option<String>)> {
     let js = source.read_to_string()?;
+    Ok(minify_js_code(
+        js,
+        js_file_name(source, source_map),
+        is_module,
+        source_map,
+    ))
+}
+
+/// Minifies js that has already been read. If the js can't be minified, it is returned as it is
+fn minify_js_code(
+    js: String,
+    file_name: Lrc<FileName>,
+    is_module: IsModule,
+    source_map: bool,
+) -> (String, Option<String>) {
+    let cm = Arc::<SourceMap>::default();
+
     let c = swc::Compiler::new(cm.clone());
     let output = GLOBALS
         .set(&Default::default(), || {
             try_with_handler(cm.clone(), Default::default(), |handler| {
-                let filename = Lrc::new(match source {
-                    manganis_common::AssetSource::Local(path) => FileName::Real(path.clone()),
-                    manganis_common::AssetSource::Remote(url) => FileName::Url(url.clone()),
-                });
-                let fm = cm.new_source_file(filename, js.to_string());
+                let fm = cm.new_source_file(file_name, js.to_string());
 
                 c.minify(
                     fm,
@@ -208,30 +718,177 @@ pub(crate) fn minify_js(source: &AssetSource) -> anyhow::Result<String> {
                     &JsMinifyOptions {
                         compress: BoolOrDataConfig::from_bool(true),
                         mangle: BoolOrDataConfig::from_bool(true),
+                        module: is_module,
+                        source_map: BoolOrDataConfig::from_bool(source_map),
+                        inline_sources_content: true,
+                        emit_source_map_columns: true,
                         ..Default::default()
                     },
                 )
                 .context("failed to minify javascript")
             })
         })
-        .map(|output| output.code);
+        .map(|output| (output.code, output.map));
 
     match output {
-        Ok(output) => Ok(output),
+        Ok(output) => output,
         Err(err) => {
             tracing::error!("Failed to minify javascript: {}", err);
-            Ok(js)
+            (js, None)
         }
     }
 }
 
+/// Compiles js or TypeScript to js for the target version of the standard in the options. TypeScript types are stripped, JSX is transformed into js and defined globals are replaced. The js is minified if the options enable it. Returns the js and the source map for it if `source_map` is true
+fn compile_js(
+    source: &AssetSource,
+    syntax: Syntax,
+    is_module: IsModule,
+    options: &JsOptions,
+    source_map: bool,
+) -> anyhow::Result<(String, Option<String>)> {
+    let compile_options = compile_options(syntax, is_module, options, source_map)?;
+    let cm = Arc::<SourceMap>::default();
+
+    let js = source.read_to_string()?;
+    let c = swc::Compiler::new(cm.clone());
+    let output = GLOBALS.set(&Default::default(), || {
+        try_with_handler(cm.clone(), Default::default(), |handler| {
+            let fm = cm.new_source_file(js_file_name(source, source_map), js);
+
+            c.process_js_file(fm, handler, &compile_options)
+                .context("failed to compile javascript")
+        })
+    })?;
+
+    Ok((output.code, output.map))
+}
+
+/// The options to compile js with. Syntax that is newer than the target is compiled away and defined globals are replaced before the js is minified, so the compressor can remove branches that become dead. The rest of the js is kept as it is written
+pub(crate) fn compile_options(
+    syntax: Syntax,
+    is_module: IsModule,
+    options: &JsOptions,
+    source_map: bool,
+) -> anyhow::Result<swc::config::Options> {
+    let minify = options.minify();
+    // swc panics if a define is not a valid expression, so check them before they are handed to it
+    for (name, value) in options.defines() {
+        verify_js_define(name, value)?;
+    }
+    let transform = (!options.defines().is_empty()).then(|| TransformConfig {
+        optimizer: Some(OptimizerConfig {
+            globals: Some(GlobalPassOption {
+                vars: options
+                    .defines()
+                    .iter()
+                    .map(|(name, value)| (name.as_str().into(), value.as_str().into()))
+                    .collect(),
+                // Only the defines are inlined, not the environment of the machine the js is built on
+                envs: GlobalInliningPassEnvs::Map(Default::default()),
+                typeofs: Default::default(),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+
+    Ok(swc::config::Options {
+        config: swc::config::Config {
+            jsc: JscConfig {
+                syntax: Some(syntax),
+                target: Some(swc_es_version(options.target())),
+                transform: transform.into(),
+                minify: minify.then(|| JsMinifyOptions {
+                    compress: BoolOrDataConfig::from_bool(true),
+                    mangle: BoolOrDataConfig::from_bool(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            minify: BoolConfig::new(Some(minify)),
+            is_module: Some(is_module),
+            source_maps: source_map.then_some(SourceMapsConfig::Bool(true)),
+            ..Default::default()
+        },
+        swcrc: false,
+        ..Default::default()
+    })
+}
+
+fn swc_es_version(version: EsVersion) -> swc_ecma_ast::EsVersion {
+    match version {
+        EsVersion::Es5 => swc_ecma_ast::EsVersion::Es5,
+        EsVersion::Es2015 => swc_ecma_ast::EsVersion::Es2015,
+        EsVersion::Es2016 => swc_ecma_ast::EsVersion::Es2016,
+        EsVersion::Es2017 => swc_ecma_ast::EsVersion::Es2017,
+        EsVersion::Es2018 => swc_ecma_ast::EsVersion::Es2018,
+        EsVersion::Es2019 => swc_ecma_ast::EsVersion::Es2019,
+        EsVersion::Es2020 => swc_ecma_ast::EsVersion::Es2020,
+        EsVersion::Es2021 => swc_ecma_ast::EsVersion::Es2021,
+        EsVersion::Es2022 => swc_ecma_ast::EsVersion::Es2022,
+        EsVersion::EsNext => swc_ecma_ast::EsVersion::EsNext,
+    }
+}
+
+fn js_file_name(source: &AssetSource, source_map: bool) -> Lrc<FileName> {
+    Lrc::new(match source {
+        // Source maps only name the file so they don't contain paths from the machine the js was built on
+        AssetSource::Local(path) if source_map => FileName::Custom(
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        ),
+        AssetSource::Local(path) => FileName::Real(path.clone()),
+        AssetSource::Remote(url) => FileName::Url(url.clone()),
+    })
+}
+
 impl Process for JsOptions {
     fn process(&self, source: &AssetSource, output_path: &Path) -> anyhow::Result<()> {
-        let js = if self.minify() {
-            minify_js(source)?
+        let source_map = self.source_map() || Config::current().source_maps();
+        // Js that isn't marked as a module may still be one, so let swc detect it
+        let is_module = if self.module() {
+            IsModule::Bool(true)
         } else {
-            source.read_to_string()?
+            IsModule::Unknown
         };
+        let (mut js, map) = match self.ty() {
+            _ if self.bundle() => bundle_js(source, self, source_map)
+                .with_context(|| format!("Failed to bundle {source}"))?,
+            JsType::Ts | JsType::Tsx => compile_js(
+                source,
+                Syntax::Typescript(TsSyntax {
+                    tsx: self.ty() == JsType::Tsx,
+                    ..Default::default()
+                }),
+                is_module,
+                self,
+                source_map,
+            )
+            .with_context(|| format!("Failed to compile {source}"))?,
+            // Js that targets an older version of the standard or replaces globals needs to be compiled, not just minified
+            JsType::Js | JsType::Mjs
+                if self.target() != EsVersion::EsNext || !self.defines().is_empty() =>
+            {
+                compile_js(
+                    source,
+                    Syntax::Es(Default::default()),
+                    is_module,
+                    self,
+                    source_map,
+                )
+                .with_context(|| format!("Failed to compile {source}"))?
+            }
+            JsType::Js | JsType::Mjs if self.minify() => minify_js(source, is_module, source_map)?,
+            JsType::Js | JsType::Mjs => (source.read_to_string()?, None),
+        };
+
+        if let Some(map) = map {
+            if let Some(url) = write_source_map(&map, output_path)? {
+                js.push_str(&format!("\n//# sourceMappingURL={url}\n"));
+            }
+        }
 
         std::fs::write(output_path, js).with_context(|| {
             format!(
@@ -273,3 +930,473 @@ impl Process for JsonOptions {
         Ok(())
     }
 }
+
+impl Process for HtmlOptions {
+    fn process(&self, source: &AssetSource, output_path: &Path) -> anyhow::Result<()> {
+        let html = source.read_to_string()?;
+        let html = transform_html(&html, self, source);
+
+        std::fs::write(output_path, html).with_context(|| {
+            format!(
+                "Failed to write html to output location: {}",
+                output_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Rewrites the references in html to the locations the files are served at. If the options enable it, comments and whitespace are removed and inline stylesheets and scripts are minified
+fn transform_html(html: &str, options: &HtmlOptions, source: &AssetSource) -> String {
+    let minify = options.minify();
+    let mut output = String::with_capacity(html.len());
+    // Whitespace inside `<pre>` is rendered as it is written
+    let mut preformatted = 0usize;
+    // Whether the script that is being read is inline js, and if it is a module
+    let mut script = None;
+
+    for token in tokenize_html(html) {
+        match token {
+            HtmlToken::StartTag(tag) => {
+                match tag.name() {
+                    "pre" if !tag.self_closing() => preformatted += 1,
+                    "script" => script = inline_script_type(&tag),
+                    _ => {}
+                }
+                write_start_tag(&mut output, &tag, options.references());
+            }
+            HtmlToken::EndTag { name, source } => {
+                if name == "pre" {
+                    preformatted = preformatted.saturating_sub(1);
+                }
+                output.push_str(source);
+            }
+            HtmlToken::Text(text) if minify && preformatted == 0 => {
+                collapse_whitespace(&mut output, text);
+            }
+            HtmlToken::Comment(comment) if minify && !is_conditional_comment(comment) => {}
+            HtmlToken::RawText { element, content } if minify && element == "style" => {
+                output.push_str(&minify_inline_css(content));
+            }
+            HtmlToken::RawText { element, content } if minify && element == "script" => {
+                match script.take() {
+                    Some(is_module) => {
+                        output.push_str(&minify_inline_js(content, is_module, source));
+                    }
+                    None => output.push_str(content),
+                }
+            }
+            token => output.push_str(token.source()),
+        }
+    }
+
+    output
+}
+
+/// Writes a start tag with the references in its attributes replaced by the locations the files are served at
+fn write_start_tag(output: &mut String, tag: &HtmlTag, references: &[HtmlReference]) {
+    let source = tag.source();
+    let mut written = 0;
+    for attribute in tag.attributes() {
+        if !attribute.is_reference() {
+            continue;
+        }
+        let Some(reference) = attribute
+            .value()
+            .and_then(|url| references.iter().find(|reference| reference.url() == url))
+        else {
+            continue;
+        };
+        let span = attribute.value_span();
+        output.push_str(&source[written..span.start]);
+        output.push('"');
+        output.push_str(&escape_html_attribute(reference.location()));
+        output.push('"');
+        written = span.end;
+    }
+    output.push_str(&source[written..]);
+}
+
+/// Returns whether the content of a script element is a module if it is inline js, or None if the script is loaded from a file or is not js
+fn inline_script_type(tag: &HtmlTag) -> Option<bool> {
+    if tag.attribute("src").is_some() {
+        return None;
+    }
+    let ty = tag
+        .attribute("type")
+        .and_then(|ty| ty.value())
+        .unwrap_or_default();
+    match ty.trim().to_ascii_lowercase().as_str() {
+        "" | "text/javascript" | "application/javascript" => Some(false),
+        "module" => Some(true),
+        _ => None,
+    }
+}
+
+/// Replaces every run of whitespace in text with a single space
+fn collapse_whitespace(output: &mut String, text: &str) {
+    let mut whitespace = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            whitespace = true;
+            continue;
+        }
+        if whitespace {
+            push_space(output);
+        }
+        whitespace = false;
+        output.push(c);
+    }
+    if whitespace {
+        push_space(output);
+    }
+}
+
+fn push_space(output: &mut String) {
+    // Whitespace at the start of the document is never rendered, and text around a removed comment shouldn't leave two spaces behind
+    if !output.is_empty() && !output.ends_with(|c: char| c.is_ascii_whitespace()) {
+        output.push(' ');
+    }
+}
+
+/// Conditional comments are read by some email clients, so they are kept
+fn is_conditional_comment(comment: &str) -> bool {
+    comment.contains("[if") || comment.contains("[endif]")
+}
+
+fn minify_inline_css(css: &str) -> String {
+    let targets = match css_targets(None) {
+        Ok(targets) => targets,
+        Err(err) => {
+            tracing::error!("Failed to read the css targets from the config: {err}");
+            Targets::default()
+        }
+    };
+    match transform_css(css, "", &CssOptions::new(), targets, None, false) {
+        Ok((minified, _)) => escape_end_tags(&minified, "style"),
+        Err(err) => {
+            tracing::error!("Failed to minify inline css: {err}");
+            css.to_string()
+        }
+    }
+}
+
+fn minify_inline_js(js: &str, is_module: bool, source: &AssetSource) -> String {
+    let file_name = Lrc::new(FileName::Custom(format!("inline script in {source}")));
+    let (minified, _) = minify_js_code(js.to_string(), file_name, IsModule::Bool(is_module), false);
+    escape_end_tags(&minified, "script")
+}
+
+/// Minifying may unescape strings like `"<\/script>"`, which would end the element early, so the end tags are escaped again
+fn escape_end_tags(content: &str, element: &str) -> String {
+    let mut escaped = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(index) = rest.find("</") {
+        escaped.push_str(&rest[..index]);
+        rest = &rest[index + 2..];
+        let ends_element = rest
+            .get(..element.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(element));
+        escaped.push_str(if ends_element { "<\\/" } else { "</" });
+    }
+    escaped.push_str(rest);
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    fn transform(css: &str, options: &CssOptions) -> String {
+        transform_css(css, "style.css", options, Targets::default(), None, false)
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn css_references_are_rewritten_to_their_served_location() {
+        let mut options = CssOptions::new();
+        options.set_references(vec![
+            CssReference::new("theme.css", "/assets/theme-1.css"),
+            CssReference::new("logo.png#icon", "/assets/logo-2.png#icon"),
+        ]);
+        let css = transform(
+            r#"@import "theme.css";
+            .a { background: url(logo.png#icon) }
+            .b { background: url(other.png) }"#,
+            &options,
+        );
+
+        assert!(css.contains(r#"@import "/assets/theme-1.css""#), "{css}");
+        assert!(css.contains("url(/assets/logo-2.png#icon)"), "{css}");
+        // Urls without a reference are left as they are
+        assert!(css.contains("url(other.png)"), "{css}");
+    }
+
+    #[test]
+    fn css_references_that_need_quotes_are_escaped() {
+        let mut options = CssOptions::new();
+        options.set_references(vec![CssReference::new(
+            "logo.png",
+            "/assets/my logo (1).png",
+        )]);
+        let css = transform(".a { background: url(logo.png) }", &options);
+
+        assert!(css.contains(r#"url("/assets/my logo (1).png")"#), "{css}");
+    }
+
+    fn parse_map(map: Option<String>) -> serde_json::Value {
+        serde_json::from_str(&map.expect("a source map should be generated")).unwrap()
+    }
+
+    #[test]
+    fn css_source_maps_contain_the_original_stylesheet() {
+        let css = ".button {\n  color: red;\n}\n";
+        let (code, map) = transform_css(
+            css,
+            "/project/assets/style.css",
+            &CssOptions::new(),
+            Targets::default(),
+            None,
+            true,
+        )
+        .unwrap();
+        let map = parse_map(map);
+
+        assert_eq!(code, ".button{color:red}");
+        // The sources are relative to the stylesheet so the map doesn't contain paths from the build machine
+        assert_eq!(map["sources"], serde_json::json!(["style.css"]));
+        assert_eq!(map["sourcesContent"], serde_json::json!([css]));
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+    }
+
+    #[test]
+    fn css_source_map_sources_are_relative_to_the_first_stylesheet() {
+        let sources = [
+            "/project/assets/style.css".to_string(),
+            "/project/assets/theme/colors.css".to_string(),
+        ];
+        let mut map = css_source_map(&sources, &["a {}", "b {}"]).unwrap();
+        let map: serde_json::Value = serde_json::from_str(&map.to_json(None).unwrap()).unwrap();
+
+        assert_eq!(
+            map["sources"],
+            serde_json::json!(["style.css", "theme/colors.css"])
+        );
+    }
+
+    #[test]
+    fn minified_js_source_maps_only_name_the_file() {
+        let js =
+            "function add(first, second) {\n  return first + second;\n}\nconsole.log(add(1, 2));\n";
+        let source = AssetSource::Local(PathBuf::from("/project/assets/script.js"));
+        let (code, map) = minify_js_code(
+            js.to_string(),
+            js_file_name(&source, true),
+            IsModule::Bool(false),
+            true,
+        );
+        let map = parse_map(map);
+
+        assert!(code.len() < js.len(), "{code}");
+        assert_eq!(map["sources"], serde_json::json!(["script.js"]));
+        assert_eq!(map["sourcesContent"], serde_json::json!([js]));
+    }
+
+    #[test]
+    fn js_is_not_mapped_unless_source_maps_are_enabled() {
+        let source = AssetSource::Local(PathBuf::from("/project/assets/script.js"));
+        let (_, map) = minify_js_code(
+            "console.log(1 + 2);".to_string(),
+            js_file_name(&source, false),
+            IsModule::Bool(false),
+            false,
+        );
+        assert_eq!(map, None);
+        assert_eq!(
+            *js_file_name(&source, false),
+            FileName::Real(PathBuf::from("/project/assets/script.js"))
+        );
+    }
+
+    #[test]
+    fn compiled_typescript_maps_back_to_the_typescript() {
+        let ts = "const message: string = \"hello\";\nconsole.log(message);\n";
+        let folder = write_files("ts-map", &[("main.ts", ts)]);
+        let path = folder.join("main.ts");
+
+        let mut options = JsOptions::new(JsType::Ts);
+        options.set_minify(false);
+        let result = compile_js(
+            &AssetSource::Local(path),
+            Syntax::Typescript(Default::default()),
+            IsModule::Bool(true),
+            &options,
+            true,
+        );
+        let (code, map) = result.unwrap();
+        let map = parse_map(map);
+
+        assert!(!code.contains(": string"), "{code}");
+        assert_eq!(map["sources"], serde_json::json!(["main.ts"]));
+        assert!(!map["mappings"].as_str().unwrap().is_empty());
+    }
+
+    fn minify_html(html: &str, references: Vec<HtmlReference>) -> String {
+        let mut options = HtmlOptions::new();
+        options.set_minify(true);
+        options.set_references(references);
+        transform_html(
+            html,
+            &options,
+            &AssetSource::Local(PathBuf::from("index.html")),
+        )
+    }
+
+    #[test]
+    fn html_references_are_rewritten_and_quoted() {
+        let html = minify_html(
+            r#"<img src=logo.png alt=logo><a href='page.html?a=1&amp;b=2'>page</a><a href="other.html">other</a>"#,
+            vec![
+                HtmlReference::new("logo.png", "/assets/logo-1.png"),
+                HtmlReference::new("page.html?a=1&b=2", "/assets/page-2.html?a=1&b=2"),
+            ],
+        );
+
+        assert!(
+            html.contains(r#"<img src="/assets/logo-1.png" alt=logo>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<a href="/assets/page-2.html?a=1&amp;b=2">"#),
+            "{html}"
+        );
+        // Urls without a reference are left as they are
+        assert!(html.contains(r#"<a href="other.html">"#), "{html}");
+    }
+
+    #[test]
+    fn html_whitespace_is_collapsed_outside_of_pre() {
+        let html = minify_html(
+            "<p>\n    a    b\n</p>\n<pre>  keep\n    this  </pre>\n<p>  c </p>",
+            Vec::new(),
+        );
+
+        assert!(html.contains("<p> a b </p>"), "{html}");
+        assert!(html.contains("<pre>  keep\n    this  </pre>"), "{html}");
+        assert!(html.contains("<p> c </p>"), "{html}");
+    }
+
+    #[test]
+    fn html_comments_are_removed_except_conditional_comments() {
+        let html = minify_html(
+            "<p>a</p><!-- removed --><!--[if mso]><table></table><![endif]--><p>b</p>",
+            Vec::new(),
+        );
+
+        assert!(!html.contains("removed"), "{html}");
+        assert!(
+            html.contains("<!--[if mso]><table></table><![endif]-->"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn raw_text_elements_are_not_rewritten() {
+        let html = minify_html(
+            r#"<textarea>  <img src="logo.png">  </textarea><script type="text/template"><img src="logo.png"></script>"#,
+            vec![HtmlReference::new("logo.png", "/assets/logo-1.png")],
+        );
+
+        assert!(
+            html.contains(r#"<textarea>  <img src="logo.png">  </textarea>"#),
+            "{html}"
+        );
+        // Scripts that aren't js are left as they are
+        assert!(
+            html.contains(r#"<script type="text/template"><img src="logo.png"></script>"#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn inline_scripts_keep_end_tags_escaped() {
+        let html = minify_html(
+            r#"<script>document.write("<script><\/script>");</script><style>.a::after { content: "<\/style>" }</style>"#,
+            Vec::new(),
+        );
+
+        // The script and stylesheet still end where they ended before they were minified
+        assert_eq!(html.matches("</script>").count(), 1, "{html}");
+        assert!(html.contains(r#"<\/script>"#), "{html}");
+        assert_eq!(html.matches("</style>").count(), 1, "{html}");
+    }
+
+    /// Processes an image and returns the encoded file
+    fn encode_image(options: &ImageOptions, source: &Path) -> Vec<u8> {
+        let output = source.with_file_name("output");
+        process_image(options, &AssetSource::Local(source.to_path_buf()), &output).unwrap();
+        std::fs::read(output).unwrap()
+    }
+
+    #[test]
+    fn animated_gifs_keep_every_frame() {
+        let folder = write_files("animated-gif", &[]);
+        let source = folder.join("animation.gif");
+        let mut encoder = GifEncoder::new(std::fs::File::create(&source).unwrap());
+        for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+            let frame = image::RgbaImage::from_pixel(8, 8, image::Rgba(color));
+            encoder.encode_frame(Frame::new(frame)).unwrap();
+        }
+        drop(encoder);
+
+        let gif = encode_image(&ImageOptions::new(ImageType::Gif, Some((4, 4))), &source);
+        let frames = GifDecoder::new(std::io::Cursor::new(gif))
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert!(frames
+            .iter()
+            .all(|frame| frame.buffer().dimensions() == (4, 4)));
+        assert_eq!(frames[1].buffer().get_pixel(0, 0).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn large_icos_are_scaled_down() {
+        let folder = write_files("large-ico", &[]);
+        let source = folder.join("icon.png");
+        image::RgbaImage::new(512, 256).save(&source).unwrap();
+
+        let ico = encode_image(&ImageOptions::new(ImageType::Ico, None), &source);
+
+        assert_eq!(image::guess_format(&ico).unwrap(), ImageFormat::Ico);
+        let decoded = image::load_from_memory(&ico).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (256, 128));
+    }
+
+    #[test]
+    fn grayscale_images_are_converted_for_rgb_formats() {
+        let folder = write_files("rgb-formats", &[]);
+        let source = folder.join("gray.png");
+        image::ImageBuffer::<image::Luma<u16>, _>::from_pixel(6, 4, image::Luma([30000]))
+            .save(&source)
+            .unwrap();
+
+        for (ty, format) in [
+            (ImageType::Bmp, ImageFormat::Bmp),
+            (ImageType::Tiff, ImageFormat::Tiff),
+            (ImageType::Qoi, ImageFormat::Qoi),
+        ] {
+            let encoded = encode_image(&ImageOptions::new(ty, None), &source);
+
+            assert_eq!(image::guess_format(&encoded).unwrap(), format);
+            let decoded = image::load_from_memory(&encoded).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (6, 4), "{ty}");
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{io::BufWriter, path::Path};
+
+use image::{
+    codecs::ico::{IcoEncoder, IcoFrame},
+    imageops::FilterType,
+    DynamicImage, ExtendedColorType, ImageFormat, Rgba, RgbaImage,
+};
+use manganis_common::IconRole;
+
+/// Saves one image of an icon set
+pub(crate) fn save_icon(
+    image: DynamicImage,
+    role: &IconRole,
+    size: Option<(u32, u32)>,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    match *role {
+        IconRole::Favicon => {
+            let frames = IconRole::FAVICON_SIZES
+                .iter()
+                .map(|&size| {
+                    let icon = fit_icon(&image, size, 1.0, None);
+                    IcoFrame::as_png(icon.as_raw(), size, size, ExtendedColorType::Rgba8)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let file = std::fs::File::create(output_path)?;
+            IcoEncoder::new(BufWriter::new(file)).encode_images(&frames)?;
+        }
+        IconRole::Icon => {
+            fit_icon(&image, icon_size(size), 1.0, None)
+                .save_with_format(output_path, ImageFormat::Png)?;
+        }
+        IconRole::AppleTouch { background } => {
+            fit_icon(&image, icon_size(size), 1.0, Some(background))
+                .save_with_format(output_path, ImageFormat::Png)?;
+        }
+        IconRole::Maskable { background } => {
+            fit_icon(
+                &image,
+                icon_size(size),
+                IconRole::MASKABLE_SAFE_ZONE,
+                Some(background),
+            )
+            .save_with_format(output_path, ImageFormat::Png)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Icons are square, so only the width of the size is used
+fn icon_size(size: Option<(u32, u32)>) -> u32 {
+    const DEFAULT_ICON_SIZE: u32 = 512;
+    size.map(|(width, _)| width).unwrap_or(DEFAULT_ICON_SIZE)
+}
+
+/// Scales the image to fit inside `scale` of a square icon and centers it. Transparent pixels are filled with the background color if there is one
+fn fit_icon(image: &DynamicImage, size: u32, scale: f32, background: Option<[u8; 3]>) -> RgbaImage {
+    let content_size = ((size as f32 * scale).round() as u32).max(1);
+    let content = image
+        .resize(content_size, content_size, FilterType::Lanczos3)
+        .into_rgba8();
+
+    let fill = match background {
+        Some([r, g, b]) => Rgba([r, g, b, u8::MAX]),
+        None => Rgba([0, 0, 0, 0]),
+    };
+    let mut icon = RgbaImage::from_pixel(size, size, fill);
+    let x = size.saturating_sub(content.width()) / 2;
+    let y = size.saturating_sub(content.height()) / 2;
+    image::imageops::overlay(&mut icon, &content, x as i64, y as i64);
+
+    icon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    /// A red image twice as wide as it is tall
+    fn wide_image() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255])))
+    }
+
+    #[test]
+    fn icons_are_padded_to_a_square() {
+        let icon = fit_icon(&wide_image(), 16, 1.0, None);
+
+        assert_eq!(icon.dimensions(), (16, 16));
+        // The wide image is centered vertically, so the top and bottom are transparent
+        assert_eq!(icon.get_pixel(8, 0).0[3], 0);
+        assert_eq!(icon.get_pixel(8, 15).0[3], 0);
+        assert_eq!(icon.get_pixel(0, 8).0, [255, 0, 0, 255]);
+        assert_eq!(icon.get_pixel(15, 8).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn the_background_fills_the_padding() {
+        let icon = fit_icon(&wide_image(), 16, 1.0, Some([0, 0, 255]));
+
+        assert_eq!(icon.get_pixel(8, 0).0, [0, 0, 255, 255]);
+        assert_eq!(icon.get_pixel(8, 8).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn maskable_icons_keep_the_image_inside_the_safe_zone() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(20, 20, Rgba([255, 0, 0, 255])));
+        let icon = fit_icon(&image, 100, IconRole::MASKABLE_SAFE_ZONE, Some([0, 0, 255]));
+
+        // The image is scaled to 80x80 and centered, leaving 10 pixels of background on every side
+        assert_eq!(icon.get_pixel(9, 50).0, [0, 0, 255, 255]);
+        assert_eq!(icon.get_pixel(10, 50).0, [255, 0, 0, 255]);
+        assert_eq!(icon.get_pixel(89, 50).0, [255, 0, 0, 255]);
+        assert_eq!(icon.get_pixel(90, 50).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn icons_smaller_than_a_pixel_do_not_overflow() {
+        let icon = fit_icon(&wide_image(), 0, 1.0, None);
+
+        assert_eq!(icon.dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn favicons_contain_every_favicon_size() {
+        let folder = write_files("favicon", &[]);
+        let output = folder.join("favicon.ico");
+        save_icon(wide_image(), &IconRole::Favicon, None, &output).unwrap();
+
+        let ico = std::fs::read(&output).unwrap();
+        // The header of an ico is followed by the number of images it contains
+        assert_eq!(
+            u16::from_le_bytes([ico[4], ico[5]]) as usize,
+            IconRole::FAVICON_SIZES.len()
+        );
+        // Each image is listed with its width and height, where 0 means 256
+        let sizes: Vec<_> = ico[6..]
+            .chunks(16)
+            .take(IconRole::FAVICON_SIZES.len())
+            .map(|entry| (entry[0] as u32, entry[1] as u32))
+            .collect();
+        assert_eq!(
+            sizes,
+            IconRole::FAVICON_SIZES.map(|size| (size, size)).to_vec()
+        );
+    }
+
+    #[test]
+    fn apple_touch_icons_are_opaque_pngs() {
+        let folder = write_files("apple-touch-icon", &[]);
+        let output = folder.join("apple-touch-icon.png");
+        save_icon(
+            wide_image(),
+            &IconRole::AppleTouch {
+                background: [255, 255, 255],
+            },
+            Some((32, 32)),
+            &output,
+        )
+        .unwrap();
+
+        let icon = image::open(&output).unwrap().into_rgba8();
+        assert_eq!(icon.dimensions(), (32, 32));
+        assert!(icon.pixels().all(|pixel| pixel.0[3] == u8::MAX));
+    }
+}
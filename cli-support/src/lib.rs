@@ -1,12 +1,19 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 
+mod bundle;
 #[allow(hidden_glob_reexports)]
 mod file;
 mod folder;
+mod icon;
 mod linker_intercept;
 mod manifest;
 mod marker;
+mod purge;
+mod quality;
+mod sprite;
+#[cfg(test)]
+mod test_util;
 
 pub use file::process_file;
 pub use folder::process_folder;
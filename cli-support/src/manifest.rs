@@ -1,9 +1,20 @@
+use anyhow::Context;
+use lightningcss::stylesheet::{ParserOptions, PrinterOptions, StyleSheet};
 pub use railwind::warning::Warning as TailwindWarning;
-use std::path::PathBuf;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
-use manganis_common::{linker, AssetManifest, AssetType};
+use manganis_common::{
+    linker, AssetManifest, AssetSource, AssetType, CssOptions, FileOptions, JsOptions, JsType,
+    SnippetAsset, SnippetType,
+};
 
-use crate::{file::process_file, process_folder};
+use crate::{
+    file::{process_css, process_file, process_image, Process},
+    process_folder,
+};
 
 use object::{File, Object, ObjectSection};
 use std::fs;
@@ -86,12 +97,50 @@ impl AssetManifestExt for AssetManifest {
             }
         }
 
+        // Purged css keeps the rules for every class the application uses. The classes aren't part of the name of the css because the name is chosen when the css is collected
+        let classes = collect_classes(self);
+        let used_classes: HashSet<&str> = classes.split_whitespace().collect();
+        // Bundled js loads the bare specifiers it can't resolve locally from the import map at runtime
+        let import_specifiers = self.import_specifiers();
+
         self.assets().iter().try_for_each(|asset| {
             match asset {
                 AssetType::File(file_asset) => {
                     tracing::info!("Optimizing and bundling {}", file_asset);
                     tracing::trace!("Copying asset from {:?} to {:?}", file_asset, location);
-                    match process_file(file_asset, &location) {
+                    if let FileOptions::Js(js) = file_asset.options() {
+                        for specifier in js.externals() {
+                            if !import_specifiers.contains(&specifier.as_str()) {
+                                tracing::warn!("{file_asset} imports {specifier}, but it is not installed in node_modules or mapped to js with import_map");
+                            }
+                        }
+                    }
+                    let result = match file_asset.options() {
+                        FileOptions::Css(css) if css.purge() => process_css(
+                            css,
+                            file_asset.location().source(),
+                            &location.join(file_asset.location().unique_name()),
+                            Some(&used_classes),
+                        ),
+                        // Images with a minimum quality report the quality they reached
+                        FileOptions::Image(image) if image.min_quality().is_some() => {
+                            process_image(
+                                image,
+                                file_asset.location().source(),
+                                &location.join(file_asset.location().unique_name()),
+                            )
+                            .map(|quality| {
+                                if let Some((encoder_quality, score)) = quality {
+                                    tracing::info!(
+                                        "Encoded {} at encoder quality {encoder_quality} with a quality of {score:.4}",
+                                        file_asset
+                                    );
+                                }
+                            })
+                        }
+                        _ => process_file(file_asset, &location),
+                    };
+                    match result {
                         Ok(_) => {}
                         Err(err) => {
                             tracing::error!("Failed to copy static asset: {}", err);
@@ -112,7 +161,15 @@ impl AssetManifestExt for AssetManifest {
                 _ => {}
             }
             Ok::<(), anyhow::Error>(())
-        })
+        })?;
+
+        // The inline snippets from every crate are bundled into one stylesheet and one script
+        for ty in [SnippetType::Css, SnippetType::Js] {
+            bundle_snippets(self, ty, &location)
+                .with_context(|| format!("Failed to bundle {ty} snippets"))?;
+        }
+
+        Ok(())
     }
 
     fn collect_tailwind_css(
@@ -120,14 +177,7 @@ impl AssetManifestExt for AssetManifest {
         include_preflight: bool,
         warnings: &mut Vec<TailwindWarning>,
     ) -> String {
-        let mut all_classes = String::new();
-
-        for asset in self.assets() {
-            if let AssetType::Tailwind(classes) = asset {
-                all_classes.push_str(classes.classes());
-                all_classes.push(' ');
-            }
-        }
+        let all_classes = collect_classes(self);
 
         let source = railwind::Source::String(all_classes, railwind::CollectionOptions::String);
 
@@ -137,6 +187,72 @@ impl AssetManifestExt for AssetManifest {
     }
 }
 
+/// Collects every class declared with the `classes!` macro separated by spaces
+fn collect_classes(manifest: &AssetManifest) -> String {
+    let mut all_classes = String::new();
+
+    for asset in manifest.assets() {
+        if let AssetType::Tailwind(classes) = asset {
+            all_classes.push_str(classes.classes());
+            all_classes.push(' ');
+        }
+    }
+
+    all_classes
+}
+
+/// Concatenates the inline snippets of a type from every crate into one bundle and processes it like any other css or js asset. Snippets are ordered by the name of the crate they are declared in, snippets from the same crate keep the order they appear in the manifest, and identical snippets are only included the first time they appear
+fn bundle_snippets(
+    manifest: &AssetManifest,
+    ty: SnippetType,
+    location: &Path,
+) -> anyhow::Result<()> {
+    let mut snippets: Vec<&SnippetAsset> = Vec::new();
+    for asset in manifest.assets() {
+        if let AssetType::Snippet(snippet) = asset {
+            if snippet.ty() == ty && !snippets.iter().any(|s| s.content() == snippet.content()) {
+                snippets.push(snippet);
+            }
+        }
+    }
+    if snippets.is_empty() {
+        return Ok(());
+    }
+    // The sort is stable, so snippets from the same crate keep their order
+    snippets.sort_by(|a, b| a.package().cmp(b.package()));
+    tracing::info!("Bundling {} {ty} snippets", snippets.len());
+
+    let mut bundle = String::new();
+    for snippet in snippets {
+        match ty {
+            // Each snippet is printed on its own so a block it leaves open can't swallow the next snippet
+            SnippetType::Css => {
+                let css = StyleSheet::parse(snippet.content(), ParserOptions::default())
+                    .map_err(|err| anyhow::anyhow!("Failed to parse css snippet: {err}"))?
+                    .to_css(PrinterOptions::default())?
+                    .code;
+                bundle.push_str(&css);
+                bundle.push('\n');
+            }
+            // Each snippet runs in its own function so its declarations, including functions and vars, don't conflict with the other snippets
+            SnippetType::Js => {
+                bundle.push_str("(() => {\n");
+                bundle.push_str(snippet.content());
+                bundle.push_str("\n})();\n");
+            }
+        }
+    }
+
+    // The bundle is written first and then processed in place
+    let output_path = location.join(ty.bundle_name());
+    std::fs::write(&output_path, bundle)?;
+    let source = AssetSource::Local(output_path.clone());
+    match ty {
+        SnippetType::Css => CssOptions::new().process(&source, &output_path),
+        SnippetType::Js => JsOptions::new(JsType::Js).process(&source, &output_path),
+    }
+}
+
 fn deserialize_assets(json: &str) -> Vec<AssetType> {
     let deserializer = serde_json::Deserializer::from_str(json);
     deserializer
@@ -210,3 +326,71 @@ pub fn get_json_from_object_files(object_paths: Vec<PathBuf>) -> Vec<String> {
 
     all_json
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    #[test]
+    fn snippets_are_ordered_by_crate() {
+        let location = write_files("snippet-order", &[]);
+        let manifest = AssetManifest::new(vec![
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Css,
+                "zebra",
+                ".zebra { color: red }",
+            )),
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Css,
+                "apple",
+                ".apple-second { color: blue }",
+            )),
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Css,
+                "apple",
+                ".apple-first { color: green }",
+            )),
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Css,
+                "mango",
+                ".zebra { color: red }",
+            )),
+        ]);
+
+        bundle_snippets(&manifest, SnippetType::Css, &location).unwrap();
+        let css = std::fs::read_to_string(location.join(SnippetType::Css.bundle_name())).unwrap();
+
+        let apple_second = css.find(".apple-second").unwrap();
+        let apple_first = css.find(".apple-first").unwrap();
+        let zebra = css.find(".zebra").unwrap();
+        assert!(apple_second < apple_first, "{css}");
+        assert!(apple_first < zebra, "{css}");
+        // Identical snippets are only included once
+        assert_eq!(css.matches(".zebra").count(), 1, "{css}");
+    }
+
+    #[test]
+    fn js_snippets_are_scoped_to_their_own_function() {
+        let location = write_files("snippet-scope", &[]);
+        let manifest = AssetManifest::new(vec![
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Js,
+                "apple",
+                "function log() { console.log('apple'); }\nlog();",
+            )),
+            AssetType::Snippet(SnippetAsset::new(
+                SnippetType::Js,
+                "zebra",
+                "function log() { console.log('zebra'); }\nlog();",
+            )),
+        ]);
+
+        bundle_snippets(&manifest, SnippetType::Js, &location).unwrap();
+        let js = std::fs::read_to_string(location.join(SnippetType::Js.bundle_name())).unwrap();
+
+        // In a shared scope the second function would replace the first one
+        assert!(js.contains("apple"), "{js}");
+        assert!(js.contains("zebra"), "{js}");
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use lightningcss::{
+    rules::{style::StyleRule, CssRule, CssRuleList},
+    selector::{Component, Selector},
+    stylesheet::StyleSheet,
+};
+
+/// Removes the style rules from the stylesheet whose selectors use a class that is not in `used_classes`. Selectors without classes are always kept
+pub(crate) fn purge_css(stylesheet: &mut StyleSheet, used_classes: &HashSet<&str>) {
+    purge_rules(&mut stylesheet.rules, used_classes);
+}
+
+fn purge_rules(rules: &mut CssRuleList, used_classes: &HashSet<&str>) {
+    rules.0.retain_mut(|rule| match rule {
+        CssRule::Style(style) => purge_style(style, used_classes),
+        CssRule::Nesting(nesting) => purge_style(&mut nesting.style, used_classes),
+        CssRule::Media(media) => purge_block(&mut media.rules, used_classes),
+        CssRule::Supports(supports) => purge_block(&mut supports.rules, used_classes),
+        CssRule::LayerBlock(layer) => purge_block(&mut layer.rules, used_classes),
+        CssRule::Container(container) => purge_block(&mut container.rules, used_classes),
+        CssRule::Scope(scope) => purge_block(&mut scope.rules, used_classes),
+        CssRule::StartingStyle(starting) => purge_block(&mut starting.rules, used_classes),
+        CssRule::MozDocument(document) => purge_block(&mut document.rules, used_classes),
+        _ => true,
+    });
+}
+
+/// Purges the rules inside an at rule and returns true if any are left
+fn purge_block(rules: &mut CssRuleList, used_classes: &HashSet<&str>) -> bool {
+    purge_rules(rules, used_classes);
+    !rules.0.is_empty()
+}
+
+/// Removes the unused selectors from a style rule and returns true if any are left
+fn purge_style(style: &mut StyleRule, used_classes: &HashSet<&str>) -> bool {
+    style
+        .selectors
+        .0
+        .retain(|selector| selector_used(selector, used_classes));
+    if style.selectors.0.is_empty() {
+        return false;
+    }
+    purge_rules(&mut style.rules, used_classes);
+    true
+}
+
+/// Returns true if every class the selector needs to match is used. Selector lists inside `:is()`, `:where()` and `:has()` are used if any selector in them is used. Classes inside `:not()` are ignored because the selector can match without them
+fn selector_used(selector: &Selector, used_classes: &HashSet<&str>) -> bool {
+    let any_used = |selectors: &[Selector]| {
+        selectors
+            .iter()
+            .any(|selector| selector_used(selector, used_classes))
+    };
+    selector
+        .iter_raw_match_order()
+        .all(|component| match component {
+            Component::Class(class) => used_classes.contains(class.as_ref()),
+            Component::Is(selectors)
+            | Component::Where(selectors)
+            | Component::Has(selectors)
+            | Component::Any(_, selectors) => any_used(selectors),
+            Component::Slotted(selector) | Component::Host(Some(selector)) => {
+                selector_used(selector, used_classes)
+            }
+            _ => true,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lightningcss::stylesheet::{ParserOptions, PrinterOptions};
+
+    fn purge(css: &str, used_classes: &[&str]) -> String {
+        let mut stylesheet = StyleSheet::parse(css, ParserOptions::default()).unwrap();
+        purge_css(&mut stylesheet, &used_classes.iter().copied().collect());
+        stylesheet
+            .to_css(PrinterOptions {
+                minify: true,
+                ..Default::default()
+            })
+            .unwrap()
+            .code
+    }
+
+    #[test]
+    fn rules_for_unused_classes_are_removed() {
+        assert_eq!(
+            purge(
+                ".used{color:red}.unused{color:blue}div{margin:0}",
+                &["used"]
+            ),
+            ".used{color:red}div{margin:0}"
+        );
+    }
+
+    #[test]
+    fn every_class_in_a_selector_must_be_used() {
+        assert_eq!(
+            purge(".a .b{color:red}.a.c{color:blue}", &["a", "b"]),
+            ".a .b{color:red}"
+        );
+    }
+
+    #[test]
+    fn unused_selectors_are_removed_from_selector_lists() {
+        assert_eq!(purge(".a,.b{color:red}", &["b"]), ".b{color:red}");
+    }
+
+    #[test]
+    fn empty_at_rules_are_removed() {
+        assert_eq!(
+            purge(
+                "@media (width>=600px){.a{color:red}}@media print{.b{color:blue}}",
+                &["b"]
+            ),
+            "@media print{.b{color:#00f}}"
+        );
+    }
+
+    #[test]
+    fn nested_rules_are_purged() {
+        assert_eq!(
+            purge(".a{color:red;&.b{color:blue}&.c{color:green}}", &["a", "b"]),
+            ".a{color:red;&.b{color:#00f}}"
+        );
+    }
+
+    #[test]
+    fn classes_inside_not_are_ignored() {
+        assert_eq!(
+            purge(".a:not(.unused){color:red}", &["a"]),
+            ".a:not(.unused){color:red}"
+        );
+    }
+
+    #[test]
+    fn is_and_where_are_used_if_any_selector_in_them_is_used() {
+        assert_eq!(
+            purge(
+                ":is(.a,.unused) p{color:red}:where(.unused) p{color:blue}:is(.b,.c){color:green}",
+                &["a"]
+            ),
+            ":is(.a,.unused) p{color:red}"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use image::{DynamicImage, RgbaImage};
+
+/// The size of the square windows the structural similarity is measured in
+const WINDOW_SIZE: u32 = 8;
+/// Stabilizes the luminance term for windows with a mean close to zero
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+/// Stabilizes the contrast term for windows with a variance close to zero
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// Finds the lowest encoder quality from 0 to 100 that produces an image with a structural similarity of at least `min_quality` compared to the source. Returns the encoded image, the quality, and the score. If even the highest quality is below `min_quality`, the highest quality is returned
+pub(crate) fn encode_with_min_quality(
+    source: &DynamicImage,
+    min_quality: f32,
+    encode: impl Fn(u8) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<(Vec<u8>, u8, f64)> {
+    let source = source.to_rgba8();
+    let min_quality = min_quality as f64;
+    let score = |encoded: &[u8]| -> anyhow::Result<f64> {
+        let decoded = image::load_from_memory(encoded)?.into_rgba8();
+        Ok(ssim(&source, &decoded))
+    };
+
+    let best_encoded = encode(100)?;
+    let best_score = score(&best_encoded)?;
+    let mut best = (best_encoded, 100, best_score);
+    if best_score < min_quality {
+        return Ok(best);
+    }
+
+    // Higher qualities have higher scores, so a binary search finds the lowest quality that meets the target
+    let (mut low, mut high) = (0, 100);
+    while low < high {
+        let quality = low + (high - low) / 2;
+        let encoded = encode(quality)?;
+        let score = score(&encoded)?;
+        if score >= min_quality {
+            best = (encoded, quality, score);
+            high = quality;
+        } else {
+            low = quality + 1;
+        }
+    }
+
+    Ok(best)
+}
+
+/// Computes the mean structural similarity (SSIM) between the source image and the encoded image from 0 (unrelated) to 1 (identical). If the encoded image is transparent, the colors are premultiplied by the alpha so the hidden colors of transparent pixels are ignored. Otherwise only the colors are compared because the format can't store transparency
+pub(crate) fn ssim(source: &RgbaImage, encoded: &RgbaImage) -> f64 {
+    if source.dimensions() != encoded.dimensions() || source.width() == 0 || source.height() == 0 {
+        return 0.0;
+    }
+
+    let transparent = encoded.pixels().any(|pixel| pixel[3] != u8::MAX);
+    let channels = if transparent { 4 } else { 3 };
+    let (source, encoded) = (
+        channel_planes(source, transparent),
+        channel_planes(encoded, transparent),
+    );
+
+    let (width, height) = (encoded.width, encoded.height);
+    let window_width = WINDOW_SIZE.min(width);
+    let window_height = WINDOW_SIZE.min(height);
+    // Windows overlap by half so edges between windows are measured too
+    let positions = |size: u32, window: u32| {
+        let step = (window / 2).max(1);
+        (0..=size - window).step_by(step as usize)
+    };
+
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in positions(height, window_height) {
+        for x in positions(width, window_width) {
+            for channel in 0..channels {
+                total += window_ssim(
+                    &source.planes[channel],
+                    &encoded.planes[channel],
+                    width,
+                    (x, y),
+                    (window_width, window_height),
+                );
+                windows += 1;
+            }
+        }
+    }
+
+    total / windows as f64
+}
+
+/// The red, green, blue and alpha values of an image as separate planes
+struct ChannelPlanes {
+    width: u32,
+    height: u32,
+    planes: [Vec<f64>; 4],
+}
+
+fn channel_planes(image: &RgbaImage, premultiply: bool) -> ChannelPlanes {
+    let mut planes: [Vec<f64>; 4] = Default::default();
+    for pixel in image.pixels() {
+        let alpha = pixel[3] as f64 / u8::MAX as f64;
+        for (channel, plane) in planes.iter_mut().enumerate() {
+            let value = pixel[channel] as f64;
+            plane.push(if premultiply && channel < 3 {
+                value * alpha
+            } else {
+                value
+            });
+        }
+    }
+    ChannelPlanes {
+        width: image.width(),
+        height: image.height(),
+        planes,
+    }
+}
+
+/// Computes the structural similarity of one channel in one window
+fn window_ssim(
+    a: &[f64],
+    b: &[f64],
+    stride: u32,
+    (x, y): (u32, u32),
+    (width, height): (u32, u32),
+) -> f64 {
+    let samples = (width * height) as f64;
+    let values = || {
+        (y..y + height).flat_map(move |y| {
+            (x..x + width).map(move |x| {
+                let index = (y * stride + x) as usize;
+                (a[index], b[index])
+            })
+        })
+    };
+
+    let (sum_a, sum_b) = values().fold((0.0, 0.0), |(sum_a, sum_b), (a, b)| (sum_a + a, sum_b + b));
+    let (mean_a, mean_b) = (sum_a / samples, sum_b / samples);
+
+    let (variance_a, variance_b, covariance) =
+        values().fold((0.0, 0.0, 0.0), |(va, vb, cov), (a, b)| {
+            let (da, db) = (a - mean_a, b - mean_b);
+            (va + da * da, vb + db * db, cov + da * db)
+        });
+    let (variance_a, variance_b, covariance) = (
+        variance_a / samples,
+        variance_b / samples,
+        covariance / samples,
+    );
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn gradient(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x * 8) as u8, (y * 8) as u8, ((x + y) * 4) as u8, 255])
+        })
+    }
+
+    /// Encodes the image as a png after shifting every channel by more the lower the quality is
+    fn encode_with_error(image: &RgbaImage, quality: u8) -> Vec<u8> {
+        let error = (100 - quality) as i32;
+        let degraded = RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            let pixel = image.get_pixel(x, y);
+            let shift = if (x + y) % 2 == 0 { error } else { -error };
+            image::Rgba([
+                (pixel[0] as i32 + shift).clamp(0, 255) as u8,
+                (pixel[1] as i32 + shift).clamp(0, 255) as u8,
+                (pixel[2] as i32 + shift).clamp(0, 255) as u8,
+                pixel[3],
+            ])
+        });
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(degraded)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn identical_images_are_fully_similar() {
+        let image = gradient(32, 32);
+        assert!((ssim(&image, &image) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn images_with_different_sizes_are_not_similar() {
+        assert_eq!(ssim(&gradient(32, 32), &gradient(16, 32)), 0.0);
+    }
+
+    #[test]
+    fn similarity_drops_as_the_error_grows() {
+        let image = gradient(32, 32);
+        let score = |quality| {
+            let encoded = image::load_from_memory(&encode_with_error(&image, quality)).unwrap();
+            ssim(&image, &encoded.into_rgba8())
+        };
+        assert!(score(90) > score(50));
+        assert!(score(50) > score(0));
+    }
+
+    #[test]
+    fn hidden_colors_of_transparent_pixels_are_ignored() {
+        let source = RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 0]));
+        let encoded = RgbaImage::from_pixel(16, 16, image::Rgba([0, 255, 0, 0]));
+        assert!((ssim(&source, &encoded) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn search_finds_the_lowest_quality_that_meets_the_minimum() {
+        let image = DynamicImage::ImageRgba8(gradient(32, 32));
+        let source = image.to_rgba8();
+        let min_quality = 0.9;
+
+        let (_, quality, score) = encode_with_min_quality(&image, min_quality, |quality| {
+            Ok(encode_with_error(&source, quality))
+        })
+        .unwrap();
+
+        assert!(score >= min_quality as f64);
+        assert!(quality < 100);
+        let lower = image::load_from_memory(&encode_with_error(&source, quality - 1)).unwrap();
+        assert!(ssim(&source, &lower.into_rgba8()) < min_quality as f64);
+    }
+
+    #[test]
+    fn search_falls_back_to_the_highest_quality() {
+        let image = DynamicImage::ImageRgba8(gradient(32, 32));
+        let qualities = RefCell::new(Vec::new());
+
+        // An encoder that always inverts the image can never reach the minimum quality
+        let (_, quality, score) = encode_with_min_quality(&image, 0.99, |quality| {
+            qualities.borrow_mut().push(quality);
+            let mut inverted = image.clone();
+            inverted.invert();
+            let mut bytes = Vec::new();
+            inverted.write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )?;
+            Ok(bytes)
+        })
+        .unwrap();
+
+        assert_eq!(quality, 100);
+        assert!(score < 0.99);
+        assert_eq!(qualities.into_inner(), [100]);
+    }
+}
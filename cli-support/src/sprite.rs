@@ -0,0 +1,237 @@
+use std::{collections::HashSet, path::Path};
+
+use anyhow::Context;
+use manganis_common::{AssetSource, SvgSpriteOptions};
+
+use crate::file::Process;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Attributes of the root svg that only make sense on a standalone document, so they are not copied to the symbol
+const ROOT_ONLY_ATTRIBUTES: &[&str] = &["id", "x", "y", "width", "height", "viewBox", "version"];
+
+impl Process for SvgSpriteOptions {
+    fn process(&self, _: &AssetSource, output_path: &Path) -> anyhow::Result<()> {
+        let mut sprite = format!("<svg xmlns=\"{SVG_NAMESPACE}\">");
+        for icon in self.icons() {
+            let svg = std::fs::read_to_string(icon.source()).with_context(|| {
+                format!("Failed to read sprite icon {}", icon.source().display())
+            })?;
+            let symbol = svg_to_symbol(&svg, icon.id()).with_context(|| {
+                format!("Failed to parse sprite icon {}", icon.source().display())
+            })?;
+            sprite.push_str(&symbol);
+        }
+        sprite.push_str("</svg>");
+
+        std::fs::write(output_path, sprite).with_context(|| {
+            format!(
+                "Failed to write svg sprite to output location: {}",
+                output_path.display()
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Converts an svg document into a minimal `<symbol>` with the given id. Comments, metadata, and editor specific elements and attributes are removed. Ids inside the icon are prefixed with the symbol id so they don't collide with ids in other icons
+fn svg_to_symbol(svg: &str, id: &str) -> anyhow::Result<String> {
+    let document = roxmltree::Document::parse(svg)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "svg" {
+        anyhow::bail!("The root element must be an svg element");
+    }
+
+    let view_box = match root.attribute("viewBox") {
+        Some(view_box) => view_box.to_string(),
+        None => {
+            let length = |name| {
+                root.attribute(name)
+                    .map(|value: &str| value.trim_end_matches("px").to_string())
+            };
+            match (length("width"), length("height")) {
+                (Some(width), Some(height)) => format!("0 0 {width} {height}"),
+                _ => anyhow::bail!("The svg must have a viewBox or a width and height"),
+            }
+        }
+    };
+
+    let ids: HashSet<&str> = root
+        .descendants()
+        .filter_map(|node| node.attribute("id"))
+        .collect();
+    let writer = SymbolWriter { prefix: id, ids };
+
+    let mut symbol = String::new();
+    symbol.push_str("<symbol id=\"");
+    escape_into(id, &mut symbol);
+    symbol.push_str("\" viewBox=\"");
+    escape_into(&view_box, &mut symbol);
+    symbol.push('"');
+    writer.write_attributes(root, ROOT_ONLY_ATTRIBUTES, &mut symbol);
+    symbol.push('>');
+    for child in root.children() {
+        writer.write_node(child, &mut symbol);
+    }
+    symbol.push_str("</symbol>");
+
+    Ok(symbol)
+}
+
+struct SymbolWriter<'a> {
+    /// The id of the symbol that every id in the icon is prefixed with
+    prefix: &'a str,
+    /// The ids defined in the icon
+    ids: HashSet<&'a str>,
+}
+
+impl SymbolWriter<'_> {
+    fn write_node(&self, node: roxmltree::Node, out: &mut String) {
+        match node.node_type() {
+            roxmltree::NodeType::Element => {
+                let name = node.tag_name();
+                // Elements from editors like inkscape and metadata are not needed to render the icon
+                if name.namespace() != Some(SVG_NAMESPACE) || name.name() == "metadata" {
+                    return;
+                }
+                out.push('<');
+                out.push_str(name.name());
+                self.write_attributes(node, &[], out);
+                if node.has_children() {
+                    out.push('>');
+                    for child in node.children() {
+                        self.write_node(child, out);
+                    }
+                    out.push_str("</");
+                    out.push_str(name.name());
+                    out.push('>');
+                } else {
+                    out.push_str("/>");
+                }
+            }
+            roxmltree::NodeType::Text => {
+                let text = node.text().unwrap_or_default();
+                // Whitespace between elements is only formatting
+                if !text.trim().is_empty() {
+                    escape_into(&self.prefix_references(text), out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn write_attributes(&self, node: roxmltree::Node, skip: &[&str], out: &mut String) {
+        for attribute in node.attributes() {
+            let name = attribute.name();
+            match attribute.namespace() {
+                // xlink:href is written as href which every modern browser supports
+                None | Some(XLINK_NAMESPACE) if !skip.contains(&name) => {}
+                _ => continue,
+            }
+            let value = if name == "id" {
+                format!("{}-{}", self.prefix, attribute.value())
+            } else {
+                self.prefix_references(attribute.value())
+            };
+            out.push(' ');
+            out.push_str(name);
+            out.push_str("=\"");
+            escape_into(&value, out);
+            out.push('"');
+        }
+    }
+
+    /// Prefixes references like `url(#gradient)` and `#gradient` to ids in the icon
+    fn prefix_references(&self, value: &str) -> String {
+        if let Some(id) = value.strip_prefix('#') {
+            if self.ids.contains(id) {
+                return format!("#{}-{id}", self.prefix);
+            }
+        }
+        let mut value = value.to_string();
+        for id in &self.ids {
+            value = value.replace(
+                &format!("url(#{id})"),
+                &format!("url(#{}-{id})", self.prefix),
+            );
+        }
+        value
+    }
+}
+
+/// Escapes text so it can be used in an attribute value or element content
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_are_prefixed_with_the_symbol_id() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><linearGradient id="fill"/><path id="shape" d="M0 0"/></svg>"##;
+
+        assert_eq!(
+            svg_to_symbol(svg, "home").unwrap(),
+            r#"<symbol id="home" viewBox="0 0 24 24"><linearGradient id="home-fill"/><path id="home-shape" d="M0 0"/></symbol>"#
+        );
+    }
+
+    #[test]
+    fn references_to_ids_are_rewritten() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 24 24"><linearGradient id="fill"/><path id="shape" fill="url(#fill)" style="stroke: url(#fill)"/><use href="#shape"/><use xlink:href="#shape"/><use href="#other"/></svg>"##;
+
+        assert_eq!(
+            svg_to_symbol(svg, "home").unwrap(),
+            r##"<symbol id="home" viewBox="0 0 24 24"><linearGradient id="home-fill"/><path id="home-shape" fill="url(#home-fill)" style="stroke: url(#home-fill)"/><use href="#home-shape"/><use href="#home-shape"/><use href="#other"/></symbol>"##
+        );
+    }
+
+    #[test]
+    fn standalone_attributes_are_removed() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="24" height="24" viewBox="0 0 48 48" fill="none"><path d="M0 0"/></svg>"#;
+
+        assert_eq!(
+            svg_to_symbol(svg, "home").unwrap(),
+            r#"<symbol id="home" viewBox="0 0 48 48" fill="none"><path d="M0 0"/></symbol>"#
+        );
+    }
+
+    #[test]
+    fn the_view_box_falls_back_to_the_size() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="24px" height="16"><path d="M0 0"/></svg>"#;
+
+        assert_eq!(
+            svg_to_symbol(svg, "home").unwrap(),
+            r#"<symbol id="home" viewBox="0 0 24 16"><path d="M0 0"/></symbol>"#
+        );
+    }
+
+    #[test]
+    fn svgs_without_a_size_are_rejected() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="M0 0"/></svg>"#;
+
+        assert!(svg_to_symbol(svg, "home").is_err());
+    }
+
+    #[test]
+    fn editor_elements_and_metadata_are_removed() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" viewBox="0 0 24 24" inkscape:version="1.0"><!-- icon --><metadata>info</metadata><inkscape:grid/><path d="M0 0"/></svg>"#;
+
+        assert_eq!(
+            svg_to_symbol(svg, "home").unwrap(),
+            r#"<symbol id="home" viewBox="0 0 24 24"><path d="M0 0"/></symbol>"#
+        );
+    }
+}
@@ -85,7 +85,7 @@ fn link() {
         .collect::<Vec<_>>();
 
     // Make sure the right number of assets were collected
-    assert_eq!(locations.len(), 16);
+    assert_eq!(locations.len(), 36);
 
     // Then copy the assets to a temporary directory and run the application
     let assets_dir = PathBuf::from("./assets");
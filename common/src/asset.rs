@@ -2,11 +2,13 @@ use std::{
     fmt::Display,
     hash::{DefaultHasher, Hash, Hasher},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use anyhow::Context;
 use base64::Engine;
 use serde::{Deserialize, Serialize};
+use swc_common::Spanned;
 use url::Url;
 
 use crate::{cache::manifest_dir, Config, FileOptions};
@@ -25,6 +27,8 @@ pub enum AssetType {
     Folder(FolderAsset),
     /// A tailwind class asset
     Tailwind(TailwindAsset),
+    /// A css or js snippet declared in Rust source
+    Snippet(SnippetAsset),
     /// A metadata asset
     Metadata(MetadataAsset),
 }
@@ -73,10 +77,26 @@ impl AssetSource {
     pub fn last_segment(&self) -> &str {
         match self {
             Self::Local(path) => path.file_name().unwrap().to_str().unwrap(),
-            Self::Remote(url) => url.path_segments().unwrap().last().unwrap(),
+            Self::Remote(url) => url.path_segments().unwrap().next_back().unwrap(),
         }
     }
 
+    /// Returns the extension of the path of the file source without fetching remote files
+    pub fn path_extension(&self) -> Option<&str> {
+        let path = match self {
+            Self::Local(path) => path.as_path(),
+            Self::Remote(url) => Path::new(url.path()),
+        };
+        path.extension().and_then(|extension| extension.to_str())
+    }
+
+    /// Parses the type of the file source from the extension of its path, or returns the default type if the extension is missing or unknown
+    pub fn type_from_extension<T: FromStr + Default>(&self) -> T {
+        self.path_extension()
+            .and_then(|extension| extension.parse().ok())
+            .unwrap_or_default()
+    }
+
     /// Returns the extension of the file source
     pub fn extension(&self) -> Option<String> {
         match self {
@@ -184,7 +204,7 @@ fn ext_of_mime(mime: &str) -> &str {
         "text/css" => "css",
         "text/csv" => "csv",
         "text/html" => "html",
-        "image/vnd.microsoft.icon" => "ico",
+        "image/vnd.microsoft.icon" | "image/x-icon" => "ico",
         "text/javascript" => "js",
         "application/json" => "json",
         "application/ld+json" => "jsonld",
@@ -199,10 +219,13 @@ fn ext_of_mime(mime: &str) -> &str {
         "image/gif" => "gif",
         "image/webp" => "webp",
         "image/avif" => "avif",
+        "image/bmp" => "bmp",
+        "image/tiff" => "tiff",
+        "image/qoi" => "qoi",
         "font/ttf" => "ttf",
         "font/woff" => "woff",
         "font/woff2" => "woff2",
-        other => other.split('/').last().unwrap_or_default(),
+        other => other.split('/').next_back().unwrap_or_default(),
     }
 }
 
@@ -234,32 +257,48 @@ fn get_mime_by_ext(trimmed: &Path) -> &'static str {
 /// Get the mime type from a URI using its extension
 pub fn get_mime_from_ext(extension: Option<&str>) -> &'static str {
     match extension {
-        Some("bin") => "application/octet-stream",
-        Some("css") => "text/css",
-        Some("csv") => "text/csv",
-        Some("html") => "text/html",
-        Some("ico") => "image/vnd.microsoft.icon",
-        Some("js") => "text/javascript",
-        Some("json") => "application/json",
-        Some("jsonld") => "application/ld+json",
-        Some("mjs") => "text/javascript",
-        Some("rtf") => "application/rtf",
-        Some("svg") => "image/svg+xml",
-        Some("mp4") => "video/mp4",
-        Some("png") => "image/png",
-        Some("jpg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("webp") => "image/webp",
-        Some("avif") => "image/avif",
-        Some("txt") => "text/plain",
-        // Assume HTML when a TLD is found for eg. `dioxus:://dioxuslabs.app` | `dioxus://hello.com`
-        Some(_) => "text/html",
+        Some(extension) => known_mime_from_ext(extension)
+            // Assume HTML when a TLD is found for eg. `dioxus:://dioxuslabs.app` | `dioxus://hello.com`
+            .unwrap_or("text/html"),
         // https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types
         // using octet stream according to this:
         None => "application/octet-stream",
     }
 }
 
+/// Get the mime type for an extension, or None if the extension isn't a known file type
+pub fn known_mime_from_ext(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "bin" => "application/octet-stream",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "html" => "text/html",
+        "ico" => "image/vnd.microsoft.icon",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "jsonld" => "application/ld+json",
+        "mjs" => "text/javascript",
+        "rtf" => "application/rtf",
+        "svg" => "image/svg+xml",
+        "mp4" => "video/mp4",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "qoi" => "image/qoi",
+        "txt" => "text/plain",
+        "cur" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => return None,
+    })
+}
+
 /// The location of an asset before and after it is collected
 #[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash, Eq)]
 pub struct AssetLocation {
@@ -268,7 +307,7 @@ pub struct AssetLocation {
 }
 
 impl AssetLocation {
-    /// Returns the unique name of the file that the asset will be served from
+    /// Returns the unique name of the file that the asset will be served from. The name is a hash of the source, the options and the version of manganis. Purged css also depends on the classes the whole application uses, which are collected after the name is chosen, so they are not part of the name
     pub fn unique_name(&self) -> &str {
         &self.unique_name
     }
@@ -649,7 +688,7 @@ fn resolve_asset_location(location: &AssetLocation) -> Result<String, ManganisSu
 }
 
 /// An error that can occur while collecting assets without CLI support
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ManganisSupportError {
     /// An error that can occur while collecting assets from other packages without CLI support
     ExternalPackageCollection,
@@ -714,3 +753,118 @@ impl TailwindAsset {
         &self.classes
     }
 }
+
+/// A snippet of css or js declared in Rust source. Every snippet of the same type is concatenated into one bundle
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone)]
+pub struct SnippetAsset {
+    ty: SnippetType,
+    package: String,
+    content: String,
+}
+
+impl SnippetAsset {
+    /// Creates a new snippet asset
+    pub fn new(ty: SnippetType, package: &str, content: &str) -> Self {
+        Self {
+            ty,
+            package: package.to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    /// Returns the type of the snippet
+    pub fn ty(&self) -> SnippetType {
+        self.ty
+    }
+
+    /// Returns the name of the package the snippet is declared in
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    /// Returns the text of the snippet
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Checks that the snippet is valid css or a valid classic js script
+    pub fn verify(&self) -> anyhow::Result<()> {
+        match self.ty {
+            SnippetType::Css => {
+                lightningcss::stylesheet::StyleSheet::parse(&self.content, Default::default())
+                    .map_err(|err| anyhow::anyhow!("Failed to parse css snippet: {err}"))?;
+            }
+            SnippetType::Js => {
+                let cm = swc_common::SourceMap::default();
+                let fm = cm.new_source_file(
+                    swc_common::sync::Lrc::new(swc_common::FileName::Anon),
+                    self.content.clone(),
+                );
+                // Snippets are concatenated into one classic script, so they can't import modules
+                let mut errors = Vec::new();
+                let parsed = swc_ecma_parser::parse_file_as_script(
+                    &fm,
+                    Default::default(),
+                    swc_ecma_ast::EsVersion::EsNext,
+                    None,
+                    &mut errors,
+                );
+                if let Some(err) = parsed.err().or_else(|| errors.into_iter().next()) {
+                    let location = cm.lookup_char_pos(err.span().lo);
+                    anyhow::bail!(
+                        "Failed to parse js snippet at {}:{}: {}",
+                        location.line,
+                        location.col_display + 1,
+                        err.kind().msg()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the location the bundle the snippet is concatenated into is served at. The bundle is only written by a CLI that supports manganis
+    pub fn served_location(&self) -> String {
+        self.ty.served_location()
+    }
+}
+
+/// The type of an inline snippet
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Hash, Eq)]
+pub enum SnippetType {
+    /// A css snippet
+    Css,
+    /// A js snippet
+    Js,
+}
+
+impl SnippetType {
+    /// The name of the file every css snippet is bundled into
+    pub const CSS_BUNDLE: &'static str = "manganis-snippets.css";
+    /// The name of the file every js snippet is bundled into
+    pub const JS_BUNDLE: &'static str = "manganis-snippets.js";
+
+    /// Returns the name of the file every snippet of this type is bundled into
+    pub fn bundle_name(&self) -> &'static str {
+        match self {
+            Self::Css => Self::CSS_BUNDLE,
+            Self::Js => Self::JS_BUNDLE,
+        }
+    }
+
+    /// Returns the location the bundle of every snippet of this type is served at
+    pub fn served_location(&self) -> String {
+        let config = Config::current();
+        let root = config.assets_serve_location();
+        format!("{root}{}", self.bundle_name())
+    }
+}
+
+impl Display for SnippetType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Css => write!(f, "css"),
+            Self::Js => write!(f, "js"),
+        }
+    }
+}
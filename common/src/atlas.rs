@@ -0,0 +1,322 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{AssetSource, ImageOptions, ImageType};
+
+/// One image packed into a sprite sheet
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct AtlasFrame {
+    name: String,
+    source: PathBuf,
+    /// When the image was last modified. The sheet needs to be regenerated if any of the images in it change
+    last_updated: Option<String>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl AtlasFrame {
+    /// Returns the name of the frame. This is the path of the image relative to the atlas folder without the extension
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path of the image the frame was created from
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Returns the x position of the frame in the sprite sheet
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// Returns the y position of the frame in the sprite sheet
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Returns the width of the frame
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the frame
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// A sprite sheet that contains some of the images in an atlas folder
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct AtlasSheet {
+    width: u32,
+    height: u32,
+    frames: Vec<AtlasFrame>,
+}
+
+impl AtlasSheet {
+    /// Returns the width of the sprite sheet
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the sprite sheet
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the frames in the sprite sheet
+    pub fn frames(&self) -> &[AtlasFrame] {
+        &self.frames
+    }
+
+    /// Draws every frame into a transparent sprite sheet
+    pub fn render(&self) -> anyhow::Result<DynamicImage> {
+        let mut sheet = RgbaImage::new(self.width, self.height);
+        for frame in &self.frames {
+            let image = decode_frame(&frame.source)?;
+            image::imageops::replace(&mut sheet, &image, frame.x as i64, frame.y as i64);
+        }
+        Ok(DynamicImage::ImageRgba8(sheet))
+    }
+}
+
+/// Packs every image in a folder and its subfolders into as few sprite sheets as possible. Each sheet is at most `max_size` and frames are separated by `padding` transparent pixels
+pub fn pack_atlas(
+    folder: &Path,
+    max_size: (u32, u32),
+    padding: u32,
+) -> anyhow::Result<Vec<AtlasSheet>> {
+    let mut images = Vec::new();
+    collect_images(folder, folder, &mut images)?;
+    if images.is_empty() {
+        anyhow::bail!("No images found in atlas folder {}", folder.display());
+    }
+
+    let (max_width, max_height) = max_size;
+    for (name, _, (width, height)) in &images {
+        if *width > max_width || *height > max_height {
+            anyhow::bail!(
+                "The image {name} is {width}x{height} which does not fit in a {max_width}x{max_height} sprite sheet"
+            );
+        }
+    }
+
+    // Shelf packing works best when the tallest images are placed first. The name breaks ties so the layout is stable between builds
+    images.sort_by(
+        |(name_a, _, (width_a, height_a)), (name_b, _, (width_b, height_b))| {
+            height_b
+                .cmp(height_a)
+                .then(width_b.cmp(width_a))
+                .then(name_a.cmp(name_b))
+        },
+    );
+
+    let mut sheets = Vec::new();
+    let mut packer = ShelfPacker::new(max_size, padding);
+    let mut frames = Vec::new();
+    for (name, source, (width, height)) in images {
+        let (x, y) = match packer.insert(width, height) {
+            Some(position) => position,
+            None => {
+                // The current sheet is full, so start a new one
+                sheets.push(packer.finish(std::mem::take(&mut frames)));
+                packer = ShelfPacker::new(max_size, padding);
+                packer.insert(width, height).unwrap_or_default()
+            }
+        };
+        frames.push(AtlasFrame {
+            name,
+            last_updated: AssetSource::Local(source.clone()).last_updated(),
+            source,
+            x,
+            y,
+            width,
+            height,
+        });
+    }
+    sheets.push(packer.finish(frames));
+
+    Ok(sheets)
+}
+
+/// Packs rectangles into rows that are as tall as the first rectangle placed in them
+struct ShelfPacker {
+    max_size: (u32, u32),
+    padding: u32,
+    /// The y position, height and next free x position of each shelf
+    shelves: Vec<(u32, u32, u32)>,
+    /// The y position of the next shelf
+    next_shelf: u32,
+    width: u32,
+    height: u32,
+}
+
+impl ShelfPacker {
+    fn new(max_size: (u32, u32), padding: u32) -> Self {
+        Self {
+            max_size,
+            padding,
+            shelves: Vec::new(),
+            next_shelf: 0,
+            width: 0,
+            height: 0,
+        }
+    }
+
+    /// Finds a place for the rectangle and returns its position, or None if the sheet is full
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (max_width, max_height) = self.max_size;
+        let position = match self
+            .shelves
+            .iter_mut()
+            .find(|(_, shelf_height, x)| height <= *shelf_height && *x + width <= max_width)
+        {
+            Some((y, _, x)) => {
+                let position = (*x, *y);
+                *x += width + self.padding;
+                position
+            }
+            None => {
+                let y = self.next_shelf;
+                if y + height > max_height || width > max_width {
+                    return None;
+                }
+                self.shelves.push((y, height, width + self.padding));
+                self.next_shelf += height + self.padding;
+                (0, y)
+            }
+        };
+        self.width = self.width.max(position.0 + width);
+        self.height = self.height.max(position.1 + height);
+        Some(position)
+    }
+
+    /// Creates the sheet from the frames placed in it
+    fn finish(self, frames: Vec<AtlasFrame>) -> AtlasSheet {
+        AtlasSheet {
+            width: self.width,
+            height: self.height,
+            frames,
+        }
+    }
+}
+
+/// Finds every image in the folder and its subfolders along with its name and size
+fn collect_images(
+    root: &Path,
+    folder: &Path,
+    images: &mut Vec<(String, PathBuf, (u32, u32))>,
+) -> anyhow::Result<()> {
+    let entries = std::fs::read_dir(folder)
+        .with_context(|| format!("Failed to read atlas folder {}", folder.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_images(root, &path, images)?;
+            continue;
+        }
+        let is_image = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.parse::<ImageType>().is_ok());
+        if !is_image {
+            continue;
+        }
+
+        let name = path
+            .strip_prefix(root)?
+            .with_extension("")
+            .iter()
+            .map(|segment| segment.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let size = decode_frame(&path)?.dimensions();
+        images.push((name, path, size));
+    }
+    Ok(())
+}
+
+/// Decodes one image in the atlas folder, applying its color profile and orientation
+fn decode_frame(path: &Path) -> anyhow::Result<DynamicImage> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read atlas image {}", path.display()))?;
+    ImageOptions::new(ImageType::Png, None)
+        .decode(&bytes)
+        .with_context(|| format!("Failed to decode atlas image {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    #[test]
+    fn shelf_packer_fills_rows_before_opening_new_ones() {
+        let mut packer = ShelfPacker::new((10, 10), 1);
+        assert_eq!(packer.insert(4, 4), Some((0, 0)));
+        assert_eq!(packer.insert(4, 3), Some((5, 0)));
+        // The first shelf is full, so a new one starts below it with the padding
+        assert_eq!(packer.insert(4, 4), Some((0, 5)));
+        // Shorter rectangles fill the rest of the second shelf
+        assert_eq!(packer.insert(5, 2), Some((5, 5)));
+        assert_eq!(packer.insert(4, 5), None);
+
+        let sheet = packer.finish(Vec::new());
+        assert_eq!((sheet.width(), sheet.height()), (10, 9));
+    }
+
+    #[test]
+    fn shelf_packer_rejects_rectangles_larger_than_the_sheet() {
+        let mut packer = ShelfPacker::new((8, 8), 0);
+        assert_eq!(packer.insert(9, 1), None);
+        assert_eq!(packer.insert(1, 9), None);
+        assert_eq!(packer.insert(8, 8), Some((0, 0)));
+        assert_eq!(packer.insert(1, 1), None);
+    }
+
+    #[test]
+    fn pack_atlas_splits_images_into_sheets() {
+        let folder = write_files("atlas", &[]);
+        std::fs::create_dir_all(folder.join("icons")).unwrap();
+        for (path, size) in [("large.png", 6), ("icons/small.png", 4), ("medium.png", 5)] {
+            RgbaImage::new(size, size).save(folder.join(path)).unwrap();
+        }
+
+        let sheets = pack_atlas(&folder, (8, 8), 1).unwrap();
+
+        let frames = sheets
+            .iter()
+            .map(|sheet| {
+                sheet
+                    .frames()
+                    .iter()
+                    .map(|frame| (frame.name(), frame.x(), frame.y(), frame.width()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            [
+                vec![("large", 0, 0, 6)],
+                vec![("medium", 0, 0, 5)],
+                vec![("icons/small", 0, 0, 4)],
+            ]
+        );
+    }
+
+    #[test]
+    fn pack_atlas_rejects_images_larger_than_the_sheet() {
+        let folder = write_files("atlas-large", &[]);
+        RgbaImage::new(16, 4).save(folder.join("wide.png")).unwrap();
+
+        let error = pack_atlas(&folder, (8, 8), 0).unwrap_err();
+
+        assert!(error.to_string().contains("wide is 16x4"));
+    }
+}
@@ -1,8 +1,8 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::cache::config_path;
+use crate::{cache::config_path, EsVersion};
 
 fn default_assets_serve_location() -> String {
     #[cfg(target_arch = "wasm32")]
@@ -20,6 +20,14 @@ fn default_assets_serve_location() -> String {
 pub struct Config {
     #[serde(default = "default_assets_serve_location")]
     assets_serve_location: String,
+    #[serde(default)]
+    css_targets: Option<String>,
+    #[serde(default)]
+    source_maps: bool,
+    #[serde(default)]
+    source_map_dir: Option<PathBuf>,
+    #[serde(default)]
+    js_target: EsVersion,
 }
 
 impl Config {
@@ -27,6 +35,10 @@ impl Config {
     pub fn with_assets_serve_location(&self, assets_serve_location: impl Into<String>) -> Self {
         Self {
             assets_serve_location: assets_serve_location.into(),
+            css_targets: self.css_targets.clone(),
+            source_maps: self.source_maps,
+            source_map_dir: self.source_map_dir.clone(),
+            js_target: self.js_target,
         }
     }
 
@@ -35,6 +47,70 @@ impl Config {
         &self.assets_serve_location
     }
 
+    /// The browserslist query for the browsers css should support, like `"> 0.5%, last 2 versions, not dead"`. Css assets without their own targets are prefixed and modern syntax is lowered for these browsers.
+    pub fn with_css_targets(&self, css_targets: impl Into<String>) -> Self {
+        Self {
+            assets_serve_location: self.assets_serve_location.clone(),
+            css_targets: Some(css_targets.into()),
+            source_maps: self.source_maps,
+            source_map_dir: self.source_map_dir.clone(),
+            js_target: self.js_target,
+        }
+    }
+
+    /// The browserslist query for the browsers css should support if one is set
+    pub fn css_targets(&self) -> Option<&str> {
+        self.css_targets.as_deref()
+    }
+
+    /// Whether source maps should be generated for every minified css and js asset. The maps are written next to the assets and linked with a `sourceMappingURL` comment
+    pub fn with_source_maps(&self, source_maps: bool) -> Self {
+        Self {
+            assets_serve_location: self.assets_serve_location.clone(),
+            css_targets: self.css_targets.clone(),
+            source_maps,
+            source_map_dir: self.source_map_dir.clone(),
+            js_target: self.js_target,
+        }
+    }
+
+    /// Whether source maps should be generated for every minified css and js asset
+    pub fn source_maps(&self) -> bool {
+        self.source_maps
+    }
+
+    /// The directory source maps are written to instead of the assets directory. Use it to keep source maps out of the public directory, for example to upload them to an error tracker. Assets don't link to maps in this directory
+    pub fn with_source_map_dir(&self, source_map_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            assets_serve_location: self.assets_serve_location.clone(),
+            css_targets: self.css_targets.clone(),
+            source_maps: self.source_maps,
+            source_map_dir: Some(source_map_dir.into()),
+            js_target: self.js_target,
+        }
+    }
+
+    /// The directory source maps are written to if they are kept out of the assets directory
+    pub fn source_map_dir(&self) -> Option<&Path> {
+        self.source_map_dir.as_deref()
+    }
+
+    /// The version of the ECMAScript standard js assets without their own target are compiled to. Set it to support older browsers and webviews
+    pub fn with_js_target(&self, js_target: EsVersion) -> Self {
+        Self {
+            assets_serve_location: self.assets_serve_location.clone(),
+            css_targets: self.css_targets.clone(),
+            source_maps: self.source_maps,
+            source_map_dir: self.source_map_dir.clone(),
+            js_target,
+        }
+    }
+
+    /// The version of the ECMAScript standard js assets are compiled to
+    pub fn js_target(&self) -> EsVersion {
+        self.js_target
+    }
+
     #[doc(hidden)]
     /// Returns the path to the config
     /// This is only used in the macro
@@ -70,6 +146,10 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             assets_serve_location: default_assets_serve_location(),
+            css_targets: None,
+            source_maps: false,
+            source_map_dir: None,
+            js_target: EsVersion::EsNext,
         }
     }
 }
@@ -0,0 +1,458 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use lightningcss::{
+    dependencies::{Dependency, DependencyOptions},
+    printer::PrinterOptions,
+    stylesheet::{ParserOptions, StyleSheet},
+};
+use serde::{Deserialize, Serialize};
+use url::{Position, Url};
+
+use crate::{known_mime_from_ext, read_stylesheet, AssetSource, CssType, FileAsset, FileOptions};
+
+/// A reference to another file in a stylesheet with `url()` or `@import`
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct CssReference {
+    url: String,
+    location: String,
+}
+
+impl CssReference {
+    /// Creates a new css reference
+    pub fn new(url: impl Into<String>, location: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            location: location.into(),
+        }
+    }
+
+    /// Returns the url as it is written in the stylesheet
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the location the url is rewritten to
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+/// A stylesheet that is inlined into a bundled stylesheet
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct CssImport {
+    source: PathBuf,
+    /// When the stylesheet was last modified, so the bundle changes when an import does
+    last_updated: Option<String>,
+}
+
+impl CssImport {
+    /// Creates a new css import and records when the stylesheet was last modified
+    pub fn new(source: PathBuf) -> Self {
+        let last_updated = AssetSource::Local(source.clone()).last_updated();
+        Self {
+            source,
+            last_updated,
+        }
+    }
+
+    /// Returns the path to the imported stylesheet
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+}
+
+/// Returns every url in a stylesheet that is referenced with `url()` or `@import`
+pub fn css_urls(css: &str) -> anyhow::Result<Vec<String>> {
+    let mut urls = Vec::new();
+    for dependency in analyze_css(css)? {
+        let url = match dependency {
+            Dependency::Import(import) => import.url,
+            Dependency::Url(url) => url.url,
+        };
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    Ok(urls)
+}
+
+fn analyze_css(css: &str) -> anyhow::Result<Vec<Dependency>> {
+    let stylesheet = StyleSheet::parse(css, ParserOptions::default())
+        .map_err(|err| anyhow::anyhow!("Failed to parse css: {err}"))?;
+    Ok(stylesheet
+        .to_css(PrinterOptions {
+            analyze_dependencies: Some(DependencyOptions::default()),
+            ..Default::default()
+        })?
+        .dependencies
+        .unwrap_or_default())
+}
+
+/// Finds the local files a css asset references with `url()` or `@import` and collects them as file assets with the default options. The references in the css are rewritten to the location the files are served at. Stylesheets that are imported are searched for references too. If the css is bundled, imported stylesheets are inlined instead of collected. Files smaller than the inline limit of the css are inlined as data urls. Returns every file the css depends on
+///
+/// Only local stylesheets are searched. Urls with a scheme, absolute paths and fragments are left as they are
+pub fn collect_css_dependencies(file: &mut FileAsset) -> anyhow::Result<Vec<FileAsset>> {
+    let mut dependencies = Vec::new();
+    collect_css_dependencies_inner(file, &mut Vec::new(), &mut dependencies)?;
+    Ok(dependencies)
+}
+
+fn collect_css_dependencies_inner(
+    file: &mut FileAsset,
+    stack: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<FileAsset>,
+) -> anyhow::Result<()> {
+    let FileOptions::Css(options) = file.options() else {
+        return Ok(());
+    };
+    let ty = options.ty();
+    let bundle = options.bundle();
+    let inline_below = options.inline_below();
+    let AssetSource::Local(path) = file.location().source() else {
+        if bundle {
+            anyhow::bail!("Only local css can be bundled");
+        }
+        return Ok(());
+    };
+    let path = path.clone();
+
+    let mut stylesheets = CollectedStylesheets {
+        bundle,
+        inline_below,
+        references: Vec::new(),
+        imports: Vec::new(),
+        dependencies,
+    };
+    stylesheets.collect(&path, ty, stack)?;
+    let CollectedStylesheets {
+        references,
+        imports,
+        ..
+    } = stylesheets;
+
+    file.with_options_mut(|options| {
+        if let FileOptions::Css(options) = options {
+            options.set_references(references);
+            options.set_imports(imports);
+        }
+    });
+
+    Ok(())
+}
+
+/// The references and imports of a stylesheet and every stylesheet that is bundled into it
+struct CollectedStylesheets<'a> {
+    bundle: bool,
+    inline_below: Option<u64>,
+    references: Vec<CssReference>,
+    imports: Vec<CssImport>,
+    dependencies: &'a mut Vec<FileAsset>,
+}
+
+impl CollectedStylesheets<'_> {
+    fn collect(
+        &mut self,
+        path: &Path,
+        ty: CssType,
+        stack: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        if stack.iter().any(|parent| parent == path) {
+            anyhow::bail!("{} imports itself", path.display());
+        }
+
+        let (css, loaded) = read_stylesheet(&AssetSource::Local(path.to_path_buf()), ty)?;
+        // The files Sass loads are compiled into the css, so they are tracked like bundled stylesheets
+        for loaded in loaded {
+            self.add_import(CssImport::new(loaded));
+        }
+        let css_dependencies =
+            analyze_css(&css).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let manganis_support = std::env::var("MANGANIS_SUPPORT").is_ok();
+        stack.push(path.to_path_buf());
+        for css_dependency in css_dependencies {
+            let (url, import) = match css_dependency {
+                Dependency::Import(import) => (import.url, true),
+                Dependency::Url(url) => (url.url, false),
+            };
+            let Some((dependency_path, suffix)) = resolve_local_url(path, &url) else {
+                if self.bundle && import {
+                    anyhow::bail!(
+                        "{} imports {url}, but only local stylesheets can be bundled",
+                        path.display()
+                    );
+                }
+                continue;
+            };
+            let dependency_path = dependency_path.canonicalize().with_context(|| {
+                format!(
+                    "{} references {url}, but {} does not exist",
+                    path.display(),
+                    dependency_path.display()
+                )
+            })?;
+            if !dependency_path.is_file() {
+                anyhow::bail!(
+                    "{} references {url}, but {} is not a file",
+                    path.display(),
+                    dependency_path.display()
+                );
+            }
+
+            // Bundled stylesheets are inlined, so their references become references of the bundle
+            if self.bundle && import {
+                self.add_import(CssImport::new(dependency_path.clone()));
+                self.collect(&dependency_path, CssType::Css, stack)?;
+                continue;
+            }
+
+            // Small files are inlined as data urls so the browser doesn't need another request for them. Data urls can't have a query or fragment, and files without a known type can't be given a mime type
+            let inline = !import
+                && suffix.is_empty()
+                && dependency_path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(known_mime_from_ext)
+                    .is_some()
+                && self.inline_below.is_some_and(|limit| {
+                    dependency_path
+                        .metadata()
+                        .is_ok_and(|metadata| metadata.len() < limit)
+                });
+            let mut dependency = FileAsset::new(AssetSource::Local(dependency_path));
+            if inline {
+                dependency.set_url_encoded(true);
+                if manganis_support {
+                    if let Ok(data) = dependency.served_location() {
+                        self.add_reference(CssReference::new(url, data))?;
+                    }
+                }
+                continue;
+            }
+
+            collect_css_dependencies_inner(&mut dependency, stack, self.dependencies)?;
+            // Without manganis support the css isn't processed, so the reference is left as it is
+            if manganis_support {
+                if let Ok(location) = dependency.served_location() {
+                    self.add_reference(CssReference::new(url, format!("{location}{suffix}")))?;
+                }
+            }
+            if !self.dependencies.contains(&dependency) {
+                self.dependencies.push(dependency);
+            }
+        }
+        stack.pop();
+
+        Ok(())
+    }
+
+    fn add_import(&mut self, import: CssImport) {
+        if !self.imports.contains(&import) {
+            self.imports.push(import);
+        }
+    }
+
+    fn add_reference(&mut self, reference: CssReference) -> anyhow::Result<()> {
+        match self.references.iter().find(|r| r.url() == reference.url()) {
+            Some(existing) if existing != &reference => anyhow::bail!(
+                "{} points to different files in the bundled stylesheets",
+                reference.url()
+            ),
+            Some(_) => {}
+            None => self.references.push(reference),
+        }
+        Ok(())
+    }
+}
+
+/// Resolves a url relative to the file it is referenced in. Returns the path of the file and the query and fragment of the url, or None if the url doesn't point to a local file
+pub(crate) fn resolve_local_url(path: &Path, url: &str) -> Option<(PathBuf, String)> {
+    if url.is_empty() || url.starts_with('/') || url.starts_with('#') {
+        return None;
+    }
+    let base = Url::from_file_path(path).ok()?;
+    let resolved = base.join(url).ok()?;
+    if resolved.scheme() != "file" || Url::parse(url).is_ok() {
+        return None;
+    }
+    let path = resolved.to_file_path().ok()?;
+    Some((path, resolved[Position::AfterPath..].to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    fn css_file(path: PathBuf) -> FileAsset {
+        FileAsset::new(AssetSource::Local(path)).with_options(FileOptions::Css(Default::default()))
+    }
+
+    #[test]
+    fn relative_urls_resolve_next_to_the_stylesheet() {
+        let stylesheet = Path::new("/assets/css/style.css");
+        assert_eq!(
+            resolve_local_url(stylesheet, "../images/logo.png?v=2#icon"),
+            Some((
+                PathBuf::from("/assets/images/logo.png"),
+                "?v=2#icon".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve_local_url(stylesheet, "fonts/inter.woff2"),
+            Some((
+                PathBuf::from("/assets/css/fonts/inter.woff2"),
+                String::new()
+            ))
+        );
+    }
+
+    #[test]
+    fn non_local_urls_are_not_resolved() {
+        let stylesheet = Path::new("/assets/style.css");
+        for url in [
+            "",
+            "/logo.png",
+            "#gradient",
+            "https://example.com/logo.png",
+            "data:image/png;base64,AAAA",
+        ] {
+            assert_eq!(resolve_local_url(stylesheet, url), None, "{url}");
+        }
+    }
+
+    #[test]
+    fn urls_and_imports_are_found_once() {
+        let css = r#"
+            @import "theme.css";
+            .a { background: url(logo.png); }
+            .b { background: url("logo.png"); }
+        "#;
+        assert_eq!(css_urls(css).unwrap(), ["theme.css", "logo.png"]);
+    }
+
+    #[test]
+    fn referenced_files_are_collected() {
+        let folder = write_files(
+            "css-collect",
+            &[
+                (
+                    "style.css",
+                    "@import \"theme.css\"; .a { background: url(images/logo.png#icon) }",
+                ),
+                ("theme.css", ".b { background: url(images/pattern.svg) }"),
+                ("images/logo.png", "png"),
+                ("images/pattern.svg", "<svg/>"),
+            ],
+        );
+
+        let mut file = css_file(folder.join("style.css"));
+        let dependencies = collect_css_dependencies(&mut file).unwrap();
+
+        let sources = dependencies
+            .iter()
+            .map(|dependency| dependency.location().source().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [
+                folder.join("images/pattern.svg"),
+                folder.join("theme.css"),
+                folder.join("images/logo.png"),
+            ]
+            .map(|path| AssetSource::Local(path).to_string())
+        );
+    }
+
+    #[test]
+    fn bundled_imports_are_inlined_instead_of_collected() {
+        let folder = write_files(
+            "css-bundle",
+            &[
+                ("style.css", "@import \"theme.css\";"),
+                ("theme.css", ".b { background: url(pattern.svg) }"),
+                ("pattern.svg", "<svg/>"),
+            ],
+        );
+
+        let mut file = css_file(folder.join("style.css"));
+        file.with_options_mut(|options| {
+            if let FileOptions::Css(options) = options {
+                options.set_bundle(true);
+            }
+        });
+        let dependencies = collect_css_dependencies(&mut file).unwrap();
+
+        assert_eq!(dependencies.len(), 1);
+        let FileOptions::Css(options) = file.options() else {
+            panic!("the options should still be css options");
+        };
+        let imports = options
+            .imports()
+            .iter()
+            .map(CssImport::source)
+            .collect::<Vec<_>>();
+        assert_eq!(imports, [folder.join("theme.css")]);
+    }
+
+    #[test]
+    fn only_small_files_with_a_known_type_and_no_suffix_are_inlined() {
+        let folder = write_files(
+            "css-inline",
+            &[
+                (
+                    "style.css",
+                    ".a { background: url(small.png) }
+                    .b { background: url(sprite.svg#icon) }
+                    .c { background: url(data.custom) }
+                    .d { background: url(large.png) }",
+                ),
+                ("small.png", "png"),
+                ("sprite.svg", "<svg/>"),
+                ("data.custom", "data"),
+                ("large.png", &"a".repeat(100)),
+            ],
+        );
+
+        let mut file = css_file(folder.join("style.css"));
+        file.with_options_mut(|options| {
+            if let FileOptions::Css(options) = options {
+                options.set_inline_below(Some(10));
+            }
+        });
+        let dependencies = collect_css_dependencies(&mut file).unwrap();
+
+        let sources = dependencies
+            .iter()
+            .map(|dependency| dependency.location().source().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            ["sprite.svg", "data.custom", "large.png"]
+                .map(|path| AssetSource::Local(folder.join(path)).to_string())
+        );
+    }
+
+    #[test]
+    fn missing_files_and_import_cycles_are_errors() {
+        let folder = write_files(
+            "css-errors",
+            &[
+                ("missing.css", ".a { background: url(missing.png) }"),
+                ("a.css", "@import \"b.css\";"),
+                ("b.css", "@import \"a.css\";"),
+            ],
+        );
+
+        let missing = collect_css_dependencies(&mut css_file(folder.join("missing.css")))
+            .unwrap_err()
+            .to_string();
+        let cycle = collect_css_dependencies(&mut css_file(folder.join("a.css")))
+            .unwrap_err()
+            .to_string();
+
+        assert!(missing.contains("references missing.png"), "{missing}");
+        assert!(cycle.contains("imports itself"), "{cycle}");
+    }
+}
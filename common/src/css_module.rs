@@ -0,0 +1,102 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use lightningcss::{
+    css_modules::{Config, CssModuleReference, Pattern, Segment},
+    printer::PrinterOptions,
+    stylesheet::{ParserOptions, StyleSheet},
+};
+
+use crate::AssetSource;
+
+/// A class in a css module
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct CssModuleClass {
+    name: String,
+    scoped: String,
+}
+
+impl CssModuleClass {
+    /// Returns the name of the class in the stylesheet
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the scoped class names to use in html. Classes the class composes are included after the scoped class name
+    pub fn scoped(&self) -> &str {
+        &self.scoped
+    }
+}
+
+/// Creates the scope of a css module. The scope is a hash of the package and the path of the stylesheet in the package, so the same stylesheet always has the same class names and stylesheets in different packages never share class names
+pub fn css_module_scope(source: &AssetSource) -> String {
+    let location = match source {
+        AssetSource::Local(path) => {
+            let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+            let path = path.strip_prefix(&manifest_dir).unwrap_or(path);
+            path.to_string_lossy().replace('\\', "/")
+        }
+        AssetSource::Remote(url) => url.to_string(),
+    };
+
+    let mut hash = DefaultHasher::new();
+    std::env::var("CARGO_PKG_NAME").ok().hash(&mut hash);
+    std::env::var("CARGO_PKG_VERSION").ok().hash(&mut hash);
+    location.hash(&mut hash);
+    format!("{:08x}", hash.finish() as u32)
+}
+
+/// Returns the lightningcss config that scopes every class, id and animation name in a css module by adding the scope after the name
+pub fn css_module_config(scope: &str) -> Config<'_> {
+    Config {
+        pattern: Pattern {
+            segments: vec![
+                Segment::Local,
+                Segment::Literal("_"),
+                Segment::Literal(scope),
+            ]
+            .into(),
+        },
+        ..Default::default()
+    }
+}
+
+/// Finds the classes in a css module and the scoped names they are renamed to. The classes are sorted by name
+pub fn css_module_classes(css: &str, scope: &str) -> anyhow::Result<Vec<CssModuleClass>> {
+    let stylesheet = StyleSheet::parse(
+        css,
+        ParserOptions {
+            css_modules: Some(css_module_config(scope)),
+            ..Default::default()
+        },
+    )
+    .map_err(|err| anyhow::anyhow!("Failed to parse css module: {err}"))?;
+    let exports = stylesheet
+        .to_css(PrinterOptions::default())?
+        .exports
+        .unwrap_or_default();
+
+    let mut classes = exports
+        .into_iter()
+        .map(|(name, export)| {
+            let mut scoped = export.name;
+            for composed in export.composes {
+                match composed {
+                    CssModuleReference::Local { name } | CssModuleReference::Global { name } => {
+                        scoped.push(' ');
+                        scoped.push_str(&name);
+                    }
+                    CssModuleReference::Dependency { name: composed, specifier } => {
+                        anyhow::bail!("The class {name} composes {composed} from {specifier}, but composing classes from other files is not supported")
+                    }
+                }
+            }
+            Ok(CssModuleClass { name, scoped })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(classes)
+}
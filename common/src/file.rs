@@ -1,5 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, str::FromStr};
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use crate::{
+    AssetSource, AtlasSheet, CssImport, CssReference, HtmlReference, ImageMetadata, JsModule,
+    JsReference, SvgSpriteOptions,
+};
 
 /// The options for a file asset
 #[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
@@ -16,6 +21,10 @@ pub enum FileOptions {
     Js(JsOptions),
     /// A Json asset
     Json(JsonOptions),
+    /// An html asset
+    Html(HtmlOptions),
+    /// An svg sprite asset
+    SvgSprite(SvgSpriteOptions),
     /// Any other asset
     Other(UnknownFileOptions),
 }
@@ -29,6 +38,8 @@ impl Display for FileOptions {
             Self::Css(options) => write!(f, "{}", options),
             Self::Js(options) => write!(f, "{}", options),
             Self::Json(options) => write!(f, "{}", options),
+            Self::Html(options) => write!(f, "{}", options),
+            Self::SvgSprite(options) => write!(f, "{}", options),
             Self::Other(options) => write!(f, "{}", options),
         }
     }
@@ -38,10 +49,14 @@ impl FileOptions {
     /// Returns the default options for a given extension
     pub fn default_for_extension(extension: Option<&str>) -> Self {
         if let Some(extension) = extension {
-            if extension == CssOptions::EXTENSION {
-                return Self::Css(CssOptions::default());
+            if let Ok(ty) = extension.parse::<CssType>() {
+                let mut options = CssOptions::new();
+                options.set_ty(ty);
+                return Self::Css(options);
             } else if extension == JsonOptions::EXTENSION {
                 return Self::Json(JsonOptions::default());
+            } else if extension == HtmlOptions::EXTENSION || extension == "htm" {
+                return Self::Html(HtmlOptions::default());
             } else if let Ok(ty) = extension.parse::<ImageType>() {
                 return Self::Image(ImageOptions::new(ty, None));
             } else if let Ok(ty) = extension.parse::<VideoType>() {
@@ -64,8 +79,10 @@ impl FileOptions {
             Self::Video(options) => Some(options.ty.extension()),
             Self::Font(options) => Some(options.ty.extension()),
             Self::Css(_) => Some(CssOptions::EXTENSION),
-            Self::Js(js) => Some(js.ty.extension()),
+            Self::Js(js) => Some(js.ty.output_extension()),
             Self::Json(_) => Some(JsonOptions::EXTENSION),
+            Self::Html(_) => Some(HtmlOptions::EXTENSION),
+            Self::SvgSprite(_) => Some(SvgSpriteOptions::EXTENSION),
             Self::Other(extension) => extension.extension.as_deref(),
         }
     }
@@ -84,6 +101,22 @@ pub struct ImageOptions {
     size: Option<(u32, u32)>,
     preload: bool,
     ty: ImageType,
+    transforms: Vec<ImageTransform>,
+    keep_metadata: bool,
+    icon: Option<IconRole>,
+    atlas: Option<AtlasSheet>,
+    min_quality: Option<MinQuality>,
+}
+
+/// The lowest structural similarity an encoded image may have compared to the source image
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy)]
+struct MinQuality(f32);
+
+// Floats don't implement Hash, so we hash the bits of the float instead
+impl std::hash::Hash for MinQuality {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
 }
 
 impl Display for ImageOptions {
@@ -93,12 +126,27 @@ impl Display for ImageOptions {
         } else {
             write!(f, "{}", self.ty)?;
         }
+        for transform in &self.transforms {
+            write!(f, " ({})", transform)?;
+        }
         if self.compress {
             write!(f, " (compressed)")?;
         }
         if self.preload {
             write!(f, " (preload)")?;
         }
+        if self.keep_metadata {
+            write!(f, " (keep metadata)")?;
+        }
+        if let Some(MinQuality(min_quality)) = self.min_quality {
+            write!(f, " (min quality {})", min_quality)?;
+        }
+        if let Some(icon) = &self.icon {
+            write!(f, " ({})", icon)?;
+        }
+        if let Some(atlas) = &self.atlas {
+            write!(f, " (sprite sheet with {} frames)", atlas.frames().len())?;
+        }
         Ok(())
     }
 }
@@ -111,6 +159,11 @@ impl ImageOptions {
             size,
             ty,
             preload: false,
+            transforms: Vec::new(),
+            keep_metadata: false,
+            icon: None,
+            atlas: None,
+            min_quality: None,
         }
     }
 
@@ -153,6 +206,293 @@ impl ImageOptions {
     pub fn set_compress(&mut self, compress: bool) {
         self.compress = compress;
     }
+
+    /// Returns the transforms applied to the image before it is resized and encoded
+    pub fn transforms(&self) -> &[ImageTransform] {
+        &self.transforms
+    }
+
+    /// Adds a transform to the end of the list of transforms applied to the image
+    pub fn add_transform(&mut self, transform: ImageTransform) {
+        self.transforms.push(transform);
+    }
+
+    /// Applies the transforms to the image in order
+    pub fn apply_transforms(
+        &self,
+        mut image: image::DynamicImage,
+    ) -> anyhow::Result<image::DynamicImage> {
+        for transform in &self.transforms {
+            image = transform.apply(image)?;
+        }
+        Ok(image)
+    }
+
+    /// Returns whether the EXIF metadata of the image should be kept in the processed image
+    pub fn keep_metadata(&self) -> bool {
+        self.keep_metadata
+    }
+
+    /// Sets whether the EXIF metadata of the image should be kept in the processed image
+    pub fn set_keep_metadata(&mut self, keep_metadata: bool) {
+        self.keep_metadata = keep_metadata;
+    }
+
+    /// Returns the lowest structural similarity (SSIM) from 0 to 1 the encoded image may have compared to the resized source image
+    pub fn min_quality(&self) -> Option<f32> {
+        self.min_quality.map(|MinQuality(min_quality)| min_quality)
+    }
+
+    /// Sets the lowest structural similarity (SSIM) from 0 to 1 the encoded image may have compared to the resized source image. The encoder quality is lowered as far as possible while staying above this score
+    pub fn set_min_quality(&mut self, min_quality: Option<f32>) {
+        self.min_quality = min_quality.map(MinQuality);
+    }
+
+    /// Returns the role of the image in an icon set if it was generated by the icon asset
+    pub fn icon(&self) -> Option<&IconRole> {
+        self.icon.as_ref()
+    }
+
+    /// Sets the role of the image in an icon set
+    pub fn set_icon(&mut self, icon: Option<IconRole>) {
+        self.icon = icon;
+    }
+
+    /// Returns the sprite sheet the image is drawn from if it was generated by the atlas asset
+    pub fn atlas(&self) -> Option<&AtlasSheet> {
+        self.atlas.as_ref()
+    }
+
+    /// Sets the sprite sheet the image is drawn from
+    pub fn set_atlas(&mut self, atlas: Option<AtlasSheet>) {
+        self.atlas = atlas;
+    }
+
+    /// Decodes the image, converts it to sRGB, rotates it to match the EXIF orientation, and applies the transforms. Svg images are rendered at the size of the image if it is set
+    pub fn decode(&self, bytes: &[u8]) -> anyhow::Result<image::DynamicImage> {
+        if image::guess_format(bytes).is_err() && bytes.trim_ascii_start().starts_with(b"<") {
+            let image = render_svg(bytes, self.size())?;
+            return self.apply_transforms(image);
+        }
+
+        let metadata = ImageMetadata::read(bytes);
+        let image = image::load_from_memory(bytes)?;
+        let image = metadata.apply_orientation(metadata.convert_to_srgb(image));
+        self.apply_transforms(image)
+    }
+
+    /// Replaces [`ImageType::Auto`] with the concrete image type that the source image will be encoded as
+    pub fn resolve_auto(&mut self, source: &AssetSource) -> anyhow::Result<()> {
+        if self.ty == ImageType::Auto {
+            let image = self.decode(&source.read_to_bytes()?)?;
+            self.ty = ImageType::auto_for(&image);
+        }
+        Ok(())
+    }
+}
+
+/// Renders an svg image at the given size or the size the svg declares. The svg keeps its aspect ratio inside the given size
+fn render_svg(bytes: &[u8], size: Option<(u32, u32)>) -> anyhow::Result<image::DynamicImage> {
+    let tree = resvg::usvg::Tree::from_data(bytes, &resvg::usvg::Options::default())?;
+    let svg_size = tree.size();
+    let (width, height) = size.unwrap_or((
+        svg_size.width().ceil() as u32,
+        svg_size.height().ceil() as u32,
+    ));
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow::anyhow!("Invalid svg size {width}x{height}"))?;
+    // Scale the svg to fit inside the image without stretching it and center it on the transparent canvas
+    let scale = (width as f32 / svg_size.width()).min(height as f32 / svg_size.height());
+    let transform = resvg::tiny_skia::Transform::from_scale(scale, scale).post_translate(
+        (width as f32 - svg_size.width() * scale) / 2.0,
+        (height as f32 - svg_size.height() * scale) / 2.0,
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+    // The pixmap stores premultiplied colors
+    let mut rgba = image::RgbaImage::new(width, height);
+    for (pixel, color) in rgba.pixels_mut().zip(pixmap.pixels()) {
+        let color = color.demultiply();
+        *pixel = image::Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+    Ok(image::DynamicImage::ImageRgba8(rgba))
+}
+
+/// The role of an image in an icon set generated by the icon asset
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub enum IconRole {
+    /// A favicon.ico that contains the icon at multiple resolutions
+    Favicon,
+    /// A square png icon. The image is scaled to fit inside the icon without changing its aspect ratio
+    Icon,
+    /// An apple touch icon for iOS home screens. iOS fills transparent pixels with black, so the icon is placed on a solid background
+    AppleTouch {
+        /// The color behind the icon
+        background: [u8; 3],
+    },
+    /// A maskable PWA icon. The image is kept inside the safe zone in the center of the icon so it is never cut off when the platform masks the icon into a circle or other shape
+    Maskable {
+        /// The color behind the icon
+        background: [u8; 3],
+    },
+}
+
+impl IconRole {
+    /// The sizes of the images in a favicon
+    pub const FAVICON_SIZES: [u32; 3] = [16, 32, 48];
+    /// The fraction of a maskable icon that is guaranteed to be visible after it is masked
+    pub const MASKABLE_SAFE_ZONE: f32 = 0.8;
+}
+
+impl Display for IconRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Favicon => write!(f, "favicon"),
+            Self::Icon => write!(f, "icon"),
+            Self::AppleTouch { .. } => write!(f, "apple touch icon"),
+            Self::Maskable { .. } => write!(f, "maskable icon"),
+        }
+    }
+}
+
+/// A transformation applied to an image before it is encoded
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub enum ImageTransform {
+    /// Rotate the image 90 degrees clockwise
+    Rotate90,
+    /// Rotate the image 180 degrees
+    Rotate180,
+    /// Rotate the image 270 degrees clockwise
+    Rotate270,
+    /// Flip the image horizontally
+    FlipHorizontal,
+    /// Flip the image vertically
+    FlipVertical,
+    /// Crop the image to a rectangle
+    Crop {
+        /// The left edge of the rectangle
+        x: u32,
+        /// The top edge of the rectangle
+        y: u32,
+        /// The width of the rectangle
+        width: u32,
+        /// The height of the rectangle
+        height: u32,
+    },
+    /// Convert the image to grayscale
+    Grayscale,
+    /// Blur the image with a gaussian blur of the given standard deviation
+    Blur(f32),
+    /// Brighten the image by the given amount. Negative values darken the image
+    Brightness(i32),
+    /// Adjust the contrast of the image by the given amount. Negative values reduce the contrast
+    Contrast(f32),
+    /// Blend a color over the image
+    Tint {
+        /// The color to blend over the image
+        color: [u8; 3],
+        /// How much of the color to blend in from 0 (none) to 1 (only the color)
+        amount: f32,
+    },
+}
+
+// Floats don't implement Hash, so we hash the bits of the float instead. The transforms are part of the asset hash, so every variant of an image needs a distinct hash
+impl std::hash::Hash for ImageTransform {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => (x, y, width, height).hash(state),
+            Self::Blur(sigma) => sigma.to_bits().hash(state),
+            Self::Brightness(amount) => amount.hash(state),
+            Self::Contrast(amount) => amount.to_bits().hash(state),
+            Self::Tint { color, amount } => (color, amount.to_bits()).hash(state),
+            _ => {}
+        }
+    }
+}
+
+impl Display for ImageTransform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rotate90 => write!(f, "rotate 90"),
+            Self::Rotate180 => write!(f, "rotate 180"),
+            Self::Rotate270 => write!(f, "rotate 270"),
+            Self::FlipHorizontal => write!(f, "flip horizontal"),
+            Self::FlipVertical => write!(f, "flip vertical"),
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => write!(f, "crop {}x{} at {},{}", width, height, x, y),
+            Self::Grayscale => write!(f, "grayscale"),
+            Self::Blur(sigma) => write!(f, "blur {}", sigma),
+            Self::Brightness(amount) => write!(f, "brightness {}", amount),
+            Self::Contrast(amount) => write!(f, "contrast {}", amount),
+            Self::Tint {
+                color: [r, g, b],
+                amount,
+            } => write!(f, "tint #{:02x}{:02x}{:02x} {}", r, g, b, amount),
+        }
+    }
+}
+
+impl ImageTransform {
+    /// Applies the transform to an image. Fails if a crop doesn't fit inside the image
+    pub fn apply(&self, image: image::DynamicImage) -> anyhow::Result<image::DynamicImage> {
+        Ok(match *self {
+            Self::Rotate90 => image.rotate90(),
+            Self::Rotate180 => image.rotate180(),
+            Self::Rotate270 => image.rotate270(),
+            Self::FlipHorizontal => image.fliph(),
+            Self::FlipVertical => image.flipv(),
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let fits = width > 0
+                    && height > 0
+                    && x.checked_add(width)
+                        .is_some_and(|right| right <= image.width())
+                    && y.checked_add(height)
+                        .is_some_and(|bottom| bottom <= image.height());
+                if !fits {
+                    anyhow::bail!(
+                        "Cannot crop {width}x{height} pixels at ({x}, {y}) from a {}x{} image",
+                        image.width(),
+                        image.height()
+                    );
+                }
+                image.crop_imm(x, y, width, height)
+            }
+            // Keep the alpha channel so transparent images stay transparent
+            Self::Grayscale => match image.color().has_alpha() {
+                true => image::DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+                false => image::DynamicImage::ImageLuma8(image.to_luma8()),
+            },
+            Self::Blur(sigma) => image.blur(sigma),
+            Self::Brightness(amount) => image.brighten(amount),
+            Self::Contrast(amount) => image.adjust_contrast(amount),
+            Self::Tint { color, amount } => {
+                let amount = amount.clamp(0.0, 1.0);
+                let mut image = image.into_rgba8();
+                for pixel in image.pixels_mut() {
+                    for (channel, tint) in pixel.0.iter_mut().zip(color) {
+                        *channel =
+                            (*channel as f32 * (1.0 - amount) + tint as f32 * amount).round() as u8;
+                    }
+                }
+                image::DynamicImage::ImageRgba8(image)
+            }
+        })
+    }
 }
 
 /// The type of an image
@@ -166,6 +506,18 @@ pub enum ImageType {
     Avif,
     /// A webp image
     Webp,
+    /// A gif image. Animated gifs keep all of their frames
+    Gif,
+    /// A bmp image
+    Bmp,
+    /// A tiff image
+    Tiff,
+    /// An ico image
+    Ico,
+    /// A qoi image
+    Qoi,
+    /// Choose the image type based on the contents of the image
+    Auto,
 }
 
 impl ImageType {
@@ -176,6 +528,54 @@ impl ImageType {
             Self::Jpg => "jpg",
             Self::Avif => "avif",
             Self::Webp => "webp",
+            Self::Gif => "gif",
+            Self::Bmp => "bmp",
+            Self::Tiff => "tiff",
+            Self::Ico => "ico",
+            Self::Qoi => "qoi",
+            Self::Auto => "auto",
+        }
+    }
+
+    /// Picks the image type an [`ImageType::Auto`] image should be encoded as. The choice only depends on the pixels of the image so the macro and the CLI always agree on the extension.
+    ///
+    /// - Images with a small palette are encoded as a quantized png which is lossless for that palette
+    /// - Flat color images like screenshots and illustrations are encoded as a lossless webp
+    /// - Photographic images with transparency are encoded as an avif which keeps the alpha channel
+    /// - Other photographic images are encoded as a jpg
+    pub fn auto_for(image: &image::DynamicImage) -> Self {
+        const MAX_PALETTE_SIZE: usize = 256;
+
+        let image = image.to_rgba8();
+        let width = image.width();
+
+        let mut transparent = false;
+        let mut palette = HashSet::new();
+        let mut same_as_neighbor = 0u64;
+        let mut neighbors = 0u64;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            transparent |= pixel[3] != u8::MAX;
+            if palette.len() <= MAX_PALETTE_SIZE {
+                palette.insert(pixel.0);
+            }
+            if x + 1 < width {
+                neighbors += 1;
+                if pixel == image.get_pixel(x + 1, y) {
+                    same_as_neighbor += 1;
+                }
+            }
+        }
+
+        if palette.len() <= MAX_PALETTE_SIZE {
+            return Self::Png;
+        }
+
+        // Flat color images repeat the same color across neighboring pixels much more often than photographs
+        let photographic = same_as_neighbor * 2 < neighbors;
+        match (photographic, transparent) {
+            (false, _) => Self::Webp,
+            (true, true) => Self::Avif,
+            (true, false) => Self::Jpg,
         }
     }
 }
@@ -195,6 +595,11 @@ impl FromStr for ImageType {
             "jpg" | "jpeg" => Ok(Self::Jpg),
             "avif" => Ok(Self::Avif),
             "webp" => Ok(Self::Webp),
+            "gif" => Ok(Self::Gif),
+            "bmp" => Ok(Self::Bmp),
+            "tif" | "tiff" => Ok(Self::Tiff),
+            "ico" => Ok(Self::Ico),
+            "qoi" => Ok(Self::Qoi),
             _ => Err(()),
         }
     }
@@ -375,11 +780,63 @@ impl Display for FontType {
     }
 }
 
+/// The language a stylesheet is written in
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Hash, Default)]
+pub enum CssType {
+    /// A css stylesheet
+    #[default]
+    Css,
+    /// A stylesheet in the SCSS syntax of Sass
+    Scss,
+    /// A stylesheet in the indented syntax of Sass
+    Sass,
+}
+
+impl CssType {
+    /// Returns the extension for this css type
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Css => "css",
+            Self::Scss => "scss",
+            Self::Sass => "sass",
+        }
+    }
+}
+
+impl FromStr for CssType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "css" => Ok(Self::Css),
+            "scss" => Ok(Self::Scss),
+            "sass" => Ok(Self::Sass),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for CssType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
 /// The options for a css asset
 #[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
 pub struct CssOptions {
+    ty: CssType,
     minify: bool,
     preload: bool,
+    targets: Option<String>,
+    module_scope: Option<String>,
+    references: Vec<CssReference>,
+    bundle: bool,
+    imports: Vec<CssImport>,
+    purge: bool,
+    safelist: Vec<String>,
+    inline_below: Option<u64>,
+    source_map: bool,
 }
 
 impl Default for CssOptions {
@@ -393,9 +850,33 @@ impl Display for CssOptions {
         if self.minify {
             write!(f, "minified")?;
         }
+        if self.ty != CssType::Css {
+            write!(f, " (compiled from {})", self.ty)?;
+        }
         if self.preload {
             write!(f, " (preload)")?;
         }
+        if let Some(targets) = &self.targets {
+            write!(f, " (targets {})", targets)?;
+        }
+        if let Some(scope) = &self.module_scope {
+            write!(f, " (module {})", scope)?;
+        }
+        if !self.references.is_empty() {
+            write!(f, " ({} references)", self.references.len())?;
+        }
+        if self.bundle {
+            write!(f, " (bundled)")?;
+        }
+        if self.purge {
+            write!(f, " (purged)")?;
+        }
+        if let Some(inline_below) = self.inline_below {
+            write!(f, " (inline below {} bytes)", inline_below)?;
+        }
+        if self.source_map {
+            write!(f, " (source map)")?;
+        }
         Ok(())
     }
 }
@@ -406,11 +887,31 @@ impl CssOptions {
     /// Creates a new css options struct
     pub const fn new() -> Self {
         Self {
+            ty: CssType::Css,
             minify: true,
             preload: false,
+            targets: None,
+            module_scope: None,
+            references: Vec::new(),
+            bundle: false,
+            imports: Vec::new(),
+            purge: false,
+            safelist: Vec::new(),
+            inline_below: None,
+            source_map: false,
         }
     }
 
+    /// Returns the language the stylesheet is written in
+    pub fn ty(&self) -> CssType {
+        self.ty
+    }
+
+    /// Sets the language the stylesheet is written in. Sass and SCSS stylesheets are compiled to css before they are processed
+    pub fn set_ty(&mut self, ty: CssType) {
+        self.ty = ty;
+    }
+
     /// Returns whether the css should be minified
     pub fn minify(&self) -> bool {
         self.minify
@@ -430,6 +931,96 @@ impl CssOptions {
     pub fn set_preload(&mut self, preload: bool) {
         self.preload = preload;
     }
+
+    /// Returns the browserslist query for the browsers the css should support
+    pub fn targets(&self) -> Option<&str> {
+        self.targets.as_deref()
+    }
+
+    /// Sets the browserslist query for the browsers the css should support. Vendor prefixes are added and modern syntax like nesting is lowered for these browsers
+    pub fn set_targets(&mut self, targets: Option<String>) {
+        self.targets = targets;
+    }
+
+    /// Returns the scope of the css module if the css is a css module
+    pub fn module_scope(&self) -> Option<&str> {
+        self.module_scope.as_deref()
+    }
+
+    /// Sets the scope of the css module. If the css is a css module, every class, id and animation name is renamed with the scope so it doesn't conflict with names in other stylesheets
+    pub fn set_module_scope(&mut self, module_scope: Option<String>) {
+        self.module_scope = module_scope;
+    }
+
+    /// Returns the references to other files in the css that are rewritten to the location the files are served at
+    pub fn references(&self) -> &[CssReference] {
+        &self.references
+    }
+
+    /// Sets the references to other files in the css that are rewritten to the location the files are served at
+    pub fn set_references(&mut self, references: Vec<CssReference>) {
+        self.references = references;
+    }
+
+    /// Returns whether the stylesheets the css imports should be inlined into one stylesheet
+    pub fn bundle(&self) -> bool {
+        self.bundle
+    }
+
+    /// Sets whether the stylesheets the css imports should be inlined into one stylesheet
+    pub fn set_bundle(&mut self, bundle: bool) {
+        self.bundle = bundle;
+    }
+
+    /// Returns the stylesheets that are inlined into the css. These are the stylesheets in the bundle and the files a Sass stylesheet loads
+    pub fn imports(&self) -> &[CssImport] {
+        &self.imports
+    }
+
+    /// Sets the stylesheets that are inlined into the css
+    pub fn set_imports(&mut self, imports: Vec<CssImport>) {
+        self.imports = imports;
+    }
+
+    /// Returns whether style rules that use classes the application never uses should be removed
+    pub fn purge(&self) -> bool {
+        self.purge
+    }
+
+    /// Sets whether style rules that use classes the application never uses should be removed. The used classes are the classes collected with the `classes!` macro and the classes in the safelist. The name of the stylesheet doesn't change when the used classes do
+    pub fn set_purge(&mut self, purge: bool) {
+        self.purge = purge;
+    }
+
+    /// Returns the classes that are never purged
+    pub fn safelist(&self) -> &[String] {
+        &self.safelist
+    }
+
+    /// Sets the classes that are never purged. Use it for classes that are only added at runtime
+    pub fn set_safelist(&mut self, safelist: Vec<String>) {
+        self.safelist = safelist;
+    }
+
+    /// Returns the size in bytes below which files the css references with `url()` are inlined as data urls
+    pub fn inline_below(&self) -> Option<u64> {
+        self.inline_below
+    }
+
+    /// Sets the size in bytes below which files the css references with `url()` are inlined as data urls. Larger files are collected as separate assets
+    pub fn set_inline_below(&mut self, inline_below: Option<u64>) {
+        self.inline_below = inline_below;
+    }
+
+    /// Returns whether a source map should be generated for the css
+    pub fn source_map(&self) -> bool {
+        self.source_map
+    }
+
+    /// Sets whether a source map should be generated for the css. The map is only generated if the css is transformed, for example when it is minified
+    pub fn set_source_map(&mut self, source_map: bool) {
+        self.source_map = source_map;
+    }
 }
 
 /// The type of a Javascript asset
@@ -438,7 +1029,12 @@ pub enum JsType {
     /// A js asset
     #[default]
     Js,
-    // TODO: support ts files
+    /// A TypeScript asset
+    Ts,
+    /// A TypeScript asset with JSX
+    Tsx,
+    /// A js module asset
+    Mjs,
 }
 
 impl JsType {
@@ -446,6 +1042,17 @@ impl JsType {
     pub fn extension(&self) -> &'static str {
         match self {
             Self::Js => "js",
+            Self::Ts => "ts",
+            Self::Tsx => "tsx",
+            Self::Mjs => "mjs",
+        }
+    }
+
+    /// Returns the extension of the js this type is compiled to. TypeScript is compiled to js
+    pub fn output_extension(&self) -> &'static str {
+        match self {
+            Self::Js | Self::Ts | Self::Tsx => "js",
+            Self::Mjs => "mjs",
         }
     }
 }
@@ -456,6 +1063,9 @@ impl FromStr for JsType {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "js" => Ok(Self::Js),
+            "ts" => Ok(Self::Ts),
+            "tsx" => Ok(Self::Tsx),
+            "mjs" => Ok(Self::Mjs),
             _ => Err(()),
         }
     }
@@ -467,17 +1077,105 @@ impl Display for JsType {
     }
 }
 
+/// The version of the ECMAScript standard js is compiled to
+#[derive(
+    Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EsVersion {
+    /// ES5
+    Es5,
+    /// ES2015
+    Es2015,
+    /// ES2016
+    Es2016,
+    /// ES2017
+    Es2017,
+    /// ES2018
+    Es2018,
+    /// ES2019
+    Es2019,
+    /// ES2020
+    Es2020,
+    /// ES2021
+    Es2021,
+    /// ES2022
+    Es2022,
+    /// The latest version of the standard. Js that targets the latest version is kept as it is written
+    #[default]
+    EsNext,
+}
+
+impl FromStr for EsVersion {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "es5" => Ok(Self::Es5),
+            "es2015" | "es6" => Ok(Self::Es2015),
+            "es2016" => Ok(Self::Es2016),
+            "es2017" => Ok(Self::Es2017),
+            "es2018" => Ok(Self::Es2018),
+            "es2019" => Ok(Self::Es2019),
+            "es2020" => Ok(Self::Es2020),
+            "es2021" => Ok(Self::Es2021),
+            "es2022" => Ok(Self::Es2022),
+            "esnext" => Ok(Self::EsNext),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for EsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = match self {
+            Self::Es5 => "es5",
+            Self::Es2015 => "es2015",
+            Self::Es2016 => "es2016",
+            Self::Es2017 => "es2017",
+            Self::Es2018 => "es2018",
+            Self::Es2019 => "es2019",
+            Self::Es2020 => "es2020",
+            Self::Es2021 => "es2021",
+            Self::Es2022 => "es2022",
+            Self::EsNext => "esnext",
+        };
+        write!(f, "{}", version)
+    }
+}
+
 /// The options for a Javascript asset
 #[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash, Default)]
 pub struct JsOptions {
     ty: JsType,
     minify: bool,
     preload: bool,
+    module: bool,
+    import_specifier: Option<String>,
+    source_map: bool,
+    target: EsVersion,
+    bundle: bool,
+    modules: Vec<JsModule>,
+    externals: Vec<String>,
+    references: Vec<JsReference>,
+    defines: Vec<(String, String)>,
 }
 
 impl Display for JsOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "js")?;
+        write!(f, "{}", self.ty)?;
+        if self.module && self.ty != JsType::Mjs {
+            write!(f, " (module)")?;
+        }
+        if self.target != EsVersion::EsNext {
+            write!(f, " (target {})", self.target)?;
+        }
+        if self.bundle {
+            write!(f, " (bundled)")?;
+        }
+        if !self.defines.is_empty() {
+            write!(f, " ({} defines)", self.defines.len())?;
+        }
         Ok(())
     }
 }
@@ -489,9 +1187,24 @@ impl JsOptions {
             ty,
             preload: false,
             minify: true,
+            // Mjs is always loaded as a module
+            module: ty == JsType::Mjs,
+            import_specifier: None,
+            source_map: false,
+            target: EsVersion::EsNext,
+            bundle: false,
+            modules: Vec::new(),
+            externals: Vec::new(),
+            references: Vec::new(),
+            defines: Vec::new(),
         }
     }
 
+    /// Returns the type of the js
+    pub fn ty(&self) -> JsType {
+        self.ty
+    }
+
     /// Returns whether the js should be preloaded
     pub fn preload(&self) -> bool {
         self.preload
@@ -502,6 +1215,26 @@ impl JsOptions {
         self.preload = preload;
     }
 
+    /// Returns whether the js is an ES module instead of a classic script
+    pub fn module(&self) -> bool {
+        self.module
+    }
+
+    /// Sets whether the js is an ES module instead of a classic script. Modules are compiled as modules and preloaded with `modulepreload`
+    pub fn set_module(&mut self, module: bool) {
+        self.module = module;
+    }
+
+    /// Returns the bare specifier the js is mapped to in the import map
+    pub fn import_specifier(&self) -> Option<&str> {
+        self.import_specifier.as_deref()
+    }
+
+    /// Sets the bare specifier the js is mapped to in the import map. Modules can import the js with the specifier, like `import { debounce } from "lodash"`, even though it is served at a hashed location
+    pub fn set_import_specifier(&mut self, import_specifier: Option<String>) {
+        self.import_specifier = import_specifier;
+    }
+
     /// Returns if the js should be minified
     pub fn minify(&self) -> bool {
         self.minify
@@ -511,6 +1244,76 @@ impl JsOptions {
     pub fn set_minify(&mut self, minify: bool) {
         self.minify = minify;
     }
+
+    /// Returns whether a source map should be generated for the js
+    pub fn source_map(&self) -> bool {
+        self.source_map
+    }
+
+    /// Sets whether a source map should be generated for the js. The map is only generated if the js is minified
+    pub fn set_source_map(&mut self, source_map: bool) {
+        self.source_map = source_map;
+    }
+
+    /// Returns the version of the standard the js is compiled to
+    pub fn target(&self) -> EsVersion {
+        self.target
+    }
+
+    /// Sets the version of the standard the js is compiled to. Syntax that is newer than the target, like optional chaining or class fields, is transformed into syntax the target supports
+    pub fn set_target(&mut self, target: EsVersion) {
+        self.target = target;
+    }
+
+    /// Returns whether the modules the js imports should be bundled into it
+    pub fn bundle(&self) -> bool {
+        self.bundle
+    }
+
+    /// Sets whether the modules the js imports should be bundled into it. Unused exports are removed from the bundle when it is minified
+    pub fn set_bundle(&mut self, bundle: bool) {
+        self.bundle = bundle;
+    }
+
+    /// Returns the modules that are bundled into the js
+    pub fn modules(&self) -> &[JsModule] {
+        &self.modules
+    }
+
+    /// Sets the modules that are bundled into the js
+    pub fn set_modules(&mut self, modules: Vec<JsModule>) {
+        self.modules = modules;
+    }
+
+    /// Returns the bare specifiers the bundled js loads from the import map at runtime
+    pub fn externals(&self) -> &[String] {
+        &self.externals
+    }
+
+    /// Sets the bare specifiers the bundled js loads from the import map at runtime. They are part of the options, so the name of the bundle changes when they do
+    pub fn set_externals(&mut self, externals: Vec<String>) {
+        self.externals = externals;
+    }
+
+    /// Returns the chunks and web workers the bundled js loads from other files
+    pub fn references(&self) -> &[JsReference] {
+        &self.references
+    }
+
+    /// Sets the chunks and web workers the bundled js loads from other files
+    pub fn set_references(&mut self, references: Vec<JsReference>) {
+        self.references = references;
+    }
+
+    /// Returns the globals that are replaced with a js expression when the js is compiled
+    pub fn defines(&self) -> &[(String, String)] {
+        &self.defines
+    }
+
+    /// Sets the globals that are replaced with a js expression when the js is compiled. The name of a global can be a member expression like `process.env.NODE_ENV`
+    pub fn set_defines(&mut self, defines: Vec<(String, String)>) {
+        self.defines = defines;
+    }
 }
 
 /// The options for a Json asset
@@ -546,6 +1349,62 @@ impl JsonOptions {
     }
 }
 
+/// The options for an html asset
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct HtmlOptions {
+    minify: bool,
+    references: Vec<HtmlReference>,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for HtmlOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.minify {
+            write!(f, "minified ")?;
+        }
+        write!(f, "html")?;
+        Ok(())
+    }
+}
+
+impl HtmlOptions {
+    /// The extension of the html asset
+    pub const EXTENSION: &'static str = "html";
+
+    /// Creates a new html options struct
+    pub fn new() -> Self {
+        Self {
+            minify: true,
+            references: Vec::new(),
+        }
+    }
+
+    /// Returns whether the html should be minified
+    pub fn minify(&self) -> bool {
+        self.minify
+    }
+
+    /// Sets whether the html should be minified. Inline `<style>` and `<script>` blocks are minified with the html
+    pub fn set_minify(&mut self, minify: bool) {
+        self.minify = minify;
+    }
+
+    /// Returns the local files the html references with `src` or `href` attributes and the locations they are served at
+    pub fn references(&self) -> &[HtmlReference] {
+        &self.references
+    }
+
+    /// Sets the local files the html references with `src` or `href` attributes and the locations they are served at
+    pub fn set_references(&mut self, references: Vec<HtmlReference>) {
+        self.references = references;
+    }
+}
+
 /// The options for an unknown file asset
 #[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
 pub struct UnknownFileOptions {
@@ -572,3 +1431,107 @@ impl UnknownFileOptions {
         self.extension.as_deref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crop(x: u32, y: u32, width: u32, height: u32) -> ImageTransform {
+        ImageTransform::Crop {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    const WIDE_SVG: &[u8] = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="red"/></svg>"#;
+
+    #[test]
+    fn svg_keeps_its_aspect_ratio() {
+        let image = render_svg(WIDE_SVG, Some((16, 16))).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), (16, 16));
+
+        // The wide svg is centered vertically, so the top and bottom of the square are transparent
+        assert_eq!(image.get_pixel(8, 1).0[3], 0);
+        assert_eq!(image.get_pixel(8, 14).0[3], 0);
+        assert_eq!(image.get_pixel(0, 8).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(15, 8).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(8, 5).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(8, 11).0, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn svg_renders_at_its_own_size() {
+        let image = render_svg(WIDE_SVG, None).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 10));
+    }
+
+    #[test]
+    fn crop_inside_the_image() {
+        let image = image::DynamicImage::new_rgba8(10, 8);
+        let cropped = crop(2, 3, 8, 5).apply(image).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (8, 5));
+    }
+
+    #[test]
+    fn crop_outside_the_image_is_an_error() {
+        for transform in [
+            crop(20, 20, 4, 4),
+            crop(8, 0, 4, 4),
+            crop(0, 6, 4, 4),
+            crop(0, 0, 0, 4),
+            crop(u32::MAX, 0, 4, 4),
+        ] {
+            let err = transform
+                .apply(image::DynamicImage::new_rgba8(10, 8))
+                .unwrap_err();
+            assert!(err.to_string().contains("from a 10x8 image"), "{err}");
+        }
+    }
+
+    /// Creates an image where every pixel is different from its neighbors, like a photograph
+    fn noisy_image(alpha: u8) -> image::DynamicImage {
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(32, 32, |x, y| {
+            image::Rgba([
+                (x * 37 + y * 11) as u8,
+                (x * y) as u8,
+                (x + y * 3) as u8,
+                alpha,
+            ])
+        }))
+    }
+
+    #[test]
+    fn images_with_a_small_palette_are_pngs() {
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(32, 32, |x, _| {
+            if x % 2 == 0 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 255, 128])
+            }
+        }));
+
+        assert_eq!(ImageType::auto_for(&image), ImageType::Png);
+    }
+
+    #[test]
+    fn flat_color_images_are_webps() {
+        // Runs of four pixels share a color, but there are more colors than fit in a palette
+        let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(64, 64, |x, y| {
+            image::Rgb([(x / 4) as u8 * 16, y as u8 * 4, 0])
+        }));
+
+        assert_eq!(ImageType::auto_for(&image), ImageType::Webp);
+    }
+
+    #[test]
+    fn opaque_photographs_are_jpgs() {
+        assert_eq!(ImageType::auto_for(&noisy_image(255)), ImageType::Jpg);
+    }
+
+    #[test]
+    fn transparent_photographs_are_avifs() {
+        assert_eq!(ImageType::auto_for(&noisy_image(128)), ImageType::Avif);
+    }
+}
@@ -0,0 +1,605 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collect_css_dependencies, css::resolve_local_url, AssetSource, FileAsset, FileOptions,
+};
+
+/// The attributes that reference other files in an html document
+pub const HTML_REFERENCE_ATTRIBUTES: &[&str] = &["src", "href"];
+
+/// A reference to another file in an html document with a `src` or `href` attribute
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct HtmlReference {
+    url: String,
+    location: String,
+}
+
+impl HtmlReference {
+    /// Creates a new html reference
+    pub fn new(url: impl Into<String>, location: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            location: location.into(),
+        }
+    }
+
+    /// Returns the url as it is written in the html
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the location the url is rewritten to
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+/// The elements and attributes that load a file when the document is rendered. Other references, like `<a href>`, point to a page the user may navigate to
+const RESOURCE_ATTRIBUTES: &[(&str, &str)] = &[
+    ("audio", "src"),
+    ("embed", "src"),
+    ("iframe", "src"),
+    ("img", "src"),
+    ("input", "src"),
+    ("link", "href"),
+    ("script", "src"),
+    ("source", "src"),
+    ("track", "src"),
+    ("video", "src"),
+];
+
+/// Returns every url in an html document that is referenced with a `src` or `href` attribute
+pub fn html_urls(html: &str) -> Vec<String> {
+    collect_urls(html, |_, _| true)
+}
+
+/// Returns every url in an html document that is loaded when the document is rendered, like the `src` of an `<img>` or the `href` of a `<link>`
+pub fn html_resource_urls(html: &str) -> Vec<String> {
+    collect_urls(html, |tag, attribute| {
+        RESOURCE_ATTRIBUTES.iter().any(|(element, name)| {
+            tag.name() == *element && attribute.name().eq_ignore_ascii_case(name)
+        })
+    })
+}
+
+fn collect_urls(html: &str, filter: impl Fn(&HtmlTag, &HtmlAttribute) -> bool) -> Vec<String> {
+    let mut urls = Vec::new();
+    for token in tokenize_html(html) {
+        let HtmlToken::StartTag(tag) = token else {
+            continue;
+        };
+        for attribute in tag.attributes() {
+            if !attribute.is_reference() || !filter(&tag, attribute) {
+                continue;
+            }
+            if let Some(url) = attribute.value() {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+    }
+    urls
+}
+
+/// The elements whose content is not parsed as html
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// A piece of an html document. Joining the source of every token reproduces the document
+#[derive(Debug, PartialEq, Clone)]
+pub enum HtmlToken<'a> {
+    /// Text between tags
+    Text(&'a str),
+    /// The content of a `<script>`, `<style>`, `<textarea>` or `<title>` element
+    RawText {
+        /// The lowercase name of the element
+        element: String,
+        /// The unparsed content of the element
+        content: &'a str,
+    },
+    /// A comment including the `<!--` and `-->` delimiters
+    Comment(&'a str),
+    /// A doctype, processing instruction or cdata section
+    Declaration(&'a str),
+    /// A start tag with its attributes
+    StartTag(HtmlTag<'a>),
+    /// An end tag
+    EndTag {
+        /// The lowercase name of the element
+        name: String,
+        /// The source of the tag
+        source: &'a str,
+    },
+}
+
+impl<'a> HtmlToken<'a> {
+    /// Returns the source of the token as it is written in the document
+    pub fn source(&self) -> &'a str {
+        match self {
+            Self::Text(source) | Self::Comment(source) | Self::Declaration(source) => source,
+            Self::RawText { content, .. } => content,
+            Self::StartTag(tag) => tag.source(),
+            Self::EndTag { source, .. } => source,
+        }
+    }
+}
+
+/// A start tag in an html document
+#[derive(Debug, PartialEq, Clone)]
+pub struct HtmlTag<'a> {
+    source: &'a str,
+    name: String,
+    attributes: Vec<HtmlAttribute<'a>>,
+}
+
+impl<'a> HtmlTag<'a> {
+    /// Returns the source of the tag including the angle brackets
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the lowercase name of the element
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the attributes of the tag in the order they are written
+    pub fn attributes(&self) -> &[HtmlAttribute<'a>] {
+        &self.attributes
+    }
+
+    /// Returns the first attribute with the given name
+    pub fn attribute(&self, name: &str) -> Option<&HtmlAttribute<'a>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns true if the tag ends with `/>`
+    pub fn self_closing(&self) -> bool {
+        self.source.ends_with("/>")
+    }
+}
+
+/// An attribute of a start tag
+#[derive(Debug, PartialEq, Clone)]
+pub struct HtmlAttribute<'a> {
+    name: &'a str,
+    value: Option<&'a str>,
+    value_span: Range<usize>,
+}
+
+impl<'a> HtmlAttribute<'a> {
+    /// Returns the name of the attribute as it is written in the tag
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns true if the attribute references another file
+    pub fn is_reference(&self) -> bool {
+        HTML_REFERENCE_ATTRIBUTES
+            .iter()
+            .any(|name| self.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of the attribute with character references decoded, or None if the attribute has no value
+    pub fn value(&self) -> Option<String> {
+        self.value.map(decode_character_references)
+    }
+
+    /// Returns the span of the value in the source of the tag, including any quotes
+    pub fn value_span(&self) -> Range<usize> {
+        self.value_span.clone()
+    }
+}
+
+/// Escapes a string so it can be written as a double quoted attribute value
+pub fn escape_html_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+fn decode_character_references(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    value
+        .replace("&quot;", "\"")
+        .replace("&#34;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Splits an html document into tokens. The tokenizer is lenient: markup it doesn't understand is returned as text
+pub fn tokenize_html(html: &str) -> HtmlTokens<'_> {
+    HtmlTokens {
+        html,
+        position: 0,
+        raw_text: None,
+    }
+}
+
+/// An iterator over the tokens of an html document created with [`tokenize_html`]
+pub struct HtmlTokens<'a> {
+    html: &'a str,
+    position: usize,
+    raw_text: Option<String>,
+}
+
+impl<'a> Iterator for HtmlTokens<'a> {
+    type Item = HtmlToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.html[self.position..];
+        if let Some(element) = self.raw_text.take() {
+            let end = find_end_tag(rest, &element).unwrap_or(rest.len());
+            if end > 0 {
+                self.position += end;
+                return Some(HtmlToken::RawText {
+                    element,
+                    content: &rest[..end],
+                });
+            }
+        }
+        if rest.is_empty() {
+            return None;
+        }
+
+        let (length, token) = if let Some(comment) = rest.strip_prefix("<!--") {
+            let length = comment.find("-->").map_or(rest.len(), |end| end + 7);
+            (length, HtmlToken::Comment(&rest[..length]))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let length = rest.find('>').map_or(rest.len(), |end| end + 1);
+            (length, HtmlToken::Declaration(&rest[..length]))
+        } else if starts_tag(rest, 2) && rest.starts_with("</") {
+            let length = rest.find('>').map_or(rest.len(), |end| end + 1);
+            let name = tag_name(&rest[2..]).to_ascii_lowercase();
+            let source = &rest[..length];
+            (length, HtmlToken::EndTag { name, source })
+        } else if starts_tag(rest, 1) {
+            let tag = parse_start_tag(rest);
+            if RAW_TEXT_ELEMENTS.contains(&tag.name()) && !tag.self_closing() {
+                self.raw_text = Some(tag.name().to_string());
+            }
+            (tag.source().len(), HtmlToken::StartTag(tag))
+        } else {
+            let length = rest[1..]
+                .match_indices('<')
+                .map(|(index, _)| index + 1)
+                .find(|&index| starts_markup(&rest[index..]))
+                .unwrap_or(rest.len());
+            (length, HtmlToken::Text(&rest[..length]))
+        };
+        self.position += length;
+        Some(token)
+    }
+}
+
+fn starts_tag(rest: &str, name_start: usize) -> bool {
+    rest.starts_with('<')
+        && rest
+            .get(name_start..)
+            .is_some_and(|name| name.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+fn starts_markup(rest: &str) -> bool {
+    rest.starts_with("<!") || rest.starts_with("<?") || starts_tag(rest, 1) || starts_tag(rest, 2)
+}
+
+fn tag_name(rest: &str) -> &str {
+    let end = rest
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(rest.len());
+    &rest[..end]
+}
+
+fn find_end_tag(rest: &str, element: &str) -> Option<usize> {
+    rest.match_indices("</")
+        .map(|(index, _)| index)
+        .find(|&index| {
+            let name = &rest[index + 2..];
+            name.len() >= element.len()
+                && name.is_char_boundary(element.len())
+                && name[..element.len()].eq_ignore_ascii_case(element)
+                && name[element.len()..]
+                    .starts_with(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        })
+}
+
+fn parse_start_tag(rest: &str) -> HtmlTag<'_> {
+    let name = tag_name(&rest[1..]);
+    let mut position = 1 + name.len();
+    let mut attributes = Vec::new();
+    let bytes = rest.as_bytes();
+    let skip_whitespace = |mut position: usize| {
+        while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+            position += 1;
+        }
+        position
+    };
+
+    loop {
+        position = skip_whitespace(position);
+        match bytes.get(position) {
+            None => break,
+            Some(b'>') => {
+                position += 1;
+                break;
+            }
+            Some(b'/') => {
+                position += 1;
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        // The first character of a name may be an `=`
+        let name_start = position;
+        position += 1;
+        while position < bytes.len()
+            && !bytes[position].is_ascii_whitespace()
+            && !matches!(bytes[position], b'/' | b'>' | b'=')
+        {
+            position += 1;
+        }
+        let name = &rest[name_start..position];
+
+        let after_name = skip_whitespace(position);
+        if bytes.get(after_name) != Some(&b'=') {
+            attributes.push(HtmlAttribute {
+                name,
+                value: None,
+                value_span: position..position,
+            });
+            continue;
+        }
+        let value_start = skip_whitespace(after_name + 1);
+        let (value, value_end) = match bytes.get(value_start) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let value_end = rest[value_start + 1..]
+                    .find(quote as char)
+                    .map_or(rest.len(), |end| value_start + 1 + end);
+                (
+                    &rest[value_start + 1..value_end],
+                    (value_end + 1).min(rest.len()),
+                )
+            }
+            _ => {
+                let value_end = rest[value_start..]
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .map_or(rest.len(), |end| value_start + end);
+                (&rest[value_start..value_end], value_end)
+            }
+        };
+        attributes.push(HtmlAttribute {
+            name,
+            value: Some(value),
+            value_span: value_start..value_end,
+        });
+        position = value_end;
+    }
+
+    HtmlTag {
+        source: &rest[..position],
+        name: name.to_ascii_lowercase(),
+        attributes,
+    }
+}
+
+/// Finds the local files an html asset references with `src` or `href` attributes and collects them as file assets with the default options. The references in the html are rewritten to the location the files are served at. Stylesheets and html documents that are referenced are searched for references too. Returns every file the html depends on
+///
+/// Only local html is searched. Urls with a scheme, absolute paths and fragments are left as they are. Files that are loaded when the document is rendered must exist, but links to other pages that don't point to a file, like routes of the application, are left as they are
+pub fn collect_html_dependencies(file: &mut FileAsset) -> anyhow::Result<Vec<FileAsset>> {
+    let mut dependencies = Vec::new();
+    collect_html_dependencies_inner(file, &mut Vec::new(), &mut dependencies)?;
+    Ok(dependencies)
+}
+
+fn collect_html_dependencies_inner(
+    file: &mut FileAsset,
+    stack: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<FileAsset>,
+) -> anyhow::Result<()> {
+    let FileOptions::Html(_) = file.options() else {
+        return Ok(());
+    };
+    let AssetSource::Local(path) = file.location().source() else {
+        return Ok(());
+    };
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if stack.contains(&path) {
+        anyhow::bail!("{} references itself", path.display());
+    }
+
+    stack.push(path.clone());
+    let references = collect_references(&path, stack, dependencies)?;
+    stack.pop();
+
+    file.with_options_mut(|options| {
+        if let FileOptions::Html(options) = options {
+            options.set_references(references);
+        }
+    });
+
+    Ok(())
+}
+
+fn collect_references(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<FileAsset>,
+) -> anyhow::Result<Vec<HtmlReference>> {
+    let html = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let urls = html_urls(&html);
+    let resources = html_resource_urls(&html);
+
+    let manganis_support = std::env::var("MANGANIS_SUPPORT").is_ok();
+    let mut references = Vec::new();
+    for url in urls {
+        let Some((dependency_path, suffix)) = resolve_local_url(path, &url) else {
+            continue;
+        };
+        // Links to other pages may point to a route instead of a file
+        if !dependency_path.is_file() && !resources.contains(&url) {
+            continue;
+        }
+        let dependency_path = dependency_path.canonicalize().with_context(|| {
+            format!(
+                "{} references {url}, but {} does not exist",
+                path.display(),
+                dependency_path.display()
+            )
+        })?;
+        if !dependency_path.is_file() {
+            anyhow::bail!(
+                "{} references {url}, but {} is not a file",
+                path.display(),
+                dependency_path.display()
+            );
+        }
+
+        let mut dependency = FileAsset::new(AssetSource::Local(dependency_path));
+        collect_html_dependencies_inner(&mut dependency, stack, dependencies)?;
+        for css_dependency in collect_css_dependencies(&mut dependency)? {
+            if !dependencies.contains(&css_dependency) {
+                dependencies.push(css_dependency);
+            }
+        }
+        // Without manganis support the html isn't processed, so the reference is left as it is
+        if manganis_support {
+            if let Ok(location) = dependency.served_location() {
+                references.push(HtmlReference::new(url, format!("{location}{suffix}")));
+            }
+        }
+        if !dependencies.contains(&dependency) {
+            dependencies.push(dependency);
+        }
+    }
+
+    Ok(references)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    #[test]
+    fn tokens_reproduce_the_document() {
+        let html = r#"<!DOCTYPE html><html><!-- comment --><!--[if IE]><p>old</p><![endif]--><body class=main>a < b <br/></body></html>"#;
+        let source: String = tokenize_html(html).map(|token| token.source()).collect();
+
+        assert_eq!(source, html);
+    }
+
+    #[test]
+    fn raw_text_elements_are_not_parsed_as_html() {
+        let html = r#"<script>if (a < b) document.write("<img src=fake.png>")</script><textarea><a href="fake.html"></TEXTAREA><img src="real.png">"#;
+        let tokens: Vec<_> = tokenize_html(html).collect();
+
+        assert_eq!(
+            tokens[1],
+            HtmlToken::RawText {
+                element: "script".to_string(),
+                content: r#"if (a < b) document.write("<img src=fake.png>")"#,
+            }
+        );
+        assert!(matches!(&tokens[2], HtmlToken::EndTag { name, .. } if name == "script"));
+        // End tags of raw text elements are matched without case
+        assert!(matches!(&tokens[5], HtmlToken::EndTag { name, .. } if name == "textarea"));
+        assert_eq!(html_urls(html), ["real.png"]);
+    }
+
+    #[test]
+    fn attributes_may_be_unquoted_single_quoted_or_empty() {
+        let html = r#"<img src=logo.png alt='a "quoted" logo' hidden data-x = "y">"#;
+        let Some(HtmlToken::StartTag(tag)) = tokenize_html(html).next() else {
+            panic!("expected a start tag");
+        };
+        let values: Vec<_> = tag
+            .attributes()
+            .iter()
+            .map(|attribute| (attribute.name(), attribute.value()))
+            .collect();
+
+        assert_eq!(
+            values,
+            [
+                ("src", Some("logo.png".to_string())),
+                ("alt", Some(r#"a "quoted" logo"#.to_string())),
+                ("hidden", None),
+                ("data-x", Some("y".to_string())),
+            ]
+        );
+        // The span of a value includes its quotes
+        let alt = tag.attribute("alt").unwrap();
+        assert_eq!(&html[alt.value_span()], r#"'a "quoted" logo'"#);
+        let src = tag.attribute("SRC").unwrap();
+        assert_eq!(&html[src.value_span()], "logo.png");
+    }
+
+    #[test]
+    fn character_references_in_attributes_are_decoded() {
+        let html = r#"<a href="search.html?q=a&amp;page=2&quot;">"#;
+
+        assert_eq!(html_urls(html), [r#"search.html?q=a&page=2""#]);
+        assert_eq!(
+            escape_html_attribute(r#"search.html?q=a&page=2""#),
+            "search.html?q=a&amp;page=2&quot;"
+        );
+    }
+
+    #[test]
+    fn only_rendered_files_are_resources() {
+        let html = r#"<a href="about.html"><img src="logo.png"><link rel="stylesheet" href="style.css"><script src="main.js"></script>"#;
+
+        assert_eq!(
+            html_resource_urls(html),
+            ["logo.png", "style.css", "main.js"]
+        );
+    }
+
+    #[test]
+    fn links_to_missing_pages_are_skipped() {
+        let folder = write_files(
+            "html-links",
+            &[
+                (
+                    "index.html",
+                    r#"<a href="about">About</a><a href="docs.html">Docs</a><img src="logo.png">"#,
+                ),
+                ("docs.html", "<p>Docs</p>"),
+                ("logo.png", "png"),
+            ],
+        );
+        let mut file = FileAsset::new(AssetSource::Local(folder.join("index.html")));
+        let dependencies = collect_html_dependencies(&mut file).unwrap();
+        let mut sources: Vec<_> = dependencies
+            .iter()
+            .filter_map(|dependency| dependency.location().source().as_path().cloned())
+            .collect();
+        sources.sort();
+
+        assert_eq!(sources, [folder.join("docs.html"), folder.join("logo.png")]);
+    }
+
+    #[test]
+    fn missing_resources_are_an_error() {
+        let folder = write_files("html-missing", &[("index.html", r#"<img src="logo.png">"#)]);
+        let mut file = FileAsset::new(AssetSource::Local(folder.join("index.html")));
+        let err = collect_html_dependencies(&mut file).unwrap_err();
+
+        assert!(err.to_string().contains("logo.png"), "{err}");
+    }
+}
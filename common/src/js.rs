@@ -0,0 +1,482 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use swc_common::{sync::Lrc, BytePos, FileName, SourceMap, Spanned};
+use swc_ecma_ast::{
+    Callee, EsVersion, Expr, Lit, MemberProp, MetaPropKind, Module, ModuleDecl, ModuleItem, Str,
+};
+use swc_ecma_loader::{resolve::Resolve, resolvers::node::NodeModulesResolver, TargetEnv};
+use swc_ecma_parser::{EsSyntax, Syntax, TsSyntax};
+use swc_ecma_visit::{Visit, VisitWith};
+use url::Url;
+
+use crate::{AssetSource, FileAsset, FileOptions, JsOptions};
+
+/// The extensions of the modules that can be bundled into a js asset
+const MODULE_EXTENSIONS: &[&str] = &["js", "mjs", "jsx", "ts", "tsx"];
+
+/// A module that is bundled into a js asset
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct JsModule {
+    source: PathBuf,
+    /// When the module was last modified, so the bundle changes when a module does
+    last_updated: Option<String>,
+}
+
+impl JsModule {
+    /// Creates a new js module and records when the module was last modified
+    pub fn new(source: PathBuf) -> Self {
+        let last_updated = AssetSource::Local(source.clone()).last_updated();
+        Self {
+            source,
+            last_updated,
+        }
+    }
+
+    /// Returns the path to the bundled module
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+}
+
+/// A chunk a bundled js asset loads from another file with a dynamic `import()` or a web worker
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct JsReference {
+    specifier: String,
+    location: String,
+}
+
+impl JsReference {
+    /// Creates a new js reference
+    pub fn new(specifier: impl Into<String>, location: impl Into<String>) -> Self {
+        Self {
+            specifier: specifier.into(),
+            location: location.into(),
+        }
+    }
+
+    /// Returns the specifier as it is written in the js
+    pub fn specifier(&self) -> &str {
+        &self.specifier
+    }
+
+    /// Returns the location the specifier is rewritten to
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+}
+
+/// Parses a js or TypeScript file as an ES module. The syntax is picked from the extension of the file
+pub fn parse_js_module(cm: &SourceMap, path: &Path, name: FileName) -> anyhow::Result<Module> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", path.display()))?;
+    let syntax = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("ts") => Syntax::Typescript(TsSyntax::default()),
+        Some("tsx") => Syntax::Typescript(TsSyntax {
+            tsx: true,
+            ..Default::default()
+        }),
+        Some("jsx") => Syntax::Es(EsSyntax {
+            jsx: true,
+            ..Default::default()
+        }),
+        _ => Syntax::Es(EsSyntax::default()),
+    };
+    let fm = cm.new_source_file(Lrc::new(name), source);
+    swc_ecma_parser::parse_file_as_module(&fm, syntax, EsVersion::EsNext, None, &mut Vec::new())
+        .map_err(|err| {
+            let location = cm.lookup_char_pos(err.span().lo);
+            anyhow::anyhow!(
+                "Failed to parse {}:{}:{}: {}",
+                path.display(),
+                location.line,
+                location.col_display + 1,
+                err.kind().msg()
+            )
+        })
+}
+
+/// Checks that a global can be replaced with a js expression when the js is compiled. The name must be an identifier or a member expression like `process.env.NODE_ENV` and the value must be a single js expression
+pub fn verify_js_define(name: &str, value: &str) -> anyhow::Result<()> {
+    let valid_identifier = |part: &str| {
+        let mut chars = part.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+    };
+    if !name.split('.').all(valid_identifier) {
+        anyhow::bail!("{name} is not a valid name for a global. Only identifiers and member expressions like process.env.NODE_ENV can be defined");
+    }
+
+    let cm = SourceMap::default();
+    let fm = cm.new_source_file(Lrc::new(FileName::Anon), value.to_string());
+    let mut errors = Vec::new();
+    let parsed = swc_ecma_parser::parse_file_as_expr(
+        &fm,
+        Syntax::Es(EsSyntax::default()),
+        EsVersion::EsNext,
+        None,
+        &mut errors,
+    );
+    match parsed {
+        Ok(_) if !errors.is_empty() => anyhow::bail!(
+            "The value of {name} is not a valid js expression: {}",
+            errors[0].kind().msg()
+        ),
+        // The parser stops after the first expression, so anything after it is not part of the value
+        Ok(expr) if expr.span().hi < fm.start_pos + BytePos(value.trim_end().len() as u32) => {
+            anyhow::bail!("The value of {name} is not a single js expression: {value}")
+        }
+        Ok(_) => Ok(()),
+        Err(err) => anyhow::bail!(
+            "The value of {name} is not a valid js expression: {}",
+            err.kind().msg()
+        ),
+    }
+}
+
+/// Resolves a module specifier relative to the module it is imported in. Relative specifiers may leave out the extension and bare specifiers are resolved from `node_modules`. Returns None if the specifier is a url or an absolute path the module is loaded from at runtime
+pub fn resolve_js_module(importer: &Path, specifier: &str) -> anyhow::Result<Option<PathBuf>> {
+    if specifier.starts_with('/') || Url::parse(specifier).is_ok() {
+        return Ok(None);
+    }
+
+    let resolver = NodeModulesResolver::new(TargetEnv::Browser, Default::default(), false);
+    let resolution = resolver
+        .resolve(&FileName::Real(importer.to_path_buf()), specifier)
+        .map_err(|err| {
+            anyhow::anyhow!(
+                "{} imports {specifier}, but it could not be resolved: {err}",
+                importer.display()
+            )
+        })?;
+    let FileName::Real(path) = resolution.filename else {
+        anyhow::bail!(
+            "{} imports {specifier}, which is not a local module",
+            importer.display()
+        );
+    };
+    let supported = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| MODULE_EXTENSIONS.contains(&extension));
+    if !supported {
+        anyhow::bail!(
+            "{} imports {}, but only js and TypeScript modules can be bundled",
+            importer.display(),
+            path.display()
+        );
+    }
+
+    Ok(Some(path.canonicalize()?))
+}
+
+/// Returns if a module specifier is bare like `lodash`. Bare specifiers are resolved from `node_modules` or mapped to a js asset in the import map
+pub fn is_bare_js_specifier(specifier: &str) -> bool {
+    !specifier.starts_with("./")
+        && !specifier.starts_with("../")
+        && !specifier.starts_with('/')
+        && Url::parse(specifier).is_err()
+}
+
+/// Returns the specifier of a file an expression loads as a separate chunk. Chunks are loaded with a dynamic `import("./chunk.js")` or as a web worker with `new Worker(new URL("./worker.js", import.meta.url))`
+pub fn chunk_specifier(expr: &Expr) -> Option<&Str> {
+    let specifier = match expr {
+        Expr::Call(call) if matches!(call.callee, Callee::Import(_)) => &*call.args.first()?.expr,
+        Expr::New(new) => {
+            let Expr::Ident(callee) = &*new.callee else {
+                return None;
+            };
+            if callee.sym != "Worker" && callee.sym != "SharedWorker" {
+                return None;
+            }
+            let Expr::New(url) = &*new.args.as_ref()?.first()?.expr else {
+                return None;
+            };
+            if !matches!(&*url.callee, Expr::Ident(callee) if callee.sym == "URL") {
+                return None;
+            }
+            let [specifier, base, ..] = url.args.as_deref()? else {
+                return None;
+            };
+            // Only urls relative to the module can be resolved when the js is bundled
+            let relative_to_module = matches!(
+                &*base.expr,
+                Expr::Member(member)
+                    if matches!(&*member.obj, Expr::MetaProp(meta) if meta.kind == MetaPropKind::ImportMeta)
+                        && matches!(&member.prop, MemberProp::Ident(prop) if prop.sym == "url")
+            );
+            if !relative_to_module {
+                return None;
+            }
+            &*specifier.expr
+        }
+        _ => return None,
+    };
+    match specifier {
+        Expr::Lit(Lit::Str(specifier)) => Some(specifier),
+        _ => None,
+    }
+}
+
+/// Returns the specifier of a chunk like [`chunk_specifier`] so it can be rewritten
+pub fn chunk_specifier_mut(expr: &mut Expr) -> Option<&mut Str> {
+    chunk_specifier(expr)?;
+    let specifier = match expr {
+        Expr::Call(call) => &mut call.args.first_mut()?.expr,
+        Expr::New(new) => {
+            let Expr::New(url) = &mut *new.args.as_mut()?.first_mut()?.expr else {
+                return None;
+            };
+            &mut url.args.as_mut()?.first_mut()?.expr
+        }
+        _ => return None,
+    };
+    match &mut **specifier {
+        Expr::Lit(Lit::Str(specifier)) => Some(specifier),
+        _ => None,
+    }
+}
+
+/// Returns the specifiers of the modules a module imports or re-exports from. Type only imports and exports are skipped
+pub fn js_module_imports(module: &Module) -> Vec<String> {
+    let mut specifiers = ModuleSpecifiers::default();
+    module.visit_with(&mut specifiers);
+    specifiers.imports
+}
+
+/// Finds the local modules a js asset imports and records them in the options so the bundle is regenerated when one of them changes. Chunks that are loaded with a dynamic `import()` and web workers are collected as separate bundled js assets and the specifiers in the js are rewritten to the location the chunks are served at. Returns every chunk the js depends on
+///
+/// Only local js that is bundled is searched. Modules that are imported with a url or an absolute path are loaded at runtime
+pub fn collect_js_dependencies(file: &mut FileAsset) -> anyhow::Result<Vec<FileAsset>> {
+    let mut dependencies = Vec::new();
+    collect_js_dependencies_inner(file, &mut Vec::new(), &mut dependencies)?;
+    Ok(dependencies)
+}
+
+fn collect_js_dependencies_inner(
+    file: &mut FileAsset,
+    stack: &mut Vec<PathBuf>,
+    dependencies: &mut Vec<FileAsset>,
+) -> anyhow::Result<()> {
+    let FileOptions::Js(options) = file.options() else {
+        return Ok(());
+    };
+    if !options.bundle() {
+        return Ok(());
+    }
+    let AssetSource::Local(path) = file.location().source() else {
+        anyhow::bail!("Only local js can be bundled");
+    };
+    let path = path.canonicalize()?;
+    if stack.contains(&path) {
+        anyhow::bail!("{} loads itself as a chunk", path.display());
+    }
+
+    let mut modules = CollectedModules {
+        entry: path.clone(),
+        options: options.clone(),
+        modules: Vec::new(),
+        externals: Vec::new(),
+        references: Vec::new(),
+        dependencies,
+    };
+    stack.push(path.clone());
+    modules.collect(&path, stack)?;
+    stack.pop();
+    let CollectedModules {
+        modules,
+        externals,
+        references,
+        ..
+    } = modules;
+
+    file.with_options_mut(|options| {
+        if let FileOptions::Js(options) = options {
+            options.set_modules(modules);
+            options.set_externals(externals);
+            options.set_references(references);
+        }
+    });
+
+    Ok(())
+}
+
+/// The modules and chunks of a bundled js asset
+struct CollectedModules<'a> {
+    entry: PathBuf,
+    options: JsOptions,
+    modules: Vec<JsModule>,
+    /// The bare specifiers that can't be resolved locally, so they are loaded from the import map
+    externals: Vec<String>,
+    references: Vec<JsReference>,
+    dependencies: &'a mut Vec<FileAsset>,
+}
+
+impl CollectedModules<'_> {
+    fn collect(&mut self, path: &Path, stack: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        let cm = SourceMap::default();
+        let module = parse_js_module(&cm, path, FileName::Real(path.to_path_buf()))?;
+        let mut specifiers = ModuleSpecifiers::default();
+        module.visit_with(&mut specifiers);
+
+        for specifier in specifiers.imports {
+            let import = match resolve_js_module(path, &specifier) {
+                Ok(Some(import)) => import,
+                Ok(None) => continue,
+                // Bare specifiers may be mapped to js from another crate in the import map, so they are loaded at runtime
+                Err(_) if is_bare_js_specifier(&specifier) => {
+                    if !self.externals.contains(&specifier) {
+                        self.externals.push(specifier);
+                    }
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            if import == self.entry || self.modules.iter().any(|module| module.source == import) {
+                continue;
+            }
+            self.modules.push(JsModule::new(import.clone()));
+            self.collect(&import, stack)?;
+        }
+
+        let manganis_support = std::env::var("MANGANIS_SUPPORT").is_ok();
+        for specifier in specifiers.chunks {
+            let Some(chunk_path) = resolve_js_module(path, &specifier)? else {
+                continue;
+            };
+            // Chunks are bundled with the same options as the js that loads them
+            let mut options = JsOptions::new(
+                chunk_path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .and_then(|extension| extension.parse().ok())
+                    .unwrap_or_default(),
+            );
+            options.set_bundle(true);
+            options.set_minify(self.options.minify());
+            options.set_source_map(self.options.source_map());
+            options.set_target(self.options.target());
+            options.set_defines(self.options.defines().to_vec());
+            let mut chunk = FileAsset::new(AssetSource::Local(chunk_path))
+                .with_options(FileOptions::Js(options));
+
+            collect_js_dependencies_inner(&mut chunk, stack, self.dependencies)?;
+            // Without manganis support the js isn't processed, so the specifier is left as it is
+            if manganis_support {
+                if let Ok(location) = chunk.served_location() {
+                    self.add_reference(JsReference::new(specifier, location))?;
+                }
+            }
+            if !self.dependencies.contains(&chunk) {
+                self.dependencies.push(chunk);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_reference(&mut self, reference: JsReference) -> anyhow::Result<()> {
+        match self
+            .references
+            .iter()
+            .find(|r| r.specifier() == reference.specifier())
+        {
+            Some(existing) if existing != &reference => anyhow::bail!(
+                "{} points to different chunks in the bundled modules",
+                reference.specifier()
+            ),
+            Some(_) => {}
+            None => self.references.push(reference),
+        }
+        Ok(())
+    }
+}
+
+/// The specifiers of the modules a module imports and the chunks it loads
+#[derive(Default)]
+struct ModuleSpecifiers {
+    imports: Vec<String>,
+    chunks: Vec<String>,
+}
+
+impl Visit for ModuleSpecifiers {
+    fn visit_module_item(&mut self, item: &ModuleItem) {
+        let import = match item {
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) if !import.type_only => {
+                Some(&import.src)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportAll(export)) if !export.type_only => {
+                Some(&export.src)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(export)) if !export.type_only => {
+                export.src.as_ref()
+            }
+            _ => None,
+        };
+        if let Some(import) = import {
+            let import = import.value.to_string();
+            if !self.imports.contains(&import) {
+                self.imports.push(import);
+            }
+        }
+        item.visit_children_with(self);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Some(chunk) = chunk_specifier(expr) {
+            let chunk = chunk.value.to_string();
+            if !self.chunks.contains(&chunk) {
+                self.chunks.push(chunk);
+            }
+        }
+        expr.visit_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::write_files, JsType};
+
+    #[test]
+    fn bare_specifiers_that_cant_be_resolved_are_external() {
+        let folder = write_files(
+            "js-externals",
+            &[
+                (
+                    "main.js",
+                    "import { debounce } from 'lodash';\nimport { log } from './log.js';\nlog(debounce);",
+                ),
+                (
+                    "log.js",
+                    "import { throttle } from 'lodash';\nimport { html } from 'lit';\nexport function log() {}",
+                ),
+            ],
+        );
+        let mut options = JsOptions::new(JsType::Js);
+        options.set_bundle(true);
+        let mut file = FileAsset::new(AssetSource::Local(folder.join("main.js")))
+            .with_options(FileOptions::Js(options));
+        collect_js_dependencies(&mut file).unwrap();
+
+        let FileOptions::Js(options) = file.options() else {
+            unreachable!()
+        };
+        assert_eq!(options.externals(), ["lodash", "lit"]);
+        assert_eq!(options.modules().len(), 1);
+
+        // The externals are part of the options, so they change the name of the bundle
+        let mut without_externals = file.clone();
+        without_externals.with_options_mut(|options| {
+            if let FileOptions::Js(options) = options {
+                options.set_externals(Vec::new());
+            }
+        });
+        assert_ne!(
+            file.location().unique_name(),
+            without_externals.location().unique_name()
+        );
+    }
+}
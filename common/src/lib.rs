@@ -2,14 +2,34 @@
 //! Common types and methods for the manganis asset system
 
 mod asset;
+mod atlas;
 mod built;
 pub mod cache;
 mod config;
+mod css;
+mod css_module;
 mod file;
+mod html;
+mod js;
 pub mod linker;
 mod manifest;
+mod metadata;
+mod placeholder;
+mod sass;
+mod sprite;
+#[cfg(test)]
+mod test_util;
 
 pub use asset::*;
+pub use atlas::*;
 pub use config::*;
+pub use css::*;
+pub use css_module::*;
 pub use file::*;
+pub use html::*;
+pub use js::*;
 pub use manifest::*;
+pub use metadata::*;
+pub use placeholder::*;
+pub use sass::*;
+pub use sprite::*;
@@ -17,10 +17,73 @@ impl AssetManifest {
         &self.assets
     }
 
+    /// Returns the import map JSON that maps the bare specifiers js assets declare to the locations they are served at. If two assets declare the same specifier, the first one is used
+    pub fn import_map(&self) -> String {
+        serde_json::json!({ "imports": self.imports() }).to_string()
+    }
+
+    /// Returns the bare specifiers js assets declare in the import map
+    pub fn import_specifiers(&self) -> Vec<&str> {
+        let mut specifiers = Vec::new();
+        for asset in &self.assets {
+            let crate::AssetType::File(file) = asset else {
+                continue;
+            };
+            let crate::FileOptions::Js(js_options) = file.options() else {
+                continue;
+            };
+            if let Some(specifier) = js_options.import_specifier() {
+                if !specifiers.contains(&specifier) {
+                    specifiers.push(specifier);
+                }
+            }
+        }
+        specifiers
+    }
+
+    /// Returns the locations every bare specifier in the import map is mapped to
+    fn imports(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut imports = serde_json::Map::new();
+        for asset in &self.assets {
+            let crate::AssetType::File(file) = asset else {
+                continue;
+            };
+            let crate::FileOptions::Js(js_options) = file.options() else {
+                continue;
+            };
+            let Some(specifier) = js_options.import_specifier() else {
+                continue;
+            };
+            let Ok(asset_path) = file.served_location() else {
+                continue;
+            };
+            match imports.get(specifier).and_then(serde_json::Value::as_str) {
+                Some(existing) if existing != asset_path => {
+                    tracing::warn!(
+                        "{specifier} is mapped to both {existing} and {asset_path} in the import map. Only {existing} will be used"
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    imports.insert(specifier.to_string(), asset_path.into());
+                }
+            }
+        }
+        imports
+    }
+
     #[cfg(feature = "html")]
     /// Returns the HTML that should be injected into the head of the page
     pub fn head(&self) -> String {
         let mut head = String::new();
+        // The import map needs to come before any module is loaded
+        if !self.import_specifiers().is_empty() {
+            // Escape the end of the script tag in case a specifier contains it
+            let import_map = self.import_map().replace("</", "<\\/");
+            head.push_str(&format!(
+                "<script type=\"importmap\">{import_map}</script>\n"
+            ));
+        }
         for asset in &self.assets {
             if let crate::AssetType::File(file) = asset {
                 match file.options() {
@@ -41,9 +104,38 @@ impl AssetManifest {
                                 ))
                             }
                         }
+                        if let Some(icon) = image_options.icon() {
+                            if let Ok(asset_path) = file.served_location() {
+                                let (width, height) = image_options.size().unwrap_or_default();
+                                match icon {
+                                    crate::IconRole::Favicon => {
+                                        let sizes = crate::IconRole::FAVICON_SIZES
+                                            .map(|size| format!("{size}x{size}"))
+                                            .join(" ");
+                                        head.push_str(&format!(
+                                            "<link rel=\"icon\" sizes=\"{sizes}\" href=\"{asset_path}\">\n"
+                                        ))
+                                    }
+                                    crate::IconRole::Icon => head.push_str(&format!(
+                                        "<link rel=\"icon\" type=\"image/png\" sizes=\"{width}x{height}\" href=\"{asset_path}\">\n"
+                                    )),
+                                    crate::IconRole::AppleTouch { .. } => head.push_str(&format!(
+                                        "<link rel=\"apple-touch-icon\" sizes=\"{width}x{height}\" href=\"{asset_path}\">\n"
+                                    )),
+                                    // Maskable icons are only used from the web manifest
+                                    crate::IconRole::Maskable { .. } => {}
+                                }
+                            }
+                        }
                     }
                     crate::FileOptions::Js(js_options) => {
-                        if js_options.preload() {
+                        if js_options.preload() && js_options.module() {
+                            if let Ok(asset_path) = file.served_location() {
+                                head.push_str(&format!(
+                                    "<link rel=\"modulepreload\" href=\"{asset_path}\">\n"
+                                ))
+                            }
+                        } else if js_options.preload() {
                             if let Ok(asset_path) = file.served_location() {
                                 head.push_str(&format!(
                                     "<link rel=\"preload\" as=\"script\" href=\"{asset_path}\">\n"
@@ -58,3 +150,67 @@ impl AssetManifest {
         head
     }
 }
+
+#[cfg(all(test, feature = "html"))]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+    use crate::{AssetSource, FileAsset, FileOptions, IconRole, ImageOptions, ImageType};
+    use std::path::Path;
+
+    /// Creates a url encoded png asset so the served location doesn't depend on the environment
+    fn icon_asset(folder: &Path, icon: IconRole, size: (u32, u32)) -> AssetType {
+        let mut options = ImageOptions::new(ImageType::Png, Some(size));
+        options.set_icon(Some(icon));
+        let mut file = FileAsset::new(AssetSource::Local(folder.join("icon.png")))
+            .with_options(FileOptions::Image(options));
+        file.set_url_encoded(true);
+        AssetType::File(file)
+    }
+
+    #[test]
+    fn favicons_link_every_favicon_size() {
+        let folder = write_files("icon-head", &[("icon.png", "icon")]);
+        let manifest = AssetManifest::new(vec![icon_asset(&folder, IconRole::Favicon, (48, 48))]);
+
+        assert_eq!(
+            manifest.head(),
+            "<link rel=\"icon\" sizes=\"16x16 32x32 48x48\" href=\"data:image/png;base64,aWNvbg\">\n"
+        );
+    }
+
+    #[test]
+    fn icons_link_their_size() {
+        let folder = write_files("icon-head", &[("icon.png", "icon")]);
+        let manifest = AssetManifest::new(vec![
+            icon_asset(&folder, IconRole::Icon, (192, 192)),
+            icon_asset(
+                &folder,
+                IconRole::AppleTouch {
+                    background: [255, 255, 255],
+                },
+                (180, 180),
+            ),
+        ]);
+
+        assert_eq!(
+            manifest.head(),
+            "<link rel=\"icon\" type=\"image/png\" sizes=\"192x192\" href=\"data:image/png;base64,aWNvbg\">\n\
+             <link rel=\"apple-touch-icon\" sizes=\"180x180\" href=\"data:image/png;base64,aWNvbg\">\n"
+        );
+    }
+
+    #[test]
+    fn maskable_icons_are_not_linked() {
+        let folder = write_files("icon-head", &[("icon.png", "icon")]);
+        let manifest = AssetManifest::new(vec![icon_asset(
+            &folder,
+            IconRole::Maskable {
+                background: [255, 255, 255],
+            },
+            (512, 512),
+        )]);
+
+        assert_eq!(manifest.head(), "");
+    }
+}
@@ -0,0 +1,355 @@
+use image::DynamicImage;
+
+/// The EXIF tag that stores the orientation of the image
+const ORIENTATION_TAG: u16 = 0x0112;
+/// The header of a JPEG APP1 segment that contains EXIF data
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// The header of a JPEG APP1 segment that contains XMP data
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// The signature at the start of every PNG file
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The metadata of an image that changes how the image is decoded or is kept in the processed image
+#[derive(Debug, Default, Clone)]
+pub struct ImageMetadata {
+    orientation: u32,
+    exif: Option<Vec<u8>>,
+    icc_profile: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    /// Reads the EXIF data and ICC profile from an encoded image. Missing or invalid metadata is ignored
+    pub fn read(bytes: &[u8]) -> Self {
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(bytes))
+            .ok();
+        let orientation = exif
+            .as_ref()
+            .and_then(|exif| exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY))
+            .and_then(|field| field.value.get_uint(0))
+            .unwrap_or(1);
+        let icc_profile = image::ImageReader::new(std::io::Cursor::new(bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_decoder().ok())
+            .and_then(|mut decoder| image::ImageDecoder::icc_profile(&mut decoder).ok())
+            .flatten();
+
+        Self {
+            orientation,
+            exif: exif.map(|exif| exif.buf().to_vec()),
+            icc_profile,
+        }
+    }
+
+    /// Returns the EXIF orientation of the image from 1 to 8
+    pub fn orientation(&self) -> u32 {
+        self.orientation
+    }
+
+    /// Returns the raw TIFF encoded EXIF data of the image with the orientation reset to the default. The orientation is applied to the pixels when the image is decoded, so keeping the original orientation would rotate the image twice
+    pub fn exif(&self) -> Option<Vec<u8>> {
+        let mut exif = self.exif.clone()?;
+        reset_orientation(&mut exif);
+        Some(exif)
+    }
+
+    /// Returns the embedded ICC profile of the image
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.icc_profile.as_deref()
+    }
+
+    /// Rotates and flips the image so it is displayed upright
+    pub fn apply_orientation(&self, image: DynamicImage) -> DynamicImage {
+        match self.orientation {
+            2 => image.fliph(),
+            3 => image.rotate180(),
+            4 => image.flipv(),
+            5 => image.rotate90().fliph(),
+            6 => image.rotate90(),
+            7 => image.rotate270().fliph(),
+            8 => image.rotate270(),
+            _ => image,
+        }
+    }
+
+    /// Converts the colors of the image from the embedded ICC profile to sRGB. Browsers assume images without a profile are sRGB
+    pub fn convert_to_srgb(&self, image: DynamicImage) -> DynamicImage {
+        let Some(icc_profile) = &self.icc_profile else {
+            return image;
+        };
+        // Grayscale images have grayscale profiles which can't be converted into an RGB profile
+        if image.color().channel_count() < 3 {
+            return image;
+        }
+        let Some(profile) = qcms::Profile::new_from_slice(icc_profile, false) else {
+            tracing::warn!("Failed to parse the ICC profile of an image. The colors of the image will not be converted to sRGB");
+            return image;
+        };
+        let srgb = qcms::Profile::new_sRGB();
+        let Some(transform) = qcms::Transform::new(
+            &profile,
+            &srgb,
+            qcms::DataType::RGBA8,
+            qcms::Intent::Perceptual,
+        ) else {
+            tracing::warn!(
+                "Unsupported ICC profile. The colors of the image will not be converted to sRGB"
+            );
+            return image;
+        };
+        let mut rgba = image.into_rgba8();
+        transform.apply(&mut rgba);
+        DynamicImage::ImageRgba8(rgba)
+    }
+}
+
+/// Sets the orientation tag in TIFF encoded EXIF data to the default orientation
+fn reset_orientation(tiff: &mut [u8]) {
+    let little_endian = match tiff.get(0..2) {
+        Some(b"II") => true,
+        Some(b"MM") => false,
+        _ => return,
+    };
+    let read_u16 = |bytes: &[u8], offset: usize| {
+        let bytes = [*bytes.get(offset)?, *bytes.get(offset + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let read_u32 = |bytes: &[u8], offset: usize| {
+        let bytes: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    // The orientation is stored in the first IFD
+    let Some(ifd) = read_u32(tiff, 4).map(|offset| offset as usize) else {
+        return;
+    };
+    let Some(entries) = read_u16(tiff, ifd) else {
+        return;
+    };
+    for entry in 0..entries as usize {
+        let entry = ifd + 2 + entry * 12;
+        if read_u16(tiff, entry) == Some(ORIENTATION_TAG) {
+            // The orientation is a short stored inline in the value field of the entry
+            let value = if little_endian {
+                1u16.to_le_bytes()
+            } else {
+                1u16.to_be_bytes()
+            };
+            if let Some(field) = tiff.get_mut(entry + 8..entry + 10) {
+                field.copy_from_slice(&value);
+            }
+            return;
+        }
+    }
+}
+
+/// Removes EXIF and XMP metadata from an encoded JPEG or PNG image without re-encoding it. Returns `None` if the bytes are not a JPEG or PNG image, or an error if the structure of the image can't be read
+pub fn strip_metadata(bytes: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg_metadata(bytes).map(Some)
+    } else if bytes.starts_with(PNG_SIGNATURE) {
+        strip_png_metadata(bytes).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+fn strip_jpeg_metadata(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let truncated = || anyhow::anyhow!("The jpeg ends in the middle of a segment");
+    let mut stripped = bytes[..2].to_vec();
+    let mut offset = 2;
+    loop {
+        if *bytes.get(offset).ok_or_else(truncated)? != 0xFF {
+            anyhow::bail!("Expected a jpeg marker at byte {offset}");
+        }
+        // Any number of 0xFF fill bytes may come before a marker
+        while bytes.get(offset + 1) == Some(&0xFF) {
+            offset += 1;
+        }
+        let marker = *bytes.get(offset + 1).ok_or_else(truncated)?;
+        match marker {
+            // The rest of the file after the start of scan marker is image data
+            0xDA => {
+                stripped.extend_from_slice(&bytes[offset..]);
+                return Ok(stripped);
+            }
+            0xD9 => {
+                stripped.extend_from_slice(&bytes[offset..offset + 2]);
+                return Ok(stripped);
+            }
+            // Markers without a segment
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&bytes[offset..offset + 2]);
+                offset += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let length = u16::from_be_bytes([
+            *bytes.get(offset + 2).ok_or_else(truncated)?,
+            *bytes.get(offset + 3).ok_or_else(truncated)?,
+        ]);
+        // The length includes the two length bytes
+        if length < 2 {
+            anyhow::bail!("The jpeg segment at byte {offset} has an invalid length of {length}");
+        }
+        let end = offset + 2 + length as usize;
+        let segment = bytes.get(offset..end).ok_or_else(truncated)?;
+        let data = &segment[4..];
+        let is_metadata = marker == 0xE1
+            && (data.starts_with(JPEG_EXIF_HEADER) || data.starts_with(JPEG_XMP_HEADER));
+        if !is_metadata {
+            stripped.extend_from_slice(segment);
+        }
+        offset = end;
+    }
+}
+
+fn strip_png_metadata(bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let truncated = || anyhow::anyhow!("The png ends in the middle of a chunk");
+    let mut stripped = PNG_SIGNATURE.to_vec();
+    let mut offset = PNG_SIGNATURE.len();
+    while offset < bytes.len() {
+        let length = bytes.get(offset..offset + 4).ok_or_else(truncated)?;
+        let length = u32::from_be_bytes(length.try_into()?);
+        // Each chunk is the length, type, data, and crc
+        let end = offset + 12 + length as usize;
+        let chunk = bytes.get(offset..end).ok_or_else(truncated)?;
+        let ty = &chunk[4..8];
+        let data = &chunk[8..chunk.len() - 4];
+        let is_metadata = match ty {
+            b"eXIf" => true,
+            // XMP is stored in an international text chunk and some tools store EXIF as a raw profile in text chunks
+            b"iTXt" | b"tEXt" | b"zTXt" => {
+                data.starts_with(b"XML:com.adobe.xmp\0") || data.starts_with(b"Raw profile type")
+            }
+            _ => false,
+        };
+        if !is_metadata {
+            stripped.extend_from_slice(chunk);
+        }
+        offset = end;
+    }
+    Ok(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little endian TIFF block with a single orientation tag
+    fn exif_with_orientation(orientation: u8) -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend([0x12, 0x01, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00]);
+        tiff.extend([orientation, 0, 0, 0, 0, 0, 0, 0]);
+        tiff
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, marker];
+        segment.extend((data.len() as u16 + 2).to_be_bytes());
+        segment.extend(data);
+        segment
+    }
+
+    fn encode(format: image::ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgb8(6, 4)
+            .write_to(&mut std::io::Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    /// A jpeg with EXIF and XMP segments. Fill bytes come before some of the markers
+    fn jpeg_with_metadata() -> Vec<u8> {
+        let jpeg = encode(image::ImageFormat::Jpeg);
+        let mut exif = JPEG_EXIF_HEADER.to_vec();
+        exif.extend(exif_with_orientation(6));
+        let mut xmp = JPEG_XMP_HEADER.to_vec();
+        xmp.extend(b"<x:xmpmeta>GPS</x:xmpmeta>");
+
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend([0xFF, 0xFF]);
+        bytes.extend(jpeg_segment(0xE1, &exif));
+        bytes.extend(jpeg_segment(0xE1, &xmp));
+        bytes.push(0xFF);
+        bytes.extend(&jpeg[2..]);
+        bytes
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[test]
+    fn reads_jpeg_orientation() {
+        assert_eq!(ImageMetadata::read(&jpeg_with_metadata()).orientation(), 6);
+        assert_eq!(
+            ImageMetadata::read(&encode(image::ImageFormat::Jpeg)).orientation(),
+            1
+        );
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let bytes = jpeg_with_metadata();
+        let stripped = strip_metadata(&bytes).unwrap().unwrap();
+
+        assert!(!contains(&stripped, JPEG_EXIF_HEADER));
+        assert!(!contains(&stripped, JPEG_XMP_HEADER));
+        assert_eq!(ImageMetadata::read(&stripped).orientation(), 1);
+        let image = image::load_from_memory(&stripped).unwrap();
+        assert_eq!((image.width(), image.height()), (6, 4));
+    }
+
+    #[test]
+    fn truncated_jpeg_is_an_error() {
+        let bytes = jpeg_with_metadata();
+        assert!(strip_metadata(&bytes[..12]).is_err());
+
+        let mut bytes = bytes;
+        bytes[2] = 0x00;
+        assert!(strip_metadata(&bytes).is_err());
+    }
+
+    #[test]
+    fn strips_png_metadata() {
+        fn chunk(ty: &[u8; 4], data: &[u8]) -> Vec<u8> {
+            let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+            chunk.extend(ty);
+            chunk.extend(data);
+            // The crc of the chunks that are stripped is never read
+            chunk.extend([0; 4]);
+            chunk
+        }
+
+        let png = encode(image::ImageFormat::Png);
+        // The IHDR chunk is always 25 bytes long
+        let ihdr_end = PNG_SIGNATURE.len() + 25;
+        let mut bytes = png[..ihdr_end].to_vec();
+        bytes.extend(chunk(b"eXIf", &exif_with_orientation(3)));
+        bytes.extend(chunk(b"tEXt", b"Raw profile type exif\0GPS"));
+        bytes.extend(chunk(b"iTXt", b"XML:com.adobe.xmp\0GPS"));
+        bytes.extend(&png[ihdr_end..]);
+
+        let stripped = strip_metadata(&bytes).unwrap().unwrap();
+        assert_eq!(stripped, png);
+        assert!(strip_metadata(&bytes[..bytes.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn other_formats_are_not_stripped() {
+        assert_eq!(strip_metadata(b"GIF89a").unwrap(), None);
+    }
+}
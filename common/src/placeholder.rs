@@ -0,0 +1,277 @@
+use std::{collections::HashMap, f32::consts::PI, fmt::Display, str::FromStr};
+
+use base64::Engine;
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+
+use crate::{AssetSource, ImageOptions};
+
+/// The largest side of the image a thumbhash can be generated from
+const THUMBHASH_MAX_SIZE: u32 = 100;
+/// The largest side of the image a blurhash or dominant color is generated from. The placeholders are blurry, so sampling a small image is much faster without changing the result
+const SAMPLE_MAX_SIZE: u32 = 64;
+/// The largest side of the image a blurhash will be decoded to
+const BLURHASH_DECODE_SIZE: u32 = 32;
+
+/// The type of placeholder to generate for an image
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub enum PlaceholderType {
+    /// A [thumbhash](https://evanw.github.io/thumbhash/) which keeps the aspect ratio and transparency of the image
+    ThumbHash,
+    /// A [blurhash](https://blurha.sh/) of the image
+    BlurHash,
+    /// The most common color in the image
+    DominantColor,
+}
+
+impl Display for PlaceholderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ThumbHash => write!(f, "thumbhash"),
+            Self::BlurHash => write!(f, "blurhash"),
+            Self::DominantColor => write!(f, "dominantcolor"),
+        }
+    }
+}
+
+impl FromStr for PlaceholderType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "thumbhash" => Ok(Self::ThumbHash),
+            "blurhash" => Ok(Self::BlurHash),
+            "dominantcolor" => Ok(Self::DominantColor),
+            _ => Err(()),
+        }
+    }
+}
+
+impl PlaceholderType {
+    /// Generates a placeholder for the image as it will look after it is processed with the options
+    pub fn generate(
+        &self,
+        source: &AssetSource,
+        options: &ImageOptions,
+    ) -> anyhow::Result<Placeholder> {
+        let mut image = options.decode(&source.read_to_bytes()?)?;
+        if let Some((width, height)) = options.size() {
+            image = image.resize_exact(width, height, FilterType::Triangle);
+        }
+
+        Ok(match self {
+            Self::ThumbHash => Placeholder::ThumbHash(thumbhash(&image)),
+            Self::BlurHash => {
+                let (hash, width, height) = blurhash(&image)?;
+                Placeholder::BlurHash {
+                    hash,
+                    width,
+                    height,
+                }
+            }
+            Self::DominantColor => Placeholder::DominantColor(dominant_color(&image)),
+        })
+    }
+}
+
+/// A placeholder generated for an image
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub enum Placeholder {
+    /// A base64 encoded thumbhash
+    ThumbHash(String),
+    /// A blurhash and the size it should be decoded at to keep the aspect ratio of the image
+    BlurHash {
+        /// The blurhash string
+        hash: String,
+        /// The width the blurhash should be decoded at
+        width: u32,
+        /// The height the blurhash should be decoded at
+        height: u32,
+    },
+    /// A hex color like `#1a2b3c`
+    DominantColor(String),
+}
+
+/// Scales the image down so the largest side is at most `max_size`
+fn downscale(image: &DynamicImage, max_size: u32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width <= max_size && height <= max_size {
+        image.clone()
+    } else {
+        image.resize(max_size, max_size, FilterType::Triangle)
+    }
+}
+
+/// Encodes the image as a base64 thumbhash. This follows the reference implementation at <https://github.com/evanw/thumbhash>
+fn thumbhash(image: &DynamicImage) -> String {
+    let image = downscale(image, THUMBHASH_MAX_SIZE).to_rgba8();
+    let (w, h) = image.dimensions();
+    let (width, height) = (w as usize, h as usize);
+    let rgba = image.as_raw();
+
+    // Determine the average color
+    let (mut avg_r, mut avg_g, mut avg_b, mut avg_a) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+    for pixel in rgba.chunks_exact(4) {
+        let alpha = pixel[3] as f32 / 255.0;
+        avg_r += alpha / 255.0 * pixel[0] as f32;
+        avg_g += alpha / 255.0 * pixel[1] as f32;
+        avg_b += alpha / 255.0 * pixel[2] as f32;
+        avg_a += alpha;
+    }
+    if avg_a > 0.0 {
+        avg_r /= avg_a;
+        avg_g /= avg_a;
+        avg_b /= avg_a;
+    }
+
+    let has_alpha = avg_a < (width * height) as f32;
+    let l_limit = if has_alpha { 5.0 } else { 7.0 };
+    let max_side = width.max(height) as f32;
+    let lx = ((l_limit * width as f32 / max_side).round() as usize).max(1);
+    let ly = ((l_limit * height as f32 / max_side).round() as usize).max(1);
+
+    // Convert the image to the LPQA color space, composited on top of the average color
+    let mut l = Vec::with_capacity(width * height);
+    let mut p = Vec::with_capacity(width * height);
+    let mut q = Vec::with_capacity(width * height);
+    let mut a = Vec::with_capacity(width * height);
+    for pixel in rgba.chunks_exact(4) {
+        let alpha = pixel[3] as f32 / 255.0;
+        let r = avg_r * (1.0 - alpha) + alpha / 255.0 * pixel[0] as f32;
+        let g = avg_g * (1.0 - alpha) + alpha / 255.0 * pixel[1] as f32;
+        let b = avg_b * (1.0 - alpha) + alpha / 255.0 * pixel[2] as f32;
+        l.push((r + g + b) / 3.0);
+        p.push((r + g) / 2.0 - b);
+        q.push(r - g);
+        a.push(alpha);
+    }
+
+    let encode_channel = |channel: &[f32], nx: usize, ny: usize| {
+        let mut dc = 0.0;
+        let mut ac = Vec::new();
+        let mut scale = 0.0f32;
+        let mut fx = vec![0.0; width];
+        for cy in 0..ny {
+            let mut cx = 0;
+            while cx * ny < nx * (ny - cy) {
+                for (x, fx) in fx.iter_mut().enumerate() {
+                    *fx = (PI / width as f32 * cx as f32 * (x as f32 + 0.5)).cos();
+                }
+                let mut f = 0.0;
+                for y in 0..height {
+                    let fy = (PI / height as f32 * cy as f32 * (y as f32 + 0.5)).cos();
+                    for (x, fx) in fx.iter().enumerate() {
+                        f += channel[x + y * width] * fx * fy;
+                    }
+                }
+                f /= (width * height) as f32;
+                if cx > 0 || cy > 0 {
+                    ac.push(f);
+                    scale = scale.max(f.abs());
+                } else {
+                    dc = f;
+                }
+                cx += 1;
+            }
+        }
+        if scale > 0.0 {
+            for ac in &mut ac {
+                *ac = 0.5 + 0.5 / scale * *ac;
+            }
+        }
+        (dc, ac, scale)
+    };
+
+    let (l_dc, l_ac, l_scale) = encode_channel(&l, lx.max(3), ly.max(3));
+    let (p_dc, p_ac, p_scale) = encode_channel(&p, 3, 3);
+    let (q_dc, q_ac, q_scale) = encode_channel(&q, 3, 3);
+    let alpha_channel = has_alpha.then(|| encode_channel(&a, 5, 5));
+
+    // Write the header
+    let is_landscape = width > height;
+    let header24 = (63.0 * l_dc).round() as u32
+        | ((31.5 + 31.5 * p_dc).round() as u32) << 6
+        | ((31.5 + 31.5 * q_dc).round() as u32) << 12
+        | ((31.0 * l_scale).round() as u32) << 18
+        | (has_alpha as u32) << 23;
+    let header16 = (if is_landscape { ly } else { lx }) as u32
+        | ((63.0 * p_scale).round() as u32) << 3
+        | ((63.0 * q_scale).round() as u32) << 9
+        | (is_landscape as u32) << 15;
+    let mut hash = vec![
+        (header24 & 255) as u8,
+        ((header24 >> 8) & 255) as u8,
+        (header24 >> 16) as u8,
+        (header16 & 255) as u8,
+        (header16 >> 8) as u8,
+    ];
+    if let Some((a_dc, _, a_scale)) = &alpha_channel {
+        hash.push((15.0 * a_dc).round() as u8 | ((15.0 * a_scale).round() as u8) << 4);
+    }
+
+    // Pack the AC coefficients into nibbles
+    let ac_start = hash.len();
+    let mut ac_index = 0;
+    let alpha_ac = alpha_channel.as_ref().map(|(_, ac, _)| ac.as_slice());
+    for ac in [&l_ac[..], &p_ac[..], &q_ac[..]]
+        .into_iter()
+        .chain(alpha_ac)
+    {
+        for f in ac {
+            let index = ac_start + (ac_index >> 1);
+            if index >= hash.len() {
+                hash.push(0);
+            }
+            hash[index] |= ((15.0 * f).round() as u8) << ((ac_index & 1) * 4);
+            ac_index += 1;
+        }
+    }
+
+    base64::engine::general_purpose::STANDARD.encode(hash)
+}
+
+/// Encodes the image as a blurhash. Returns the hash and the size it should be decoded at
+fn blurhash(image: &DynamicImage) -> anyhow::Result<(String, u32, u32)> {
+    let image = downscale(image, SAMPLE_MAX_SIZE).to_rgba8();
+    let (width, height) = image.dimensions();
+
+    // Use more components along the longer side of the image
+    let (components_x, components_y) = if width >= height { (4, 3) } else { (3, 4) };
+    let hash = blurhash::encode(components_x, components_y, width, height, image.as_raw())
+        .map_err(|err| anyhow::anyhow!("Failed to encode blurhash: {err}"))?;
+
+    let scale = BLURHASH_DECODE_SIZE as f32 / width.max(height) as f32;
+    let decode_width = ((width as f32 * scale).round() as u32).max(1);
+    let decode_height = ((height as f32 * scale).round() as u32).max(1);
+
+    Ok((hash, decode_width, decode_height))
+}
+
+/// Finds the most common color in the image as a hex color
+fn dominant_color(image: &DynamicImage) -> String {
+    let image = downscale(image, SAMPLE_MAX_SIZE).to_rgba8();
+
+    // Group similar colors into buckets and average the colors in the largest bucket
+    let mut buckets: HashMap<[u8; 3], (u32, [u32; 3])> = HashMap::new();
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+        // Mostly transparent pixels are not visible, so they don't contribute to the color of the image
+        if a < 128 {
+            continue;
+        }
+        let (count, sum) = buckets.entry([r >> 4, g >> 4, b >> 4]).or_default();
+        *count += 1;
+        sum[0] += r as u32;
+        sum[1] += g as u32;
+        sum[2] += b as u32;
+    }
+
+    // Break ties by the bucket so the color is the same every time the macro runs
+    let [r, g, b] = buckets
+        .into_iter()
+        .max_by_key(|(bucket, (count, _))| (*count, *bucket))
+        .map(|(_, (count, sum))| sum.map(|channel| (channel / count) as u8))
+        .unwrap_or_default();
+
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,142 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+};
+
+use grass::{Fs, InputSyntax, Options, StdFs};
+
+use crate::{AssetSource, CssType};
+
+/// Reads the css of a stylesheet. Sass and SCSS stylesheets are compiled to css first with `@use`, `@forward` and `@import` resolved relative to the stylesheet. Returns the css and the other files that were loaded to compile it
+pub fn read_stylesheet(
+    source: &AssetSource,
+    ty: CssType,
+) -> anyhow::Result<(String, Vec<PathBuf>)> {
+    let syntax = match ty {
+        CssType::Css => return Ok((source.read_to_string()?, Vec::new())),
+        CssType::Scss => InputSyntax::Scss,
+        CssType::Sass => InputSyntax::Sass,
+    };
+
+    let fs = LoadedFiles::default();
+    let options = Options::default().fs(&fs).input_syntax(syntax);
+    let css = match source {
+        AssetSource::Local(path) => grass::from_path(path, &options)
+            .map_err(|err| anyhow::anyhow!("Failed to compile {}:\n{err}", path.display()))?,
+        AssetSource::Remote(url) => grass::from_string(source.read_to_string()?, &options)
+            .map_err(|err| anyhow::anyhow!("Failed to compile {url}:\n{err}"))?,
+    };
+
+    let root = source.as_path().and_then(|path| path.canonicalize().ok());
+    let loaded = fs
+        .loaded
+        .into_inner()
+        .into_iter()
+        .filter(|path| Some(path) != root.as_ref())
+        .collect();
+
+    Ok((css, loaded))
+}
+
+/// A file system for the Sass compiler that remembers every file it reads so the stylesheet can be compiled again when one of them changes
+#[derive(Debug, Default)]
+struct LoadedFiles {
+    loaded: RefCell<Vec<PathBuf>>,
+}
+
+impl Fs for LoadedFiles {
+    fn is_dir(&self, path: &Path) -> bool {
+        StdFs.is_dir(path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        StdFs.is_file(path)
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        let contents = StdFs.read(path)?;
+        let path = path.canonicalize()?;
+        let mut loaded = self.loaded.borrow_mut();
+        if !loaded.contains(&path) {
+            loaded.push(path);
+        }
+        Ok(contents)
+    }
+
+    fn canonicalize(&self, path: &Path) -> std::io::Result<PathBuf> {
+        StdFs.canonicalize(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::write_files;
+
+    fn minified(css: &str) -> String {
+        css.split_whitespace().collect()
+    }
+
+    #[test]
+    fn css_is_read_as_it_is() {
+        let folder = write_files("sass-css", &[("style.css", "$not-sass: 1;")]);
+        let (css, loaded) =
+            read_stylesheet(&AssetSource::Local(folder.join("style.css")), CssType::Css).unwrap();
+
+        assert_eq!(css, "$not-sass: 1;");
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn scss_is_compiled_with_the_files_it_loads() {
+        let folder = write_files(
+            "sass-scss",
+            &[
+                (
+                    "theme.scss",
+                    "@use 'colors'; .button { color: colors.$primary; &:hover { color: red; } }",
+                ),
+                ("_colors.scss", "$primary: #2563eb;"),
+            ],
+        );
+        let (css, loaded) = read_stylesheet(
+            &AssetSource::Local(folder.join("theme.scss")),
+            CssType::Scss,
+        )
+        .unwrap();
+
+        assert_eq!(
+            minified(&css),
+            ".button{color:#2563eb;}.button:hover{color:red;}"
+        );
+        // The root stylesheet isn't tracked as a loaded file
+        assert_eq!(loaded, [folder.join("_colors.scss")]);
+    }
+
+    #[test]
+    fn indented_sass_is_compiled() {
+        let folder = write_files(
+            "sass-sass",
+            &[("theme.sass", "$size: 4px\n.card\n  padding: $size * 2\n")],
+        );
+        let (css, _) = read_stylesheet(
+            &AssetSource::Local(folder.join("theme.sass")),
+            CssType::Sass,
+        )
+        .unwrap();
+
+        assert_eq!(minified(&css), ".card{padding:8px;}");
+    }
+
+    #[test]
+    fn compile_errors_name_the_stylesheet() {
+        let folder = write_files("sass-error", &[("broken.scss", ".a { color: $missing; }")]);
+        let path = folder.join("broken.scss");
+        let error = read_stylesheet(&AssetSource::Local(path.clone()), CssType::Scss)
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains(&path.display().to_string()), "{error}");
+        assert!(error.contains("Undefined variable"), "{error}");
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::AssetSource;
+
+/// One icon in an svg sprite
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct SpriteIcon {
+    id: String,
+    source: PathBuf,
+    /// The modification time of the svg when the sprite was collected
+    last_updated: Option<String>,
+}
+
+impl SpriteIcon {
+    /// Returns the id of the symbol for the icon. This is the path of the svg relative to the sprite folder without the extension, with every character that isn't valid in an id replaced with `-`
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the path of the svg the icon was created from
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+}
+
+/// The options for an svg sprite asset that combines a folder of svg icons into one svg of `<symbol>`s
+#[derive(Serialize, Deserialize, Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct SvgSpriteOptions {
+    icons: Vec<SpriteIcon>,
+}
+
+impl Display for SvgSpriteOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "svg sprite with {} icons", self.icons.len())
+    }
+}
+
+impl SvgSpriteOptions {
+    /// The extension of the svg sprite asset
+    pub const EXTENSION: &'static str = "svg";
+
+    /// Creates the options for a sprite of every svg in the folder and its subfolders
+    pub fn from_folder(folder: &Path) -> anyhow::Result<Self> {
+        let mut icons = Vec::new();
+        collect_icons(folder, folder, &mut icons)?;
+        if icons.is_empty() {
+            anyhow::bail!("No svg icons found in sprite folder {}", folder.display());
+        }
+        // Sort the icons so the sprite is the same between builds
+        icons.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut ids = HashSet::new();
+        for icon in &icons {
+            if !ids.insert(&icon.id) {
+                anyhow::bail!(
+                    "More than one icon in sprite folder {} has the id {}",
+                    folder.display(),
+                    icon.id
+                );
+            }
+        }
+
+        Ok(Self { icons })
+    }
+
+    /// Returns the icons in the sprite
+    pub fn icons(&self) -> &[SpriteIcon] {
+        &self.icons
+    }
+}
+
+/// Finds every svg in the folder and its subfolders
+fn collect_icons(root: &Path, folder: &Path, icons: &mut Vec<SpriteIcon>) -> anyhow::Result<()> {
+    let entries = std::fs::read_dir(folder)
+        .with_context(|| format!("Failed to read sprite folder {}", folder.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_icons(root, &path, icons)?;
+            continue;
+        }
+        if path.extension().and_then(|extension| extension.to_str()) != Some("svg") {
+            continue;
+        }
+
+        let id = path
+            .strip_prefix(root)?
+            .with_extension("")
+            .iter()
+            .map(|segment| segment.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("-")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        icons.push(SpriteIcon {
+            id,
+            last_updated: AssetSource::Local(path.clone()).last_updated(),
+            source: path,
+        });
+    }
+    Ok(())
+}
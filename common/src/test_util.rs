@@ -0,0 +1,35 @@
+use std::{
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use tempfile::TempDir;
+
+/// A temporary folder for a test. The folder and everything in it is removed when it is dropped
+pub(crate) struct TestFolder {
+    path: PathBuf,
+    _dir: TempDir,
+}
+
+impl Deref for TestFolder {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Writes the files into a new temporary folder named after the test. The folder derefs to its canonical path
+pub(crate) fn write_files(name: &str, files: &[(&str, &str)]) -> TestFolder {
+    let dir = tempfile::Builder::new()
+        .prefix(&format!("manganis-{name}-"))
+        .tempdir()
+        .unwrap();
+    let path = dir.path().canonicalize().unwrap();
+    for (file, contents) in files {
+        let file = path.join(file);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, contents).unwrap();
+    }
+    TestFolder { path, _dir: dir }
+}
@@ -0,0 +1,218 @@
+use quote::{quote, ToTokens};
+use syn::parse::Parse;
+
+use crate::{css_module::CssModuleAssetParser, sprite::IconSpriteAssetParser};
+
+/// An asset declared as a struct with a const for each name in the asset. Css modules get a const for each class and icon sprites get a const for each symbol id:
+/// `mg!(pub struct BUTTON = css_module("assets/button.css"))`
+pub struct AssetStructParser {
+    attributes: Vec<syn::Attribute>,
+    visibility: syn::Visibility,
+    ident: syn::Ident,
+    source: proc_macro2::TokenStream,
+    asset: syn::Result<StructAsset>,
+}
+
+impl AssetStructParser {
+    /// Returns true if the input declares a struct instead of an asset expression
+    pub fn peek(input: syn::parse::ParseStream) -> bool {
+        let fork = input.fork();
+        fork.call(syn::Attribute::parse_outer).is_ok()
+            && fork.parse::<syn::Visibility>().is_ok()
+            && fork.peek(syn::Token![struct])
+    }
+}
+
+impl Parse for AssetStructParser {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let attributes = input.call(syn::Attribute::parse_outer)?;
+        let visibility = input.parse::<syn::Visibility>()?;
+        input.parse::<syn::Token![struct]>()?;
+        let ident = input.parse::<syn::Ident>()?;
+        input.parse::<syn::Token![=]>()?;
+
+        let source = input.fork().parse::<proc_macro2::TokenStream>()?;
+        let asset_type = input.parse::<syn::Ident>()?;
+        let asset = match asset_type.to_string().as_str() {
+            "css_module" => input
+                .parse::<CssModuleAssetParser>()
+                .map(StructAsset::CssModule),
+            "icon_sprite" => input
+                .parse::<IconSpriteAssetParser>()
+                .and_then(StructAsset::icon_sprite),
+            _ => {
+                return Err(syn::Error::new(
+                    asset_type.span(),
+                    format!("Only css modules and icon sprites can be declared as a struct, but {ident} is a {asset_type} asset"),
+                ))
+            }
+        };
+
+        Ok(AssetStructParser {
+            attributes,
+            visibility,
+            ident,
+            source,
+            asset,
+        })
+    }
+}
+
+impl ToTokens for AssetStructParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let attributes = &self.attributes;
+        let visibility = &self.visibility;
+        let ident = &self.ident;
+        let source = &self.source;
+
+        let (consts, target, asset) = match &self.asset {
+            Ok(asset) => {
+                let consts = asset.consts().into_iter().map(|(ident, doc, value)| {
+                    quote! {
+                        #[doc = #doc]
+                        pub const #ident: &'static str = #value;
+                    }
+                });
+                (
+                    quote! { #(#consts)* },
+                    asset.target(),
+                    asset.into_token_stream(),
+                )
+            }
+            Err(e) => (
+                quote! {},
+                quote! { manganis::CssModuleAsset },
+                e.to_compile_error(),
+            ),
+        };
+
+        tokens.extend(quote! {
+            #(#attributes)*
+            #[allow(non_camel_case_types)]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+            #visibility struct #ident;
+
+            const _: &dyn manganis::ForMgMacro = {
+                use manganis::*;
+                &#source
+            };
+
+            #[allow(non_upper_case_globals)]
+            impl #ident {
+                #consts
+            }
+
+            impl std::ops::Deref for #ident {
+                type Target = #target;
+
+                fn deref(&self) -> &Self::Target {
+                    const ASSET: #target = #asset;
+                    &ASSET
+                }
+            }
+        })
+    }
+}
+
+/// An asset that can be declared as a struct
+enum StructAsset {
+    CssModule(CssModuleAssetParser),
+    /// An icon sprite with the rust identifier of each symbol id
+    IconSprite(IconSpriteAssetParser, Vec<syn::Ident>),
+}
+
+impl StructAsset {
+    fn icon_sprite(sprite: IconSpriteAssetParser) -> syn::Result<Self> {
+        let mut idents: Vec<syn::Ident> = Vec::new();
+        for id in &sprite.ids {
+            let ident = rust_ident(id);
+            if let Some(other) = idents.iter().position(|other| *other == ident) {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!(
+                        "The icons {} and {id} are both named {ident} in rust. Rename one of the icons",
+                        sprite.ids[other]
+                    ),
+                ));
+            }
+            idents.push(ident);
+        }
+        Ok(Self::IconSprite(sprite, idents))
+    }
+
+    /// Returns the rust identifier, documentation and value of each const
+    fn consts(&self) -> Vec<(&syn::Ident, String, &str)> {
+        match self {
+            Self::CssModule(css_module) => css_module
+                .classes
+                .iter()
+                .map(|(ident, name, scoped)| {
+                    (
+                        ident,
+                        format!("The scoped class names for `.{name}`"),
+                        scoped.as_str(),
+                    )
+                })
+                .collect(),
+            Self::IconSprite(sprite, idents) => idents
+                .iter()
+                .zip(&sprite.ids)
+                .map(|(ident, id)| (ident, format!("The id of the `{id}` symbol"), id.as_str()))
+                .collect(),
+        }
+    }
+
+    /// Returns the type the struct dereferences to
+    fn target(&self) -> proc_macro2::TokenStream {
+        match self {
+            Self::CssModule(_) => quote! { manganis::CssModuleAsset },
+            Self::IconSprite(..) => quote! { manganis::IconSpriteAsset },
+        }
+    }
+}
+
+impl ToTokens for StructAsset {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        match self {
+            Self::CssModule(css_module) => css_module.to_tokens(tokens),
+            Self::IconSprite(sprite, _) => sprite.to_tokens(tokens),
+        }
+    }
+}
+
+/// Turns a name into a rust identifier by replacing every character that isn't valid in an identifier with `_`
+pub(crate) fn rust_ident(name: &str) -> syn::Ident {
+    let mut ident: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    match ident.as_str() {
+        // These keywords can't be raw identifiers
+        "_" | "self" | "Self" | "super" | "crate" => {
+            ident.push('_');
+            syn::Ident::new(&ident, proc_macro2::Span::call_site())
+        }
+        _ => syn::parse_str::<syn::Ident>(&ident)
+            .unwrap_or_else(|_| syn::Ident::new_raw(&ident, proc_macro2::Span::call_site())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_become_rust_identifiers() {
+        let ident = |name| rust_ident(name).to_string();
+        assert_eq!(ident("primary"), "primary");
+        assert_eq!(ident("icon-button"), "icon_button");
+        assert_eq!(ident("arrows/left"), "arrows_left");
+        assert_eq!(ident("2xl"), "_2xl");
+        assert_eq!(ident("type"), "r#type");
+        assert_eq!(ident("self"), "self_");
+        assert_eq!(ident("_"), "__");
+    }
+}
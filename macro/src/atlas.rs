@@ -0,0 +1,217 @@
+use manganis_common::{
+    AssetSource, AssetType, FileAsset, FileOptions, ImageOptions, ManganisSupportError,
+};
+use quote::{quote, ToTokens};
+use syn::{parenthesized, parse::Parse};
+
+use crate::generate_link_sections;
+use crate::image::{ImageSize, ImageType};
+
+/// The largest size of a sprite sheet if no size is set. Most GPUs support textures at least this large
+const DEFAULT_MAX_SIZE: (u32, u32) = (2048, 2048);
+
+struct ParseAtlasOptions {
+    options: Vec<ParseAtlasOption>,
+}
+
+impl ParseAtlasOptions {
+    fn apply_to_options(self, options: &mut AtlasOptions) {
+        for option in self.options {
+            option.apply_to_options(options);
+        }
+    }
+}
+
+impl Parse for ParseAtlasOptions {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut options = Vec::new();
+        while !input.is_empty() {
+            options.push(input.parse::<ParseAtlasOption>()?);
+        }
+        Ok(ParseAtlasOptions { options })
+    }
+}
+
+/// The options for packing the atlas
+struct AtlasOptions {
+    max_size: (u32, u32),
+    padding: u32,
+    ty: manganis_common::ImageType,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_SIZE,
+            padding: 0,
+            ty: manganis_common::ImageType::Png,
+        }
+    }
+}
+
+enum ParseAtlasOption {
+    MaxSize((u32, u32)),
+    Padding(u32),
+    Format(manganis_common::ImageType),
+}
+
+impl ParseAtlasOption {
+    fn apply_to_options(self, options: &mut AtlasOptions) {
+        match self {
+            ParseAtlasOption::MaxSize(max_size) => options.max_size = max_size,
+            ParseAtlasOption::Padding(padding) => options.padding = padding,
+            ParseAtlasOption::Format(ty) => options.ty = ty,
+        }
+    }
+}
+
+impl Parse for ParseAtlasOption {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let _ = input.parse::<syn::Token![.]>()?;
+        let ident = input.parse::<syn::Ident>()?;
+        let content;
+        parenthesized!(content in input);
+        match ident.to_string().as_str() {
+            "max_size" => {
+                let size = content.parse::<ImageSize>()?;
+                Ok(ParseAtlasOption::MaxSize((size.width, size.height)))
+            }
+            "padding" => {
+                let padding = content.parse::<syn::LitInt>()?.base10_parse()?;
+                Ok(ParseAtlasOption::Padding(padding))
+            }
+            "format" => {
+                let ty: manganis_common::ImageType = content.parse::<ImageType>()?.into();
+                // Every sheet is encoded as one image, so the format can't be picked per image
+                if ty == manganis_common::ImageType::Auto {
+                    return Err(syn::Error::new(
+                        proc_macro2::Span::call_site(),
+                        "Sprite sheets can't use the auto image type",
+                    ));
+                }
+                Ok(ParseAtlasOption::Format(ty))
+            }
+            _ => Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "Unknown atlas option: {}. Supported options are max_size, padding, format",
+                    ident
+                ),
+            )),
+        }
+    }
+}
+
+/// One frame in the atlas
+struct AtlasFrame {
+    name: String,
+    sheet: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+pub struct AtlasAssetParser {
+    sheets: Vec<Result<String, ManganisSupportError>>,
+    frames: Vec<AtlasFrame>,
+    assets: Vec<AssetType>,
+}
+
+impl Parse for AtlasAssetParser {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inside;
+        parenthesized!(inside in input);
+        let path = inside.parse::<syn::LitStr>()?;
+
+        let parsed_options = {
+            if input.is_empty() {
+                None
+            } else {
+                Some(input.parse::<ParseAtlasOptions>()?)
+            }
+        };
+
+        let path_as_str = path.value();
+        let folder = match AssetSource::parse_folder(&path_as_str) {
+            Ok(AssetSource::Local(folder)) => folder,
+            Ok(AssetSource::Remote(url)) => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Atlas assets must be a local folder, but {url} is a url"),
+                ))
+            }
+            Err(e) => return Err(syn::Error::new(proc_macro2::Span::call_site(), e)),
+        };
+
+        let mut options = AtlasOptions::default();
+        if let Some(parsed_options) = parsed_options {
+            parsed_options.apply_to_options(&mut options);
+        }
+
+        let sheets = manganis_common::pack_atlas(&folder, options.max_size, options.padding)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
+
+        let mut sheet_names = Vec::new();
+        let mut frames = Vec::new();
+        let mut assets = Vec::new();
+        for (index, sheet) in sheets.into_iter().enumerate() {
+            for frame in sheet.frames() {
+                frames.push(AtlasFrame {
+                    name: frame.name().to_string(),
+                    sheet: index,
+                    x: frame.x(),
+                    y: frame.y(),
+                    width: frame.width(),
+                    height: frame.height(),
+                });
+            }
+
+            // Each sheet is generated from every image in it, but the file asset still needs one source. The first image of the sheet is used so the name of the sheet is readable
+            let source = AssetSource::Local(sheet.frames()[0].source().to_path_buf());
+            let mut image_options = ImageOptions::new(options.ty, None);
+            image_options.set_atlas(Some(sheet));
+            let asset = FileAsset::new(source).with_options(FileOptions::Image(image_options));
+            sheet_names.push(asset.served_location());
+            assets.push(AssetType::File(asset));
+        }
+        frames.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(AtlasAssetParser {
+            sheets: sheet_names,
+            frames,
+            assets,
+        })
+    }
+}
+
+impl ToTokens for AtlasAssetParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let link_sections = generate_link_sections(self.assets.iter().cloned());
+
+        let sheets = self.sheets.iter().map(crate::quote_path);
+
+        let frames = self.frames.iter().map(|frame| {
+            let AtlasFrame {
+                name,
+                sheet,
+                x,
+                y,
+                width,
+                height,
+            } = frame;
+            quote! {
+                manganis::AtlasFrame::new(#name, #sheet, #x, #y, #width, #height)
+            }
+        });
+
+        tokens.extend(quote! {
+            {
+                #link_sections
+                const SHEETS: &[&str] = &[#(#sheets),*];
+                const FRAMES: &[manganis::AtlasFrame] = &[#(#frames),*];
+                manganis::AtlasAsset::new(SHEETS, FRAMES)
+            }
+        })
+    }
+}
@@ -1,17 +1,18 @@
 use manganis_common::{
-    AssetSource, AssetType, CssOptions, FileAsset, FileOptions, ManganisSupportError,
+    collect_css_dependencies, AssetSource, AssetType, Config, CssOptions, FileAsset, FileOptions,
+    ManganisSupportError,
 };
 use quote::{quote, ToTokens};
-use syn::{parenthesized, parse::Parse, LitBool};
+use syn::{bracketed, parenthesized, parse::Parse, LitBool, LitStr};
 
-use crate::generate_link_section;
+use crate::{generate_link_section, generate_link_sections};
 
-struct ParseCssOptions {
+pub(crate) struct ParseCssOptions {
     options: Vec<ParseCssOption>,
 }
 
 impl ParseCssOptions {
-    fn apply_to_options(self, file: &mut FileAsset) {
+    pub(crate) fn apply_to_options(self, file: &mut FileAsset) {
         for option in self.options {
             option.apply_to_options(file);
         }
@@ -32,26 +33,55 @@ enum ParseCssOption {
     UrlEncoded(bool),
     Preload(bool),
     Minify(bool),
+    Targets(String),
+    Bundle(bool),
+    Purge(bool),
+    Safelist(Vec<String>),
+    InlineBelow(u64),
+    SourceMap(bool),
 }
 
 impl ParseCssOption {
     fn apply_to_options(self, file: &mut FileAsset) {
         match self {
-            ParseCssOption::Preload(_) | ParseCssOption::Minify(_) => {
-                file.with_options_mut(|options| {
-                    if let FileOptions::Css(options) = options {
-                        match self {
-                            ParseCssOption::Minify(format) => {
-                                options.set_minify(format);
-                            }
-                            ParseCssOption::Preload(preload) => {
-                                options.set_preload(preload);
-                            }
-                            _ => {}
+            ParseCssOption::Preload(_)
+            | ParseCssOption::Minify(_)
+            | ParseCssOption::Targets(_)
+            | ParseCssOption::Bundle(_)
+            | ParseCssOption::Purge(_)
+            | ParseCssOption::Safelist(_)
+            | ParseCssOption::InlineBelow(_)
+            | ParseCssOption::SourceMap(_) => file.with_options_mut(|options| {
+                if let FileOptions::Css(options) = options {
+                    match self {
+                        ParseCssOption::Minify(format) => {
+                            options.set_minify(format);
+                        }
+                        ParseCssOption::Preload(preload) => {
+                            options.set_preload(preload);
+                        }
+                        ParseCssOption::Targets(targets) => {
+                            options.set_targets(Some(targets));
+                        }
+                        ParseCssOption::Bundle(bundle) => {
+                            options.set_bundle(bundle);
+                        }
+                        ParseCssOption::Purge(purge) => {
+                            options.set_purge(purge);
+                        }
+                        ParseCssOption::Safelist(safelist) => {
+                            options.set_safelist(safelist);
+                        }
+                        ParseCssOption::InlineBelow(inline_below) => {
+                            options.set_inline_below(Some(inline_below));
                         }
+                        ParseCssOption::SourceMap(source_map) => {
+                            options.set_source_map(source_map);
+                        }
+                        _ => {}
                     }
-                })
-            }
+                }
+            }),
             ParseCssOption::UrlEncoded(url_encoded) => {
                 file.set_url_encoded(url_encoded);
             }
@@ -76,10 +106,37 @@ impl Parse for ParseCssOption {
             "minify" => {
                 Ok(ParseCssOption::Minify(content.parse::<LitBool>()?.value()))
             }
+            "targets" => {
+                Ok(ParseCssOption::Targets(content.parse::<LitStr>()?.value()))
+            }
+            "bundle" => {
+                Ok(ParseCssOption::Bundle(true))
+            }
+            "purge" => {
+                Ok(ParseCssOption::Purge(true))
+            }
+            "safelist" => {
+                let inside;
+                bracketed!(inside in content);
+                let classes =
+                    syn::punctuated::Punctuated::<LitStr, syn::Token![,]>::parse_terminated(
+                        &inside,
+                    )?;
+                Ok(ParseCssOption::Safelist(
+                    classes.into_iter().map(|class| class.value()).collect(),
+                ))
+            }
+            "inline_below" => {
+                let inline_below = content.parse::<syn::LitInt>()?.base10_parse()?;
+                Ok(ParseCssOption::InlineBelow(inline_below))
+            }
+            "source_map" => {
+                Ok(ParseCssOption::SourceMap(true))
+            }
             _ => Err(syn::Error::new(
                 proc_macro2::Span::call_site(),
                 format!(
-                    "Unknown Css option: {}. Supported options are preload, url_encoded, and minify",
+                    "Unknown Css option: {}. Supported options are preload, url_encoded, minify, targets, bundle, purge, safelist, inline_below, and source_map",
                     ident
                 ),
             )),
@@ -89,6 +146,7 @@ impl Parse for ParseCssOption {
 
 pub struct CssAssetParser {
     file_name: Result<String, ManganisSupportError>,
+    dependencies: Vec<FileAsset>,
     asset: AssetType,
 }
 
@@ -116,32 +174,51 @@ impl Parse for CssAssetParser {
                 ))
             }
         };
-        let mut this_file = FileAsset::new(path.clone())
-            .with_options(manganis_common::FileOptions::Css(CssOptions::new()));
+        // Css without its own targets uses the targets in the config. They are part of the options so the css is processed again if the config changes
+        let mut options = CssOptions::new();
+        let config = Config::current();
+        options.set_ty(path.type_from_extension());
+        options.set_targets(config.css_targets().map(String::from));
+        options.set_source_map(config.source_maps());
+        let mut this_file =
+            FileAsset::new(path.clone()).with_options(manganis_common::FileOptions::Css(options));
         if let Some(parsed_options) = parsed_options {
             parsed_options.apply_to_options(&mut this_file);
         }
 
+        let dependencies = collect_css_dependencies(&mut this_file)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
         let asset = manganis_common::AssetType::File(this_file.clone());
 
-        let file_name = if this_file.url_encoded() {
-            #[cfg(not(feature = "url-encoding"))]
-            return Err(syn::Error::new(
-                proc_macro2::Span::call_site(),
-                "URL encoding is not enabled. Enable the url-encoding feature to use this feature",
-            ));
-            #[cfg(feature = "url-encoding")]
-            Ok(crate::url_encoded_asset(&this_file).map_err(|e| {
-                syn::Error::new(
-                    proc_macro2::Span::call_site(),
-                    format!("Failed to encode file: {}", e),
-                )
-            })?)
-        } else {
-            this_file.served_location()
-        };
+        let file_name = css_file_name(&this_file)?;
 
-        Ok(CssAssetParser { file_name, asset })
+        Ok(CssAssetParser {
+            file_name,
+            dependencies,
+            asset,
+        })
+    }
+}
+
+/// Returns the path the css is served at, or the css encoded into a data url if the css is url encoded
+pub(crate) fn css_file_name(
+    this_file: &FileAsset,
+) -> syn::Result<Result<String, ManganisSupportError>> {
+    if this_file.url_encoded() {
+        #[cfg(not(feature = "url-encoding"))]
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "URL encoding is not enabled. Enable the url-encoding feature to use this feature",
+        ));
+        #[cfg(feature = "url-encoding")]
+        Ok(Ok(crate::url_encoded_asset(this_file).map_err(|e| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("Failed to encode file: {}", e),
+            )
+        })?))
+    } else {
+        Ok(this_file.served_location())
     }
 }
 
@@ -150,12 +227,19 @@ impl ToTokens for CssAssetParser {
         let file_name = crate::quote_path(&self.file_name);
 
         let link_section = generate_link_section(self.asset.clone());
+        let dependencies = dependency_link_sections(&self.dependencies);
 
         tokens.extend(quote! {
             {
                 #link_section
+                #dependencies
                 #file_name
             }
         })
     }
 }
+
+/// Collects the files an asset references
+pub(crate) fn dependency_link_sections(dependencies: &[FileAsset]) -> proc_macro2::TokenStream {
+    generate_link_sections(dependencies.iter().cloned().map(AssetType::File))
+}
@@ -0,0 +1,122 @@
+use manganis_common::{
+    collect_css_dependencies, css_module_classes, css_module_scope, read_stylesheet, AssetSource,
+    AssetType, Config, CssOptions, CssType, FileAsset, FileOptions, ManganisSupportError,
+};
+use quote::{quote, ToTokens};
+use syn::{parenthesized, parse::Parse};
+
+use crate::{
+    asset_struct::rust_ident,
+    css::{dependency_link_sections, ParseCssOptions},
+    generate_link_section,
+};
+
+pub struct CssModuleAssetParser {
+    file_name: Result<String, ManganisSupportError>,
+    /// The rust identifier, name and scoped class names of each class in the stylesheet
+    pub(crate) classes: Vec<(syn::Ident, String, String)>,
+    dependencies: Vec<FileAsset>,
+    asset: AssetType,
+}
+
+impl Parse for CssModuleAssetParser {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inside;
+        parenthesized!(inside in input);
+        let path = inside.parse::<syn::LitStr>()?;
+
+        let parsed_options = {
+            if input.is_empty() {
+                None
+            } else {
+                Some(input.parse::<ParseCssOptions>()?)
+            }
+        };
+
+        let path_as_str = path.value();
+        let path: AssetSource = match AssetSource::parse_file(&path_as_str) {
+            Ok(path) => path,
+            Err(e) => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("{e}"),
+                ))
+            }
+        };
+
+        let ty: CssType = path.type_from_extension();
+        let scope = css_module_scope(&path);
+        let (css, _) = read_stylesheet(&path, ty)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
+        let mut classes: Vec<(syn::Ident, String, String)> = Vec::new();
+        for class in css_module_classes(&css, &scope)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?
+        {
+            let ident = rust_ident(class.name());
+            if let Some((_, other, _)) = classes.iter().find(|(other, ..)| *other == ident) {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!(
+                        "The classes {other} and {} in {path_as_str} are both named {ident} in rust. Rename one of the classes",
+                        class.name()
+                    ),
+                ));
+            }
+            classes.push((ident, class.name().to_string(), class.scoped().to_string()));
+        }
+
+        let mut options = CssOptions::new();
+        let config = Config::current();
+        options.set_ty(ty);
+        options.set_targets(config.css_targets().map(String::from));
+        options.set_source_map(config.source_maps());
+        options.set_module_scope(Some(scope));
+        let mut this_file = FileAsset::new(path).with_options(FileOptions::Css(options));
+        if let Some(parsed_options) = parsed_options {
+            parsed_options.apply_to_options(&mut this_file);
+        }
+        // The classes in a css module are renamed, so they never match the classes the application uses with the classes! macro
+        if let FileOptions::Css(options) = this_file.options() {
+            if options.purge() || !options.safelist().is_empty() {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    "Css modules can't be purged because their classes are renamed. Only include the styles the component uses in the module instead",
+                ));
+            }
+        }
+
+        let dependencies = collect_css_dependencies(&mut this_file)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
+
+        let file_name = crate::css::css_file_name(&this_file)?;
+
+        Ok(CssModuleAssetParser {
+            file_name,
+            classes,
+            dependencies,
+            asset: AssetType::File(this_file),
+        })
+    }
+}
+
+impl ToTokens for CssModuleAssetParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let file_name = crate::quote_path(&self.file_name);
+        let classes = self
+            .classes
+            .iter()
+            .map(|(_, name, scoped)| quote! { (#name, #scoped) });
+
+        let link_section = generate_link_section(self.asset.clone());
+        let dependencies = dependency_link_sections(&self.dependencies);
+
+        tokens.extend(quote! {
+            {
+                #link_section
+                #dependencies
+                const CLASSES: &[(&str, &str)] = &[#(#classes),*];
+                manganis::CssModuleAsset::new(#file_name, CLASSES)
+            }
+        })
+    }
+}
@@ -1,11 +1,15 @@
-use manganis_common::{AssetSource, AssetType, FileAsset, ManganisSupportError};
+use manganis_common::{
+    collect_css_dependencies, collect_html_dependencies, AssetSource, AssetType, FileAsset,
+    ManganisSupportError,
+};
 use quote::{quote, ToTokens};
 use syn::{parenthesized, parse::Parse};
 
-use crate::generate_link_section;
+use crate::{css::dependency_link_sections, generate_link_section};
 
 pub struct FileAssetParser {
     file_name: Result<String, ManganisSupportError>,
+    dependencies: Vec<FileAsset>,
     asset: AssetType,
 }
 
@@ -25,12 +29,23 @@ impl Parse for FileAssetParser {
                 ))
             }
         };
-        let this_file = FileAsset::new(path);
+        let mut this_file = FileAsset::new(path);
+        // Stylesheets and html documents are collected with the files they reference
+        let mut dependencies = collect_css_dependencies(&mut this_file)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
+        dependencies.extend(
+            collect_html_dependencies(&mut this_file)
+                .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?,
+        );
         let asset = manganis_common::AssetType::File(this_file.clone());
 
         let file_name = this_file.served_location();
 
-        Ok(FileAssetParser { file_name, asset })
+        Ok(FileAssetParser {
+            file_name,
+            dependencies,
+            asset,
+        })
     }
 }
 
@@ -39,10 +54,12 @@ impl ToTokens for FileAssetParser {
         let file_name = crate::quote_path(&self.file_name);
 
         let link_section = generate_link_section(self.asset.clone());
+        let dependencies = dependency_link_sections(&self.dependencies);
 
         tokens.extend(quote! {
             {
                 #link_section
+                #dependencies
                 #file_name
             }
         })
@@ -0,0 +1,261 @@
+use manganis_common::{
+    AssetSource, AssetType, FileAsset, FileOptions, IconRole, ImageOptions, ImageType,
+    ManganisSupportError,
+};
+use quote::{quote, ToTokens};
+use syn::{bracketed, parenthesized, parse::Parse};
+
+use crate::generate_link_sections;
+
+/// The sizes of the png icons in an icon set if no sizes are set
+const DEFAULT_ICON_SIZES: [u32; 5] = [16, 32, 48, 192, 512];
+/// The size of the apple touch icon
+const APPLE_TOUCH_ICON_SIZE: u32 = 180;
+/// The sizes of the maskable icons. These are the sizes PWAs need to be installable
+const MASKABLE_ICON_SIZES: [u32; 2] = [192, 512];
+/// The color behind apple touch and maskable icons if no background is set
+const DEFAULT_BACKGROUND: [u8; 3] = [255, 255, 255];
+
+struct ParseIconOptions {
+    options: Vec<ParseIconOption>,
+}
+
+impl ParseIconOptions {
+    fn apply_to_options(self, options: &mut IconSetOptions) {
+        for option in self.options {
+            option.apply_to_options(options);
+        }
+    }
+}
+
+impl Parse for ParseIconOptions {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let mut options = Vec::new();
+        while !input.is_empty() {
+            options.push(input.parse::<ParseIconOption>()?);
+        }
+        Ok(ParseIconOptions { options })
+    }
+}
+
+/// The options for every image in the icon set
+struct IconSetOptions {
+    sizes: Vec<u32>,
+    background: [u8; 3],
+}
+
+impl Default for IconSetOptions {
+    fn default() -> Self {
+        Self {
+            sizes: DEFAULT_ICON_SIZES.to_vec(),
+            background: DEFAULT_BACKGROUND,
+        }
+    }
+}
+
+enum ParseIconOption {
+    Sizes(Vec<u32>),
+    Background([u8; 3]),
+}
+
+impl ParseIconOption {
+    fn apply_to_options(self, options: &mut IconSetOptions) {
+        match self {
+            ParseIconOption::Sizes(sizes) => options.sizes = sizes,
+            ParseIconOption::Background(background) => options.background = background,
+        }
+    }
+}
+
+impl Parse for ParseIconOption {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let _ = input.parse::<syn::Token![.]>()?;
+        let ident = input.parse::<syn::Ident>()?;
+        let content;
+        parenthesized!(content in input);
+        match ident.to_string().as_str() {
+            "sizes" => {
+                let inside;
+                bracketed!(inside in content);
+                let sizes =
+                    syn::punctuated::Punctuated::<syn::LitInt, syn::Token![,]>::parse_separated_nonempty(
+                        &inside,
+                    )?;
+                let sizes = sizes
+                    .into_iter()
+                    .map(|size| {
+                        let value: u32 = size.base10_parse()?;
+                        if value == 0 {
+                            return Err(syn::Error::new(
+                                size.span(),
+                                "Icon sizes must be larger than 0",
+                            ));
+                        }
+                        Ok(value)
+                    })
+                    .collect::<syn::Result<_>>()?;
+                Ok(ParseIconOption::Sizes(sizes))
+            }
+            "background" => {
+                let r = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let g = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let b = content.parse::<syn::LitInt>()?.base10_parse()?;
+                Ok(ParseIconOption::Background([r, g, b]))
+            }
+            _ => Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "Unknown icon option: {}. Supported options are sizes, background",
+                    ident
+                ),
+            )),
+        }
+    }
+}
+
+/// One png icon in the icon set
+struct IconVariant {
+    file_name: Result<String, ManganisSupportError>,
+    size: u32,
+    role: IconRole,
+}
+
+pub struct IconAssetParser {
+    favicon: Result<String, ManganisSupportError>,
+    icons: Vec<IconVariant>,
+    manifest: Result<String, ManganisSupportError>,
+    assets: Vec<AssetType>,
+}
+
+/// Creates the file asset for one image in the icon set
+fn icon_file(source: &AssetSource, ty: ImageType, size: u32, role: IconRole) -> FileAsset {
+    let mut options = ImageOptions::new(ty, Some((size, size)));
+    options.set_icon(Some(role));
+    FileAsset::new(source.clone()).with_options(FileOptions::Image(options))
+}
+
+/// Generates the icons entry of a web app manifest
+fn manifest_icons(icons: &[IconVariant]) -> Result<String, ManganisSupportError> {
+    let mut entries = Vec::new();
+    for icon in icons {
+        let purpose = match icon.role {
+            IconRole::Icon => "any",
+            IconRole::Maskable { .. } => "maskable",
+            // Apple touch icons are linked from the head of the page instead of the manifest
+            _ => continue,
+        };
+        entries.push(serde_json::json!({
+            "src": icon.file_name.clone()?,
+            "sizes": format!("{0}x{0}", icon.size),
+            "type": "image/png",
+            "purpose": purpose,
+        }));
+    }
+    Ok(serde_json::Value::Array(entries).to_string())
+}
+
+impl Parse for IconAssetParser {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inside;
+        parenthesized!(inside in input);
+        let path = inside.parse::<syn::LitStr>()?;
+
+        let parsed_options = {
+            if input.is_empty() {
+                None
+            } else {
+                Some(input.parse::<ParseIconOptions>()?)
+            }
+        };
+
+        let path_as_str = path.value();
+        let source: AssetSource = match AssetSource::parse_file(&path_as_str) {
+            Ok(path) => path,
+            Err(e) => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("{e}"),
+                ))
+            }
+        };
+
+        let mut options = IconSetOptions::default();
+        if let Some(parsed_options) = parsed_options {
+            parsed_options.apply_to_options(&mut options);
+        }
+
+        let mut assets = Vec::new();
+
+        // The favicon contains every favicon size, so its own size is the largest one. Svg icons are rendered at that size before they are scaled down
+        let favicon_size = IconRole::FAVICON_SIZES
+            .into_iter()
+            .max()
+            .unwrap_or_default();
+        let favicon = icon_file(&source, ImageType::Ico, favicon_size, IconRole::Favicon);
+        let favicon_name = favicon.served_location();
+        assets.push(AssetType::File(favicon));
+
+        let background = options.background;
+        let roles = options
+            .sizes
+            .iter()
+            .map(|&size| (size, IconRole::Icon))
+            .chain([(APPLE_TOUCH_ICON_SIZE, IconRole::AppleTouch { background })])
+            .chain(
+                MASKABLE_ICON_SIZES
+                    .iter()
+                    .map(|&size| (size, IconRole::Maskable { background })),
+            );
+        let mut icons = Vec::new();
+        for (size, role) in roles {
+            let file = icon_file(&source, ImageType::Png, size, role);
+            icons.push(IconVariant {
+                file_name: file.served_location(),
+                size,
+                role,
+            });
+            assets.push(AssetType::File(file));
+        }
+
+        let manifest = manifest_icons(&icons);
+
+        Ok(IconAssetParser {
+            favicon: favicon_name,
+            icons,
+            manifest,
+            assets,
+        })
+    }
+}
+
+impl ToTokens for IconAssetParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let favicon = crate::quote_path(&self.favicon);
+        let manifest = crate::quote_path(&self.manifest);
+
+        let link_sections = generate_link_sections(self.assets.iter().cloned());
+
+        let icons = self.icons.iter().map(|icon| {
+            let file_name = crate::quote_path(&icon.file_name);
+            let size = icon.size;
+            let purpose = match icon.role {
+                IconRole::AppleTouch { .. } => quote! { manganis::IconPurpose::AppleTouch },
+                IconRole::Maskable { .. } => quote! { manganis::IconPurpose::Maskable },
+                _ => quote! { manganis::IconPurpose::Any },
+            };
+            quote! {
+                manganis::Icon::new(#file_name, #size, #purpose)
+            }
+        });
+
+        tokens.extend(quote! {
+            {
+                #link_sections
+                const ICONS: &[manganis::Icon] = &[#(#icons),*];
+                manganis::IconAsset::new(#favicon, ICONS, #manifest)
+            }
+        })
+    }
+}
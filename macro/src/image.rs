@@ -1,22 +1,33 @@
 use manganis_common::ManganisSupportError;
-use manganis_common::{AssetSource, AssetType, FileAsset, FileOptions, ImageOptions};
+use manganis_common::{
+    AssetSource, AssetType, FileAsset, FileOptions, ImageOptions, ImageTransform, Placeholder,
+    PlaceholderType,
+};
 use quote::{quote, ToTokens};
-use syn::{parenthesized, parse::Parse, Token};
+use syn::{bracketed, parenthesized, parse::Parse, Token};
 
-use crate::generate_link_section;
+use crate::generate_link_sections;
 
 struct ParseImageOptions {
     options: Vec<ParseImageOption>,
 }
 
 impl ParseImageOptions {
-    fn apply_to_options(self, file: &mut FileAsset, low_quality_preview: &mut bool) {
+    fn apply_to_options(self, file: &mut FileAsset, macro_options: &mut ImageMacroOptions) {
         for option in self.options {
-            option.apply_to_options(file, low_quality_preview);
+            option.apply_to_options(file, macro_options);
         }
     }
 }
 
+/// Options that change the code the macro generates instead of the processed file
+#[derive(Default)]
+struct ImageMacroOptions {
+    low_quality_preview: bool,
+    formats: Vec<manganis_common::ImageType>,
+    placeholder: Option<PlaceholderType>,
+}
+
 impl Parse for ParseImageOptions {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let mut options = Vec::new();
@@ -29,18 +40,26 @@ impl Parse for ParseImageOptions {
 
 enum ParseImageOption {
     Format(manganis_common::ImageType),
+    Formats(Vec<manganis_common::ImageType>),
     Size((u32, u32)),
     Preload(bool),
     UrlEncoded(bool),
     Lqip(bool),
+    Placeholder(PlaceholderType),
+    Transform(ImageTransform),
+    KeepMetadata(bool),
+    MinQuality(f32),
 }
 
 impl ParseImageOption {
-    fn apply_to_options(self, file: &mut FileAsset, low_quality_preview: &mut bool) {
+    fn apply_to_options(self, file: &mut FileAsset, macro_options: &mut ImageMacroOptions) {
         match self {
             ParseImageOption::Format(_)
             | ParseImageOption::Size(_)
-            | ParseImageOption::Preload(_) => file.with_options_mut(|options| {
+            | ParseImageOption::Preload(_)
+            | ParseImageOption::Transform(_)
+            | ParseImageOption::KeepMetadata(_)
+            | ParseImageOption::MinQuality(_) => file.with_options_mut(|options| {
                 if let FileOptions::Image(options) = options {
                     match self {
                         ParseImageOption::Format(format) => {
@@ -52,6 +71,15 @@ impl ParseImageOption {
                         ParseImageOption::Preload(preload) => {
                             options.set_preload(preload);
                         }
+                        ParseImageOption::Transform(transform) => {
+                            options.add_transform(transform);
+                        }
+                        ParseImageOption::KeepMetadata(keep_metadata) => {
+                            options.set_keep_metadata(keep_metadata);
+                        }
+                        ParseImageOption::MinQuality(min_quality) => {
+                            options.set_min_quality(Some(min_quality));
+                        }
                         _ => {}
                     }
                 }
@@ -59,8 +87,14 @@ impl ParseImageOption {
             ParseImageOption::UrlEncoded(url_encoded) => {
                 file.set_url_encoded(url_encoded);
             }
+            ParseImageOption::Formats(formats) => {
+                macro_options.formats = formats;
+            }
             ParseImageOption::Lqip(lqip) => {
-                *low_quality_preview = lqip;
+                macro_options.low_quality_preview = lqip;
+            }
+            ParseImageOption::Placeholder(placeholder) => {
+                macro_options.placeholder = Some(placeholder);
             }
         }
     }
@@ -77,6 +111,10 @@ impl Parse for ParseImageOption {
                 let format = content.parse::<ImageType>()?;
                 Ok(ParseImageOption::Format(format.into()))
             }
+            "formats" => {
+                let formats = content.parse::<ImageTypes>()?;
+                Ok(ParseImageOption::Formats(formats.0))
+            }
             "size" => {
                 let size = content.parse::<ImageSize>()?;
                 Ok(ParseImageOption::Size((size.width, size.height)))
@@ -91,10 +129,96 @@ impl Parse for ParseImageOption {
             "low_quality_preview" => {
                 Ok(ParseImageOption::Lqip(true))
             }
+            "keep_metadata" => {
+                Ok(ParseImageOption::KeepMetadata(true))
+            }
+            "min_quality" => {
+                // Accept whole numbers too so min_quality(1) means a lossless image
+                let min_quality = content.parse::<syn::Lit>()?;
+                let value = match &min_quality {
+                    syn::Lit::Float(value) => value.base10_parse::<f32>()?,
+                    syn::Lit::Int(value) => value.base10_parse::<f32>()?,
+                    _ => {
+                        return Err(syn::Error::new(
+                            min_quality.span(),
+                            "The minimum quality must be a number between 0 and 1",
+                        ))
+                    }
+                };
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(syn::Error::new(
+                        min_quality.span(),
+                        "The minimum quality must be between 0 and 1",
+                    ));
+                }
+                Ok(ParseImageOption::MinQuality(value))
+            }
+            "placeholder" => {
+                let placeholder = content.parse::<ImagePlaceholderType>()?;
+                Ok(ParseImageOption::Placeholder(placeholder.0))
+            }
+            "rotate" => {
+                let degrees = content.parse::<syn::LitInt>()?;
+                let transform = match degrees.base10_parse::<u32>()? {
+                    90 => ImageTransform::Rotate90,
+                    180 => ImageTransform::Rotate180,
+                    270 => ImageTransform::Rotate270,
+                    _ => {
+                        return Err(syn::Error::new(
+                            degrees.span(),
+                            "Images can only be rotated by 90, 180, or 270 degrees",
+                        ))
+                    }
+                };
+                Ok(ParseImageOption::Transform(transform))
+            }
+            "flip_horizontal" => Ok(ParseImageOption::Transform(ImageTransform::FlipHorizontal)),
+            "flip_vertical" => Ok(ParseImageOption::Transform(ImageTransform::FlipVertical)),
+            "crop" => {
+                let x = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let y = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let size = content.parse::<ImageSize>()?;
+                Ok(ParseImageOption::Transform(ImageTransform::Crop {
+                    x,
+                    y,
+                    width: size.width,
+                    height: size.height,
+                }))
+            }
+            "grayscale" => Ok(ParseImageOption::Transform(ImageTransform::Grayscale)),
+            "blur" => {
+                let sigma = content.parse::<Number<f32>>()?;
+                Ok(ParseImageOption::Transform(ImageTransform::Blur(sigma.0)))
+            }
+            "brightness" => {
+                let amount = content.parse::<Number<i32>>()?;
+                Ok(ParseImageOption::Transform(ImageTransform::Brightness(
+                    amount.0,
+                )))
+            }
+            "contrast" => {
+                let amount = content.parse::<Number<f32>>()?;
+                Ok(ParseImageOption::Transform(ImageTransform::Contrast(amount.0)))
+            }
+            "tint" => {
+                let r = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let g = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let b = content.parse::<syn::LitInt>()?.base10_parse()?;
+                let _ = content.parse::<syn::Token![,]>()?;
+                let amount = content.parse::<Number<f32>>()?;
+                Ok(ParseImageOption::Transform(ImageTransform::Tint {
+                    color: [r, g, b],
+                    amount: amount.0,
+                }))
+            }
             _ => Err(syn::Error::new(
                 proc_macro2::Span::call_site(),
                 format!(
-                    "Unknown image option: {}. Supported options are format, size, preload, url_encoded, low_quality_preview",
+                    "Unknown image option: {}. Supported options are format, formats, size, preload, url_encoded, low_quality_preview, keep_metadata, min_quality, placeholder, rotate, flip_horizontal, flip_vertical, crop, grayscale, blur, brightness, contrast, tint",
                     ident
                 ),
             )),
@@ -102,9 +226,9 @@ impl Parse for ParseImageOption {
     }
 }
 
-struct ImageSize {
-    width: u32,
-    height: u32,
+pub(crate) struct ImageSize {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
 }
 
 impl Parse for ImageSize {
@@ -119,6 +243,33 @@ impl Parse for ImageSize {
     }
 }
 
+/// A number literal that may be negative and may be written as an integer or a float
+struct Number<T>(T);
+
+impl<T: std::str::FromStr> Parse for Number<T>
+where
+    T::Err: std::fmt::Display,
+{
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let negative = input.parse::<Option<Token![-]>>()?.is_some();
+        let lit = input.parse::<syn::Lit>()?;
+        let digits = match &lit {
+            syn::Lit::Int(int) => int.base10_digits(),
+            syn::Lit::Float(float) => float.base10_digits(),
+            _ => return Err(syn::Error::new(lit.span(), "Expected a number")),
+        };
+        let number = if negative {
+            format!("-{digits}")
+        } else {
+            digits.to_string()
+        };
+        number
+            .parse()
+            .map(Self)
+            .map_err(|e| syn::Error::new(lit.span(), e))
+    }
+}
+
 impl From<ImageType> for manganis_common::ImageType {
     fn from(val: ImageType) -> Self {
         val.0
@@ -130,16 +281,17 @@ impl Parse for ImageType {
         let _ = input.parse::<syn::Ident>()?;
         let _ = input.parse::<Token![::]>()?;
         let ident = input.parse::<syn::Ident>()?;
-        ident
-            .to_string()
-            .to_lowercase()
-            .as_str()
-            .parse::<manganis_common::ImageType>()
+        let ty = ident.to_string().to_lowercase();
+        // Auto is not a file extension, so it is not handled by the common image type parser
+        if ty == "auto" {
+            return Ok(Self(manganis_common::ImageType::Auto));
+        }
+        ty.parse::<manganis_common::ImageType>()
             .map_err(|_| {
                 syn::Error::new(
                     proc_macro2::Span::call_site(),
                     format!(
-                        "Unknown image type: {}. Supported types are png, jpeg, webp, avif",
+                        "Unknown image type: {}. Supported types are png, jpeg, webp, avif, gif, bmp, tiff, ico, qoi, auto",
                         ident
                     ),
                 )
@@ -149,7 +301,21 @@ impl Parse for ImageType {
 }
 
 #[derive(Clone, Copy)]
-struct ImageType(manganis_common::ImageType);
+pub(crate) struct ImageType(manganis_common::ImageType);
+
+struct ImageTypes(Vec<manganis_common::ImageType>);
+
+impl Parse for ImageTypes {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inside;
+        bracketed!(inside in input);
+        let array =
+            syn::punctuated::Punctuated::<ImageType, syn::Token![,]>::parse_separated_nonempty(
+                &inside,
+            )?;
+        Ok(ImageTypes(array.into_iter().map(Into::into).collect()))
+    }
+}
 
 impl Default for ImageType {
     fn default() -> Self {
@@ -157,10 +323,101 @@ impl Default for ImageType {
     }
 }
 
+struct ImagePlaceholderType(PlaceholderType);
+
+impl Parse for ImagePlaceholderType {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let _ = input.parse::<syn::Ident>()?;
+        let _ = input.parse::<Token![::]>()?;
+        let ident = input.parse::<syn::Ident>()?;
+        ident
+            .to_string()
+            .to_lowercase()
+            .parse::<PlaceholderType>()
+            .map_err(|_| {
+                syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!(
+                        "Unknown placeholder type: {}. Supported types are ThumbHash, BlurHash, DominantColor",
+                        ident
+                    ),
+                )
+            })
+            .map(Self)
+    }
+}
+
+/// One of the formats an image is encoded as when the formats option is used
+struct ImageVariant {
+    file_name: Result<String, ManganisSupportError>,
+    mime_type: &'static str,
+}
+
 pub struct ImageAssetParser {
     file_name: Result<String, ManganisSupportError>,
     low_quality_preview: Option<String>,
-    asset: AssetType,
+    placeholder: Option<Placeholder>,
+    variants: Vec<ImageVariant>,
+    assets: Vec<AssetType>,
+}
+
+/// Resolves the path the image will be served from, encoding the image into the url if needed
+fn image_file_name(file: &FileAsset) -> syn::Result<Result<String, ManganisSupportError>> {
+    if file.url_encoded() {
+        #[cfg(not(feature = "url-encoding"))]
+        return Err(syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "URL encoding is not enabled. Enable the url-encoding feature to use this feature",
+        ));
+        #[cfg(feature = "url-encoding")]
+        Ok(Ok(crate::url_encoded_asset(file).map_err(|e| {
+            syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("Failed to encode file: {}", e),
+            )
+        })?))
+    } else {
+        Ok(file.served_location())
+    }
+}
+
+/// Replaces an auto image type with the concrete type the image will be encoded as
+fn resolve_auto_type(file: &mut FileAsset, path_as_str: &str) -> syn::Result<()> {
+    if let FileOptions::Image(options) = file.options() {
+        let mut options = options.clone();
+        options
+            .resolve_auto(file.location().source())
+            .map_err(|e| {
+                syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Failed to pick an image type for {path_as_str}: {e}"),
+                )
+            })?;
+        file.with_options_mut(|file_options| *file_options = FileOptions::Image(options));
+    }
+    Ok(())
+}
+
+/// The quality of an image is measured by decoding the encoded image again, which only works for png and jpg images
+fn verify_min_quality(file: &FileAsset, path_as_str: &str) -> syn::Result<()> {
+    if let FileOptions::Image(options) = file.options() {
+        let ty = options.ty();
+        if options.min_quality().is_some()
+            && !matches!(
+                ty,
+                manganis_common::ImageType::Png | manganis_common::ImageType::Jpg
+            )
+        {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "min_quality can only be used with png and jpg images, but {path_as_str} is encoded as {}. Use .format(ImageType::Png) or .format(ImageType::Jpg) to measure the quality of the image",
+                    ty.extension()
+                ),
+            ));
+        }
+    }
+    Ok(())
 }
 
 impl Parse for ImageAssetParser {
@@ -191,31 +448,40 @@ impl Parse for ImageAssetParser {
             FileAsset::new(path.clone()).with_options(manganis_common::FileOptions::Image(
                 ImageOptions::new(manganis_common::ImageType::Avif, None),
             ));
-        let mut low_quality_preview = false;
+        let mut macro_options = ImageMacroOptions::default();
         if let Some(parsed_options) = parsed_options {
-            parsed_options.apply_to_options(&mut this_file, &mut low_quality_preview);
+            parsed_options.apply_to_options(&mut this_file, &mut macro_options);
         }
 
-        let asset = manganis_common::AssetType::File(this_file.clone());
+        // If the image has multiple formats, each format is processed into a separate file. The last format is the fallback that the image path points to
+        let mut variants = Vec::new();
+        let mut assets = Vec::new();
+        for format in &macro_options.formats {
+            let mut variant = this_file.clone();
+            variant.with_options_mut(|options| {
+                if let FileOptions::Image(options) = options {
+                    options.set_ty(*format);
+                }
+            });
+            resolve_auto_type(&mut variant, &path_as_str)?;
+            verify_min_quality(&variant, &path_as_str)?;
+            variants.push(ImageVariant {
+                file_name: image_file_name(&variant)?,
+                mime_type: manganis_common::get_mime_from_ext(variant.options().extension()),
+            });
+            assets.push(manganis_common::AssetType::File(variant.clone()));
+            this_file = variant;
+        }
+        if macro_options.formats.is_empty() {
+            // Auto images need to be resolved now so the served location has the right extension
+            resolve_auto_type(&mut this_file, &path_as_str)?;
+            verify_min_quality(&this_file, &path_as_str)?;
+            assets.push(manganis_common::AssetType::File(this_file.clone()));
+        }
 
-        let file_name = if this_file.url_encoded() {
-            #[cfg(not(feature = "url-encoding"))]
-            return Err(syn::Error::new(
-                proc_macro2::Span::call_site(),
-                "URL encoding is not enabled. Enable the url-encoding feature to use this feature",
-            ));
-            #[cfg(feature = "url-encoding")]
-            Ok(crate::url_encoded_asset(&this_file).map_err(|e| {
-                syn::Error::new(
-                    proc_macro2::Span::call_site(),
-                    format!("Failed to encode file: {}", e),
-                )
-            })?)
-        } else {
-            this_file.served_location()
-        };
+        let file_name = image_file_name(&this_file)?;
 
-        let low_quality_preview = if low_quality_preview {
+        let low_quality_preview = if macro_options.low_quality_preview {
             #[cfg(not(feature = "url-encoding"))]
             return Err(syn::Error::new(
                 proc_macro2::Span::call_site(),
@@ -253,10 +519,26 @@ impl Parse for ImageAssetParser {
             None
         };
 
+        let placeholder = match (macro_options.placeholder, this_file.options()) {
+            (Some(placeholder), FileOptions::Image(options)) => Some(
+                placeholder
+                    .generate(this_file.location().source(), options)
+                    .map_err(|e| {
+                        syn::Error::new(
+                            proc_macro2::Span::call_site(),
+                            format!("Failed to generate a placeholder for {path_as_str}: {e}"),
+                        )
+                    })?,
+            ),
+            _ => None,
+        };
+
         Ok(ImageAssetParser {
             file_name,
             low_quality_preview,
-            asset,
+            placeholder,
+            variants,
+            assets,
         })
     }
 }
@@ -268,13 +550,45 @@ impl ToTokens for ImageAssetParser {
             Some(lqip) => quote! { Some(#lqip) },
             None => quote! { None },
         };
+        let placeholder = match &self.placeholder {
+            Some(Placeholder::ThumbHash(hash)) => {
+                quote! { Some(manganis::ImagePlaceholder::ThumbHash(#hash)) }
+            }
+            Some(Placeholder::BlurHash {
+                hash,
+                width,
+                height,
+            }) => quote! {
+                Some(manganis::ImagePlaceholder::BlurHash {
+                    hash: #hash,
+                    width: #width,
+                    height: #height,
+                })
+            },
+            Some(Placeholder::DominantColor(color)) => {
+                quote! { Some(manganis::ImagePlaceholder::DominantColor(#color)) }
+            }
+            None => quote! { None },
+        };
+
+        let link_sections = generate_link_sections(self.assets.iter().cloned());
 
-        let link_section = generate_link_section(self.asset.clone());
+        let variants = self.variants.iter().map(|variant| {
+            let file_name = crate::quote_path(&variant.file_name);
+            let mime_type = variant.mime_type;
+            quote! {
+                manganis::ImageVariant::new(#file_name, #mime_type)
+            }
+        });
 
         tokens.extend(quote! {
             {
-                #link_section
-                manganis::ImageAsset::new(#file_name).with_preview(#low_quality_preview)
+                #link_sections
+                const VARIANTS: &[manganis::ImageVariant] = &[#(#variants),*];
+                manganis::ImageAsset::new(#file_name)
+                    .with_preview(#low_quality_preview)
+                    .with_placeholder(#placeholder)
+                    .with_variants(VARIANTS)
             }
         })
     }
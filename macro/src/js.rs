@@ -1,16 +1,31 @@
 use manganis_common::{
-    AssetSource, AssetType, FileAsset, FileOptions, JsOptions, JsType, ManganisSupportError,
+    collect_js_dependencies, verify_js_define, AssetSource, AssetType, Config, EsVersion,
+    FileAsset, FileOptions, JsOptions, ManganisSupportError,
 };
 use quote::{quote, ToTokens};
-use syn::{parenthesized, parse::Parse, LitBool};
+use syn::{parenthesized, parse::Parse, spanned::Spanned, LitBool, LitStr, Token};
 
-use crate::generate_link_section;
+use crate::{css::dependency_link_sections, generate_link_section};
 
 struct ParseJsOptions {
     options: Vec<ParseJsOption>,
 }
 
 impl ParseJsOptions {
+    /// Returns the environment variables the defines read their values from
+    fn env_vars(&self) -> Vec<String> {
+        self.options
+            .iter()
+            .filter_map(|option| match option {
+                ParseJsOption::Define {
+                    env_var: Some(env_var),
+                    ..
+                } => Some(env_var.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
     fn apply_to_options(self, file: &mut FileAsset) {
         for option in self.options {
             option.apply_to_options(file);
@@ -32,26 +47,65 @@ enum ParseJsOption {
     UrlEncoded(bool),
     Preload(bool),
     Minify(bool),
+    SourceMap(bool),
+    Target(EsVersion),
+    Bundle(bool),
+    Module(bool),
+    ImportMap(String),
+    Define {
+        name: String,
+        value: String,
+        env_var: Option<String>,
+    },
 }
 
 impl ParseJsOption {
     fn apply_to_options(self, file: &mut FileAsset) {
         match self {
-            ParseJsOption::Preload(_) | ParseJsOption::Minify(_) => {
-                file.with_options_mut(|options| {
-                    if let FileOptions::Js(options) = options {
-                        match self {
-                            ParseJsOption::Minify(format) => {
-                                options.set_minify(format);
-                            }
-                            ParseJsOption::Preload(preload) => {
-                                options.set_preload(preload);
-                            }
-                            _ => {}
+            ParseJsOption::Preload(_)
+            | ParseJsOption::Minify(_)
+            | ParseJsOption::SourceMap(_)
+            | ParseJsOption::Target(_)
+            | ParseJsOption::Bundle(_)
+            | ParseJsOption::Module(_)
+            | ParseJsOption::ImportMap(_)
+            | ParseJsOption::Define { .. } => file.with_options_mut(|options| {
+                if let FileOptions::Js(options) = options {
+                    match self {
+                        ParseJsOption::Minify(format) => {
+                            options.set_minify(format);
+                        }
+                        ParseJsOption::Preload(preload) => {
+                            options.set_preload(preload);
+                        }
+                        ParseJsOption::SourceMap(source_map) => {
+                            options.set_source_map(source_map);
+                        }
+                        ParseJsOption::Target(target) => {
+                            options.set_target(target);
                         }
+                        ParseJsOption::Bundle(bundle) => {
+                            options.set_bundle(bundle);
+                        }
+                        ParseJsOption::Module(module) => {
+                            options.set_module(module);
+                        }
+                        ParseJsOption::ImportMap(specifier) => {
+                            // Only modules can be imported with a specifier from the import map
+                            options.set_module(true);
+                            options.set_import_specifier(Some(specifier));
+                        }
+                        ParseJsOption::Define { name, value, .. } => {
+                            // Defining the same global again replaces the earlier value
+                            let mut defines = options.defines().to_vec();
+                            defines.retain(|(defined, _)| *defined != name);
+                            defines.push((name, value));
+                            options.set_defines(defines);
+                        }
+                        _ => {}
                     }
-                })
-            }
+                }
+            }),
             ParseJsOption::UrlEncoded(url_encoded) => {
                 file.set_url_encoded(url_encoded);
             }
@@ -72,10 +126,25 @@ impl Parse for ParseJsOption {
             }
             "url_encoded" => Ok(ParseJsOption::UrlEncoded(true)),
             "minify" => Ok(ParseJsOption::Minify(content.parse::<LitBool>()?.value())),
+            "source_map" => Ok(ParseJsOption::SourceMap(true)),
+            "target" => Ok(ParseJsOption::Target(parse_es_version(&content)?)),
+            "bundle" => Ok(ParseJsOption::Bundle(true)),
+            "module" => Ok(ParseJsOption::Module(true)),
+            "import_map" => {
+                let specifier = content.parse::<LitStr>()?;
+                if specifier.value().is_empty() {
+                    return Err(syn::Error::new(
+                        specifier.span(),
+                        "The import map specifier cannot be empty",
+                    ));
+                }
+                Ok(ParseJsOption::ImportMap(specifier.value()))
+            }
+            "define" => parse_define(&content),
             _ => Err(syn::Error::new(
                 proc_macro2::Span::call_site(),
                 format!(
-                    "Unknown Js option: {}. Supported options are preload, url_encoded, and minify",
+                    "Unknown Js option: {}. Supported options are preload, url_encoded, minify, source_map, target, bundle, module, import_map, and define",
                     ident
                 ),
             )),
@@ -83,8 +152,70 @@ impl Parse for ParseJsOption {
     }
 }
 
+/// Parses a version of the standard like `EsVersion::Es2017`
+fn parse_es_version(input: syn::parse::ParseStream) -> syn::Result<EsVersion> {
+    let _ = input.parse::<syn::Ident>()?;
+    let _ = input.parse::<Token![::]>()?;
+    let ident = input.parse::<syn::Ident>()?;
+    ident.to_string().to_lowercase().parse().map_err(|_| {
+        syn::Error::new(
+            ident.span(),
+            format!(
+                "Unknown ES version: {}. Supported versions are Es5, Es2015 through Es2022, and EsNext",
+                ident
+            ),
+        )
+    })
+}
+
+/// Parses a global and the js expression it is replaced with like `"DEBUG", "false"`. The value can also be read from an environment variable at compile time with `env!("API_BASE")`, in which case it is replaced with the value as a js string
+fn parse_define(input: syn::parse::ParseStream) -> syn::Result<ParseJsOption> {
+    let name = input.parse::<LitStr>()?;
+    let _ = input.parse::<Token![,]>()?;
+    let (value, span, env_var) = if input.peek(LitStr) {
+        let value = input.parse::<LitStr>()?;
+        (value.value(), value.span(), None)
+    } else {
+        let mac = input.parse::<syn::Macro>()?;
+        if !mac.path.is_ident("env") {
+            return Err(syn::Error::new_spanned(
+                &mac.path,
+                "The value of a define must be a string literal or env!(\"NAME\")",
+            ));
+        }
+        let env_var = mac
+            .parse_body_with(|input: syn::parse::ParseStream| {
+                let env_var = input.parse::<LitStr>()?;
+                // env! may be given a custom error message after the name
+                let _ = input.parse::<proc_macro2::TokenStream>()?;
+                Ok(env_var)
+            })?
+            .value();
+        let value = std::env::var(&env_var).map_err(|_| {
+            syn::Error::new_spanned(
+                &mac,
+                format!("environment variable `{env_var}` not defined at compile time"),
+            )
+        })?;
+        let value = serde_json::to_string(&value).unwrap();
+        (value, mac.path.span(), Some(env_var))
+    };
+    let _ = input.parse::<Option<Token![,]>>()?;
+
+    verify_js_define(&name.value(), &value)
+        .map_err(|err| syn::Error::new(span, format!("{err}")))?;
+
+    Ok(ParseJsOption::Define {
+        name: name.value(),
+        value,
+        env_var,
+    })
+}
+
 pub struct JsAssetParser {
     file_name: Result<String, ManganisSupportError>,
+    dependencies: Vec<FileAsset>,
+    env_vars: Vec<String>,
     asset: AssetType,
 }
 
@@ -112,12 +243,23 @@ impl Parse for JsAssetParser {
                 ))
             }
         };
-        let mut this_file = FileAsset::new(path.clone())
-            .with_options(manganis_common::FileOptions::Js(JsOptions::new(JsType::Js)));
+        // Js without its own source map option or target uses the settings in the config
+        let mut options = JsOptions::new(path.type_from_extension());
+        let config = Config::current();
+        options.set_source_map(config.source_maps());
+        options.set_target(config.js_target());
+        let mut this_file =
+            FileAsset::new(path.clone()).with_options(manganis_common::FileOptions::Js(options));
+        let env_vars = parsed_options
+            .as_ref()
+            .map(ParseJsOptions::env_vars)
+            .unwrap_or_default();
         if let Some(parsed_options) = parsed_options {
             parsed_options.apply_to_options(&mut this_file);
         }
 
+        let dependencies = collect_js_dependencies(&mut this_file)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
         let asset = manganis_common::AssetType::File(this_file.clone());
 
         let file_name = if this_file.url_encoded() {
@@ -137,7 +279,12 @@ impl Parse for JsAssetParser {
             this_file.served_location()
         };
 
-        Ok(JsAssetParser { file_name, asset })
+        Ok(JsAssetParser {
+            file_name,
+            dependencies,
+            env_vars,
+            asset,
+        })
     }
 }
 
@@ -146,10 +293,15 @@ impl ToTokens for JsAssetParser {
         let file_name = crate::quote_path(&self.file_name);
 
         let link_section = generate_link_section(self.asset.clone());
+        let dependencies = dependency_link_sections(&self.dependencies);
+        // Reading the environment variables with env! makes cargo rebuild the crate when they change
+        let env_vars = &self.env_vars;
 
         tokens.extend(quote! {
             {
                 #link_section
+                #dependencies
+                #(const _: &str = env!(#env_vars);)*
                 #file_name
             }
         })
@@ -1,30 +1,42 @@
 #![doc = include_str!("../README.md")]
 #![deny(missing_docs)]
 
+use asset_struct::AssetStructParser;
+use atlas::AtlasAssetParser;
 use css::CssAssetParser;
+use css_module::CssModuleAssetParser;
 use file::FileAssetParser;
 use folder::FolderAssetParser;
 use font::FontAssetParser;
+use icon::IconAssetParser;
 use image::ImageAssetParser;
 use js::JsAssetParser;
 use json::JsonAssetParser;
 use manganis_common::cache::macro_log_file;
-use manganis_common::{AssetSource, MetadataAsset, TailwindAsset};
+use manganis_common::{AssetSource, MetadataAsset, SnippetType, TailwindAsset};
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::{quote, quote_spanned, ToTokens};
+use snippet::SnippetAssetParser;
+use sprite::IconSpriteAssetParser;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use syn::{parse::Parse, parse_macro_input, LitStr};
 
+mod asset_struct;
+mod atlas;
 mod css;
+mod css_module;
 mod file;
 mod folder;
 mod font;
+mod icon;
 mod image;
 mod js;
 mod json;
+mod snippet;
+mod sprite;
 
 static LOG_FILE_FRESH: AtomicBool = AtomicBool::new(false);
 
@@ -53,7 +65,7 @@ fn generate_link_section(asset: manganis_common::AssetType) -> TokenStream2 {
 
     let asset_description = serde_json::to_string(&asset).unwrap();
 
-    let len = asset_description.as_bytes().len();
+    let len = asset_description.len();
 
     let asset_bytes = syn::LitByteStr::new(asset_description.as_bytes(), position);
 
@@ -69,6 +81,23 @@ fn generate_link_section(asset: manganis_common::AssetType) -> TokenStream2 {
     }
 }
 
+/// Generates a link section for each asset. Each asset gets its own block so the link section statics don't collide
+fn generate_link_sections(
+    assets: impl IntoIterator<Item = manganis_common::AssetType>,
+) -> TokenStream2 {
+    let link_sections = assets.into_iter().map(|asset| {
+        let link_section = generate_link_section(asset);
+        quote! {
+            {
+                #link_section
+            }
+        }
+    });
+    quote! {
+        #(#link_sections)*
+    }
+}
+
 /// Collects tailwind classes that will be included in the final binary and returns them unmodified
 ///
 /// ```rust
@@ -147,15 +176,36 @@ pub fn classes(input: TokenStream) -> TokenStream {
 pub fn mg(input: TokenStream) -> TokenStream {
     trace_to_file();
 
-    let asset = parse_macro_input!(input as AnyAssetParser);
+    let input = parse_macro_input!(input as MgInput);
 
-    quote! {
-        #asset
+    match input {
+        MgInput::Asset(asset) => quote! {
+            #asset
+        },
+        MgInput::Struct(asset) => quote! {
+            #asset
+        },
     }
     .into_token_stream()
     .into()
 }
 
+/// The input to the `mg!` macro. Css modules and icon sprites can also be declared as a struct with a const for each name instead of an expression
+enum MgInput {
+    Asset(AnyAssetParser),
+    Struct(AssetStructParser),
+}
+
+impl Parse for MgInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if AssetStructParser::peek(input) {
+            Ok(Self::Struct(input.parse()?))
+        } else {
+            Ok(Self::Asset(input.parse()?))
+        }
+    }
+}
+
 #[derive(Copy, Clone, Default, PartialEq)]
 enum ReturnType {
     #[default]
@@ -184,10 +234,15 @@ impl ToTokens for AnyAssetParser {
                     tokens
                 }
             }
+            Ok(AnyAssetParserType::Icon(icon)) => icon.into_token_stream(),
+            Ok(AnyAssetParserType::Atlas(atlas)) => atlas.into_token_stream(),
+            Ok(AnyAssetParserType::IconSprite(sprite)) => sprite.into_token_stream(),
             Ok(AnyAssetParserType::Font(font)) => font.into_token_stream(),
             Ok(AnyAssetParserType::Css(css)) => css.into_token_stream(),
+            Ok(AnyAssetParserType::CssModule(css)) => css.into_token_stream(),
             Ok(AnyAssetParserType::Js(js)) => js.into_token_stream(),
             Ok(AnyAssetParserType::Json(js)) => js.into_token_stream(),
+            Ok(AnyAssetParserType::Snippet(snippet)) => snippet.into_token_stream(),
             Err(e) => e.to_compile_error(),
         };
         let source = &self.source;
@@ -259,9 +314,14 @@ enum AnyAssetParserType {
     File(FileAssetParser),
     Folder(FolderAssetParser),
     Image(ImageAssetParser),
+    Icon(IconAssetParser),
+    Atlas(AtlasAssetParser),
+    IconSprite(IconSpriteAssetParser),
     Font(FontAssetParser),
     Css(CssAssetParser),
+    CssModule(CssModuleAssetParser),
     Js(JsAssetParser),
+    Snippet(SnippetAssetParser),
     Json(JsonAssetParser),
 }
 
@@ -275,15 +335,27 @@ impl Parse for AnyAssetParserType {
             "video" | "file" => Self::File(input.parse::<FileAssetParser>()?),
             "folder" => Self::Folder(input.parse::<FolderAssetParser>()?),
             "image" => Self::Image(input.parse::<ImageAssetParser>()?),
+            "icon" => Self::Icon(input.parse::<IconAssetParser>()?),
+            "atlas" => Self::Atlas(input.parse::<AtlasAssetParser>()?),
+            "icon_sprite" => Self::IconSprite(input.parse::<IconSpriteAssetParser>()?),
             "font" => Self::Font(input.parse::<FontAssetParser>()?),
             "css" => Self::Css(input.parse::<CssAssetParser>()?),
+            "css_module" => Self::CssModule(input.parse::<CssModuleAssetParser>()?),
             "js" => Self::Js(input.parse::<JsAssetParser>()?),
             "json" => Self::Json(input.parse::<JsonAssetParser>()?),
+            "css_inline" => Self::Snippet(SnippetAssetParser::parse(input, SnippetType::Css)?),
+            "js_inline" => Self::Snippet(SnippetAssetParser::parse(input, SnippetType::Js)?),
+            "css_snippets" => {
+                Self::Snippet(SnippetAssetParser::parse_bundle(input, SnippetType::Css)?)
+            }
+            "js_snippets" => {
+                Self::Snippet(SnippetAssetParser::parse_bundle(input, SnippetType::Js)?)
+            }
             _ => {
                 return Err(syn::Error::new(
                     proc_macro2::Span::call_site(),
                     format!(
-                        "Unknown asset type: {as_string}. Supported types are file, image, font, and css"
+                        "Unknown asset type: {as_string}. Supported types are file, image, icon, atlas, icon_sprite, font, css, and css_module"
                     ),
                 ))
             }
@@ -0,0 +1,61 @@
+use manganis_common::{AssetType, SnippetAsset, SnippetType};
+use quote::{quote, ToTokens};
+use syn::{parenthesized, parse::ParseStream};
+
+use crate::generate_link_sections;
+
+pub struct SnippetAssetParser {
+    file_name: String,
+    /// The snippet to collect. This is None if the macro only returns the location of the bundle
+    asset: Option<AssetType>,
+}
+
+impl SnippetAssetParser {
+    /// Parses an inline snippet like `css_inline(".button { color: red; }")`
+    pub fn parse(input: ParseStream, ty: SnippetType) -> syn::Result<Self> {
+        let inside;
+        parenthesized!(inside in input);
+        let content = inside.parse::<syn::LitStr>()?;
+
+        // Snippets from different crates are ordered by the crate they are declared in
+        let package = std::env::var("CARGO_PKG_NAME").unwrap_or_default();
+        let this_snippet = SnippetAsset::new(ty, &package, &content.value());
+        this_snippet
+            .verify()
+            .map_err(|e| syn::Error::new(content.span(), format!("{e}")))?;
+        let asset = manganis_common::AssetType::Snippet(this_snippet.clone());
+
+        let file_name = this_snippet.served_location();
+
+        Ok(SnippetAssetParser {
+            file_name,
+            asset: Some(asset),
+        })
+    }
+
+    /// Parses the location of the bundle of every snippet of a type like `css_snippets()`
+    pub fn parse_bundle(input: ParseStream, ty: SnippetType) -> syn::Result<Self> {
+        let _inside;
+        parenthesized!(_inside in input);
+
+        Ok(SnippetAssetParser {
+            file_name: ty.served_location(),
+            asset: None,
+        })
+    }
+}
+
+impl ToTokens for SnippetAssetParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let file_name = &self.file_name;
+
+        let link_section = generate_link_sections(self.asset.clone());
+
+        tokens.extend(quote! {
+            {
+                #link_section
+                #file_name
+            }
+        })
+    }
+}
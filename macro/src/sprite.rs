@@ -0,0 +1,70 @@
+use manganis_common::{
+    AssetSource, AssetType, FileAsset, FileOptions, ManganisSupportError, SvgSpriteOptions,
+};
+use quote::{quote, ToTokens};
+use syn::{parenthesized, parse::Parse};
+
+use crate::generate_link_section;
+
+pub struct IconSpriteAssetParser {
+    file_name: Result<String, ManganisSupportError>,
+    /// The ids of the symbols in the sprite
+    pub(crate) ids: Vec<String>,
+    asset: AssetType,
+}
+
+impl Parse for IconSpriteAssetParser {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let inside;
+        parenthesized!(inside in input);
+        let path = inside.parse::<syn::LitStr>()?;
+
+        let path_as_str = path.value();
+        let folder = match AssetSource::parse_folder(&path_as_str) {
+            Ok(AssetSource::Local(folder)) => folder,
+            Ok(AssetSource::Remote(url)) => {
+                return Err(syn::Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!("Icon sprite assets must be a local folder, but {url} is a url"),
+                ))
+            }
+            Err(e) => return Err(syn::Error::new(proc_macro2::Span::call_site(), e)),
+        };
+
+        let options = SvgSpriteOptions::from_folder(&folder)
+            .map_err(|e| syn::Error::new(proc_macro2::Span::call_site(), format!("{e:#}")))?;
+        let ids = options
+            .icons()
+            .iter()
+            .map(|icon| icon.id().to_string())
+            .collect();
+
+        // The sprite is generated from every icon in the folder, but the file asset still needs one source
+        let source = AssetSource::Local(options.icons()[0].source().to_path_buf());
+        let this_file = FileAsset::new(source).with_options(FileOptions::SvgSprite(options));
+        let file_name = this_file.served_location();
+
+        Ok(IconSpriteAssetParser {
+            file_name,
+            ids,
+            asset: AssetType::File(this_file),
+        })
+    }
+}
+
+impl ToTokens for IconSpriteAssetParser {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let file_name = crate::quote_path(&self.file_name);
+        let ids = &self.ids;
+
+        let link_section = generate_link_section(self.asset.clone());
+
+        tokens.extend(quote! {
+            {
+                #link_section
+                const IDS: &[&str] = &[#(#ids),*];
+                manganis::IconSpriteAsset::new(#file_name, IDS)
+            }
+        })
+    }
+}
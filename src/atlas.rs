@@ -0,0 +1,111 @@
+/// A texture atlas asset that is built by the [`crate::mg!`] macro with [`crate::atlas`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct AtlasAsset {
+    /// The paths to the sprite sheets
+    sheets: &'static [&'static str],
+    /// The frames in every sprite sheet
+    frames: &'static [AtlasFrame],
+}
+
+impl AtlasAsset {
+    /// Creates a new texture atlas asset
+    pub const fn new(sheets: &'static [&'static str], frames: &'static [AtlasFrame]) -> Self {
+        Self { sheets, frames }
+    }
+
+    /// Returns the paths to the sprite sheets
+    pub const fn sheets(&self) -> &'static [&'static str] {
+        self.sheets
+    }
+
+    /// Returns the frames in every sprite sheet
+    pub const fn frames(&self) -> &'static [AtlasFrame] {
+        self.frames
+    }
+
+    /// Returns the frame with the given name if it is part of the atlas. The name of a frame is the path of the image relative to the atlas folder without the extension
+    pub const fn frame(&self, name: &str) -> Option<&'static AtlasFrame> {
+        let mut i = 0;
+        while i < self.frames.len() {
+            let frame = &self.frames[i];
+            if crate::str_eq(frame.name, name) {
+                return Some(frame);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    /// Returns the path to the sprite sheet that contains the frame
+    pub const fn sheet(&self, frame: &AtlasFrame) -> &'static str {
+        self.sheets[frame.sheet]
+    }
+}
+
+/// One image packed into a sprite sheet
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub struct AtlasFrame {
+    /// The name of the frame
+    name: &'static str,
+    /// The index of the sprite sheet that contains the frame
+    sheet: usize,
+    /// The x position of the frame in the sprite sheet
+    x: u32,
+    /// The y position of the frame in the sprite sheet
+    y: u32,
+    /// The width of the frame
+    width: u32,
+    /// The height of the frame
+    height: u32,
+}
+
+impl AtlasFrame {
+    /// Creates a new frame
+    pub const fn new(
+        name: &'static str,
+        sheet: usize,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Self {
+            name,
+            sheet,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns the name of the frame
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the index of the sprite sheet that contains the frame
+    pub const fn sheet(&self) -> usize {
+        self.sheet
+    }
+
+    /// Returns the x position of the frame in the sprite sheet
+    pub const fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// Returns the y position of the frame in the sprite sheet
+    pub const fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Returns the width of the frame
+    pub const fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the frame
+    pub const fn height(&self) -> u32 {
+        self.height
+    }
+}
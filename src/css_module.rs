@@ -0,0 +1,56 @@
+/// A css module asset that is built by the [`crate::mg!`] macro with [`crate::css_module`]. Css modules declared as a struct dereference to the asset:
+/// ```rust
+/// manganis::mg!(struct BUTTON = css_module("assets/button.css"));
+/// let asset: &manganis::CssModuleAsset = &BUTTON;
+/// ```
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct CssModuleAsset {
+    /// The path to the stylesheet
+    path: &'static str,
+    /// The classes in the stylesheet and the scoped class names they were renamed to
+    classes: &'static [(&'static str, &'static str)],
+}
+
+impl CssModuleAsset {
+    /// Creates a new css module asset
+    pub const fn new(path: &'static str, classes: &'static [(&'static str, &'static str)]) -> Self {
+        Self { path, classes }
+    }
+
+    /// Returns the path to the stylesheet
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Returns the classes in the stylesheet and the scoped class names they were renamed to
+    pub const fn classes(&self) -> &'static [(&'static str, &'static str)] {
+        self.classes
+    }
+
+    /// Returns the scoped class names for the class if the stylesheet contains it
+    pub const fn get(&self, class: &str) -> Option<&'static str> {
+        let mut i = 0;
+        while i < self.classes.len() {
+            let (name, scoped) = self.classes[i];
+            if crate::str_eq(name, class) {
+                return Some(scoped);
+            }
+            i += 1;
+        }
+        None
+    }
+}
+
+impl std::ops::Deref for CssModuleAsset {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.path
+    }
+}
+
+impl std::fmt::Display for CssModuleAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.path.fmt(f)
+    }
+}
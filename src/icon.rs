@@ -0,0 +1,109 @@
+/// An icon set asset that is built by the [`crate::mg!`] macro with [`crate::icon`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct IconAsset {
+    /// The path to the multi-resolution favicon.ico
+    favicon: &'static str,
+    /// The png icons in the icon set
+    icons: &'static [Icon],
+    /// The icons entry of a web app manifest
+    manifest: &'static str,
+}
+
+impl IconAsset {
+    /// Creates a new icon set asset
+    pub const fn new(
+        favicon: &'static str,
+        icons: &'static [Icon],
+        manifest: &'static str,
+    ) -> Self {
+        Self {
+            favicon,
+            icons,
+            manifest,
+        }
+    }
+
+    /// Returns the path to the multi-resolution favicon.ico
+    pub const fn favicon(&self) -> &'static str {
+        self.favicon
+    }
+
+    /// Returns the png icons in the icon set
+    pub const fn icons(&self) -> &'static [Icon] {
+        self.icons
+    }
+
+    /// Returns the json array of icons for the `icons` field of a web app manifest. It contains the standard and maskable icons
+    pub const fn manifest(&self) -> &'static str {
+        self.manifest
+    }
+
+    /// Returns the icon with the given size and purpose if it is part of the icon set
+    pub fn get(&self, size: u32, purpose: IconPurpose) -> Option<&'static Icon> {
+        self.icons
+            .iter()
+            .find(|icon| icon.size == size && icon.purpose == purpose)
+    }
+}
+
+impl std::ops::Deref for IconAsset {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.favicon
+    }
+}
+
+impl std::fmt::Display for IconAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.favicon.fmt(f)
+    }
+}
+
+/// One png icon in an icon set
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub struct Icon {
+    /// The path to the icon
+    path: &'static str,
+    /// The width and height of the icon
+    size: u32,
+    /// Where the icon is used
+    purpose: IconPurpose,
+}
+
+impl Icon {
+    /// Creates a new icon
+    pub const fn new(path: &'static str, size: u32, purpose: IconPurpose) -> Self {
+        Self {
+            path,
+            size,
+            purpose,
+        }
+    }
+
+    /// Returns the path to the icon
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Returns the width and height of the icon
+    pub const fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns where the icon is used
+    pub const fn purpose(&self) -> IconPurpose {
+        self.purpose
+    }
+}
+
+/// Where an icon in an icon set is used
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy, Hash)]
+pub enum IconPurpose {
+    /// A standard icon for browser tabs and app launchers
+    Any,
+    /// An icon for iOS home screens with a solid background
+    AppleTouch,
+    /// A PWA icon with padding around the image so it can be masked into any shape
+    Maskable,
+}
@@ -4,6 +4,17 @@
 #[cfg(feature = "macro")]
 pub use manganis_macro::*;
 
+mod atlas;
+mod css_module;
+mod icon;
+mod placeholder;
+mod sprite;
+pub use atlas::*;
+pub use css_module::*;
+pub use icon::*;
+pub use placeholder::*;
+pub use sprite::*;
+
 /// An image asset that is built by the [`mg!`] macro
 #[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
 pub struct ImageAsset {
@@ -13,6 +24,10 @@ pub struct ImageAsset {
     preview: Option<&'static str>,
     /// A caption for the image
     caption: Option<&'static str>,
+    /// Every format the image was encoded in. The last one is the fallback the path points to
+    variants: &'static [ImageVariant],
+    /// A compact placeholder for the image
+    placeholder: Option<ImagePlaceholder>,
 }
 
 impl ImageAsset {
@@ -22,6 +37,8 @@ impl ImageAsset {
             path,
             preview: None,
             caption: None,
+            variants: &[],
+            placeholder: None,
         }
     }
 
@@ -49,6 +66,91 @@ impl ImageAsset {
     pub const fn with_caption(self, caption: Option<&'static str>) -> Self {
         Self { caption, ..self }
     }
+
+    /// Returns every format the image was encoded in, in the order they were requested. The last variant is the fallback that [`ImageAsset::path`] points to
+    pub const fn variants(&self) -> &'static [ImageVariant] {
+        self.variants
+    }
+
+    /// Sets the formats the image was encoded in
+    pub const fn with_variants(self, variants: &'static [ImageVariant]) -> Self {
+        Self { variants, ..self }
+    }
+
+    /// Returns the placeholder of the image
+    pub const fn placeholder(&self) -> Option<ImagePlaceholder> {
+        self.placeholder
+    }
+
+    /// Sets the placeholder of the image
+    pub const fn with_placeholder(self, placeholder: Option<ImagePlaceholder>) -> Self {
+        Self {
+            placeholder,
+            ..self
+        }
+    }
+
+    /// Decodes the placeholder of the image into a png data url that can be shown while the full image loads
+    pub fn placeholder_data_url(&self) -> Option<String> {
+        self.placeholder
+            .and_then(|placeholder| placeholder.data_url())
+    }
+
+    #[cfg(feature = "html")]
+    /// Returns a `<picture>` element that lets the browser pick the first variant it supports and falls back to an `<img>` with the image path
+    pub fn picture(&self, alt: &str) -> String {
+        let mut html = String::from("<picture>");
+        // The last variant is the fallback image, so it doesn't need a source
+        let sources = self.variants.split_last().map(|(_, sources)| sources);
+        for variant in sources.unwrap_or_default() {
+            html.push_str(&format!(
+                "<source type=\"{}\" srcset=\"{}\">",
+                variant.mime_type,
+                escape_attribute(variant.path)
+            ));
+        }
+        html.push_str(&format!(
+            "<img src=\"{}\" alt=\"{}\"></picture>",
+            escape_attribute(self.path),
+            escape_attribute(alt)
+        ));
+        html
+    }
+}
+
+#[cfg(feature = "html")]
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// One format of an image asset that was encoded in multiple formats with [`ImageAssetBuilder::formats`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub struct ImageVariant {
+    /// The path to this variant of the image
+    path: &'static str,
+    /// The mime type of this variant of the image
+    mime_type: &'static str,
+}
+
+impl ImageVariant {
+    /// Creates a new image variant
+    pub const fn new(path: &'static str, mime_type: &'static str) -> Self {
+        Self { path, mime_type }
+    }
+
+    /// Returns the path to this variant of the image
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Returns the mime type of this variant of the image
+    pub const fn mime_type(&self) -> &'static str {
+        self.mime_type
+    }
 }
 
 impl std::ops::Deref for ImageAsset {
@@ -76,6 +178,54 @@ pub enum ImageType {
     Webp,
     /// An avif image. Avif images can compress slightly better than webp images but are not supported by all browsers
     Avif,
+    /// A gif image. Animated gifs keep all of their frames
+    Gif,
+    /// A bmp image. Bmp images are not compressed, so they are much larger than other formats
+    Bmp,
+    /// A tiff image. Tiff images are not supported by most browsers
+    Tiff,
+    /// An ico image. Ico images are used for favicons and can be at most 256x256
+    Ico,
+    /// A qoi image. Qoi images are fast to encode and decode, but are not supported by browsers
+    Qoi,
+    /// Pick the format at compile time based on the contents of the image. Images with few colors become png, flat color images like screenshots become webp, and photographs become jpg (or avif if they contain transparency)
+    Auto,
+}
+
+/// The type of placeholder to generate for an image with [`ImageAssetBuilder::placeholder`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub enum Placeholder {
+    /// A [thumbhash](https://evanw.github.io/thumbhash/). Thumbhashes are about 25 bytes and keep the aspect ratio and transparency of the image
+    ThumbHash,
+    /// A [blurhash](https://blurha.sh/). Blurhashes are about 30 bytes and are supported by many other libraries
+    BlurHash,
+    /// The most common color in the image. This is the smallest placeholder, but it doesn't show any detail
+    DominantColor,
+}
+
+/// The version of the ECMAScript standard a javascript asset is compiled to with [`JsAssetBuilder::target`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub enum EsVersion {
+    /// ES5. Classes, arrow functions and other ES2015 syntax are compiled away
+    Es5,
+    /// ES2015
+    Es2015,
+    /// ES2016
+    Es2016,
+    /// ES2017
+    Es2017,
+    /// ES2018
+    Es2018,
+    /// ES2019
+    Es2019,
+    /// ES2020. Optional chaining and nullish coalescing are supported from this version
+    Es2020,
+    /// ES2021
+    Es2021,
+    /// ES2022. Class fields are supported from this version
+    Es2022,
+    /// The latest version of the standard. The js is kept as it is written
+    EsNext,
 }
 
 /// A builder for an image asset. This must be used in the [`mg!`] macro.
@@ -112,6 +262,128 @@ impl ImageAssetBuilder {
         Self
     }
 
+    /// Encodes the image in multiple formats
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Each format is processed into a separate file that you can read from [`ImageAsset::variants`]. Modern formats like avif are much smaller, but older browsers need a fallback. List the formats from most to least preferred; the last format is used as the fallback image path
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").formats([ImageType::Avif, ImageType::Webp, ImageType::Jpg]));
+    /// ```
+    #[allow(unused)]
+    pub const fn formats<const N: usize>(self, formats: [ImageType; N]) -> Self {
+        Self
+    }
+
+    /// Rotate the image clockwise by 90, 180, or 270 degrees
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").rotate(90));
+    /// ```
+    #[allow(unused)]
+    pub const fn rotate(self, degrees: u32) -> Self {
+        Self
+    }
+
+    /// Flip the image horizontally
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").flip_horizontal());
+    /// ```
+    #[allow(unused)]
+    pub const fn flip_horizontal(self) -> Self {
+        Self
+    }
+
+    /// Flip the image vertically
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").flip_vertical());
+    /// ```
+    #[allow(unused)]
+    pub const fn flip_vertical(self) -> Self {
+        Self
+    }
+
+    /// Crop the image to the rectangle with the top left corner at (x, y) and the given size
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").crop(8, 8, 32, 32));
+    /// ```
+    #[allow(unused)]
+    pub const fn crop(self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self
+    }
+
+    /// Convert the image to grayscale
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").grayscale());
+    /// ```
+    #[allow(unused)]
+    pub const fn grayscale(self) -> Self {
+        Self
+    }
+
+    /// Blur the image with a gaussian blur. Larger sigma values blur the image more
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").blur(2.0));
+    /// ```
+    #[allow(unused)]
+    pub const fn blur(self, sigma: f32) -> Self {
+        Self
+    }
+
+    /// Brighten the image by the given amount. Negative values darken the image
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").brightness(-20));
+    /// ```
+    #[allow(unused)]
+    pub const fn brightness(self, amount: i32) -> Self {
+        Self
+    }
+
+    /// Adjust the contrast of the image by the given amount. Negative values reduce the contrast
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").contrast(15.0));
+    /// ```
+    #[allow(unused)]
+    pub const fn contrast(self, amount: f32) -> Self {
+        Self
+    }
+
+    /// Blend a color over the image. The amount controls how much of the color is blended in from 0 (none) to 1 (only the color)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").tint(255, 0, 0, 0.25));
+    /// ```
+    #[allow(unused)]
+    pub const fn tint(self, r: u8, g: u8, b: u8, amount: f32) -> Self {
+        Self
+    }
+
     /// Make the image use a low quality preview
     ///
     /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -126,6 +398,48 @@ impl ImageAssetBuilder {
         Self
     }
 
+    /// Generate a compact placeholder for the image
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The placeholder is embedded in the binary as a short string, so it is available before any network request. You can decode it into an image with [`ImageAsset::placeholder_data_url`] to show while the full image loads
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").placeholder(Placeholder::ThumbHash));
+    /// ```
+    #[allow(unused)]
+    pub const fn placeholder(self, placeholder: Placeholder) -> Self {
+        Self
+    }
+
+    /// Keep the EXIF metadata of the image
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// By default, EXIF and XMP metadata is stripped from images because it can contain sensitive information like the location a photo was taken. The EXIF orientation is always applied to the image, so the image is displayed upright either way. Metadata can only be kept in png and jpg images
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").format(ImageType::Jpg).keep_metadata());
+    /// ```
+    #[allow(unused)]
+    pub const fn keep_metadata(self) -> Self {
+        Self
+    }
+
+    /// Lower the encoder quality as far as possible while keeping the image similar to the source
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The quality is the structural similarity (SSIM) between the resized source image and the encoded image from 0 to 1. The CLI searches for the lowest encoder quality that stays above the minimum quality and reports the quality it reached. The quality can only be measured for png and jpg images, so the macro fails for any other format
+    ///
+    /// ```rust
+    /// const _: manganis::ImageAsset = manganis::mg!(image("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").format(ImageType::Jpg).min_quality(0.95));
+    /// ```
+    #[allow(unused)]
+    pub const fn min_quality(self, min_quality: f32) -> Self {
+        Self
+    }
+
     /// Make the image preloaded
     ///
     /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -175,11 +489,145 @@ impl ImageAssetBuilder {
 /// ```rust
 /// const _: manganis::ImageAsset = manganis::mg!(image("rustacean-flat-gesture.png").preload());
 /// ```
+/// Transforms like rotating, cropping, or tinting the image are applied in the order they are written, before the image is resized and encoded. You can use them to derive variants like a disabled or thumbnail version of an image from one source file:
+/// ```rust
+/// const _: manganis::ImageAsset = manganis::mg!(image("rustacean-flat-gesture.png").grayscale().blur(1.5).size(52, 52));
+/// ```
 #[allow(unused)]
 pub const fn image(path: &'static str) -> ImageAssetBuilder {
     ImageAssetBuilder
 }
 
+/// A builder for an icon set asset. This must be used in the [`mg!`] macro.
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+pub struct IconAssetBuilder;
+
+impl IconAssetBuilder {
+    /// Sets the sizes of the png icons in the icon set (default: 16, 32, 48, 192, 512)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::IconAsset = manganis::mg!(icon("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").sizes([32, 192]));
+    /// ```
+    #[allow(unused)]
+    pub const fn sizes<const N: usize>(self, sizes: [u32; N]) -> Self {
+        Self
+    }
+
+    /// Sets the color behind the apple touch icon and maskable icons (default: white)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::IconAsset = manganis::mg!(icon("https://avatars.githubusercontent.com/u/79236386?s=48&v=4").background(30, 30, 30));
+    /// ```
+    #[allow(unused)]
+    pub const fn background(self, r: u8, g: u8, b: u8) -> Self {
+        Self
+    }
+}
+
+/// Create an icon set from the local path or url to a single image
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The icon builder generates a multi-resolution favicon.ico, square png icons, an apple touch icon, and maskable PWA icons from one image. Svg images are rendered at each size, so they stay sharp:
+/// ```rust
+/// const _: manganis::IconAsset = manganis::mg!(icon("https://avatars.githubusercontent.com/u/79236386?s=48&v=4"));
+/// ```
+/// CLIs with the html feature add links to the icons to the head of the page, and [`IconAsset::manifest`] contains the icons entry for your web app manifest
+#[allow(unused)]
+pub const fn icon(path: &'static str) -> IconAssetBuilder {
+    IconAssetBuilder
+}
+
+/// A builder for a texture atlas asset. This must be used in the [`mg!`] macro.
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+pub struct AtlasAssetBuilder;
+
+impl AtlasAssetBuilder {
+    /// Sets the largest size of each sprite sheet (default: 2048x2048). Images that don't fit in one sheet are packed into more sheets
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::AtlasAsset = manganis::mg!(atlas("assets/sprites").max_size(512, 512));
+    /// ```
+    #[allow(unused)]
+    pub const fn max_size(self, width: u32, height: u32) -> Self {
+        Self
+    }
+
+    /// Sets the number of transparent pixels between frames (default: 0). Padding keeps neighboring frames from bleeding into each other when the sheet is scaled
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::AtlasAsset = manganis::mg!(atlas("assets/sprites").padding(2));
+    /// ```
+    #[allow(unused)]
+    pub const fn padding(self, padding: u32) -> Self {
+        Self
+    }
+
+    /// Sets the format of the sprite sheets (default: png)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::AtlasAsset = manganis::mg!(atlas("assets/sprites").format(ImageType::Webp));
+    /// ```
+    #[allow(unused)]
+    pub const fn format(self, format: ImageType) -> Self {
+        Self
+    }
+}
+
+/// Create a texture atlas from the local path to a folder of images
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The atlas builder packs every image in the folder and its subfolders into as few sprite sheets as possible, so they can be loaded with one request:
+/// ```rust
+/// const ATLAS: manganis::AtlasAsset = manganis::mg!(atlas("assets/sprites"));
+/// ```
+/// The frame map is available at compile time. Frames are named after the path of the image relative to the folder without the extension:
+/// ```rust
+/// const ATLAS: manganis::AtlasAsset = manganis::mg!(atlas("assets/sprites"));
+/// const COIN: &manganis::AtlasFrame = ATLAS.frame("coin").unwrap();
+/// const COIN_SHEET: &str = ATLAS.sheet(COIN);
+/// ```
+#[allow(unused)]
+pub const fn atlas(path: &'static str) -> AtlasAssetBuilder {
+    AtlasAssetBuilder
+}
+
+/// A builder for an svg icon sprite asset. This must be used in the [`mg!`] macro.
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+pub struct IconSpriteAssetBuilder;
+
+/// Create an svg icon sprite from the local path to a folder of svg icons
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The icon sprite builder optimizes every svg in the folder and its subfolders and combines them into one svg of `<symbol>`s. The id of each symbol is the path of the svg relative to the folder without the extension:
+/// ```rust
+/// const ICONS: manganis::IconSpriteAsset = manganis::mg!(icon_sprite("assets/icons"));
+/// ```
+/// Or declare the icon sprite as a struct with a const for each symbol id. Characters that can't be used in a rust identifier are replaced with `_`, so `arrows/left` becomes `ICONS::arrows_left`. Using an id that is not in the sprite is a compile error, so `<use>` references can't break:
+/// ```rust
+/// manganis::mg!(pub struct ICONS = icon_sprite("assets/icons"));
+/// let href = ICONS.href(ICONS::search);
+/// ```
+#[allow(unused)]
+pub const fn icon_sprite(path: &'static str) -> IconSpriteAssetBuilder {
+    IconSpriteAssetBuilder
+}
+
 /// A builder for a css asset. This must be used in the [`mg!`] macro.
 ///
 /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -200,6 +648,92 @@ impl CssAssetBuilder {
         Self
     }
 
+    /// Sets the browsers the css should support with a browserslist query (default: the css targets in the config)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Vendor prefixes are added and modern syntax like nesting and `oklch()` colors is lowered so the css works in every targeted browser
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("https://sindresorhus.com/github-markdown-css/github-markdown.css").targets("> 0.5%, last 2 versions, not dead"));
+    /// ```
+    #[allow(unused)]
+    pub const fn targets(self, query: &'static str) -> Self {
+        Self
+    }
+
+    /// Inline every stylesheet the css imports into one stylesheet
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Bundling follows the whole `@import` graph of a local stylesheet. Imports with media queries, supports conditions or layers are wrapped in the matching rules, so you only need to include one stylesheet in the right order
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("assets/style.css").bundle());
+    /// ```
+    #[allow(unused)]
+    pub const fn bundle(self) -> Self {
+        Self
+    }
+
+    /// Remove the style rules that use classes your application never uses
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The used classes are every class collected with the [`classes!`] macro and the classes in the [`CssAssetBuilder::safelist`]. This can make large third party stylesheets much smaller
+    ///
+    /// The classes are collected when the application is built, after the path of the stylesheet is chosen. The purged stylesheet keeps its path when the classes the application uses change, so serve it with a cache policy that revalidates it, like `Cache-Control: no-cache`, instead of caching it forever
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("https://sindresorhus.com/github-markdown-css/github-markdown.css").purge());
+    /// ```
+    #[allow(unused)]
+    pub const fn purge(self) -> Self {
+        Self
+    }
+
+    /// Sets the classes that are never removed when the css is purged
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Use the safelist for classes that are added at runtime instead of with the [`classes!`] macro
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("https://sindresorhus.com/github-markdown-css/github-markdown.css").purge().safelist(["markdown-body"]));
+    /// ```
+    #[allow(unused)]
+    pub const fn safelist<const N: usize>(self, classes: [&'static str; N]) -> Self {
+        Self
+    }
+
+    /// Inlines the files the css references with `url()` as data urls if they are smaller than the given number of bytes. Urls with a query or fragment and files without a known mime type are never inlined
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Inlining small icons, cursors and fonts saves a request for each of them. Larger files are still collected as separate assets
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("assets/style.css").inline_below(4096));
+    /// ```
+    #[allow(unused)]
+    pub const fn inline_below(self, bytes: u64) -> Self {
+        Self
+    }
+
+    /// Generate a source map for the minified css
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The source map is written next to the css and linked with a `sourceMappingURL` comment so errors and the browser's developer tools point at the original code
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(css("assets/style.css").source_map());
+    /// ```
+    #[allow(unused)]
+    pub const fn source_map(self) -> Self {
+        Self
+    }
+
     /// Make the css preloaded
     ///
     /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -232,6 +766,8 @@ impl CssAssetBuilder {
 /// A builder for a javascript asset. This must be used in the [`mg!`] macro.
 ///
 /// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// TypeScript (`.ts` and `.tsx`) is compiled to js. The types are removed and JSX is transformed into `React.createElement` calls
 pub struct JsAssetBuilder;
 
 impl JsAssetBuilder {
@@ -249,6 +785,90 @@ impl JsAssetBuilder {
         Self
     }
 
+    /// Generate a source map for the minified js
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The source map is written next to the js and linked with a `sourceMappingURL` comment so errors and the browser's developer tools point at the original code
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").source_map());
+    /// ```
+    #[allow(unused)]
+    pub const fn source_map(self) -> Self {
+        Self
+    }
+
+    /// Sets the version of the ECMAScript standard the js is compiled to (default: the `js_target` in the config, or [`EsVersion::EsNext`])
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Syntax that is newer than the target, like optional chaining or class fields, is transformed into syntax older browsers and webviews understand
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").target(EsVersion::Es2017));
+    /// ```
+    #[allow(unused)]
+    pub const fn target(self, target: EsVersion) -> Self {
+        Self
+    }
+
+    /// Bundle the modules the js imports into it
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Relative imports and packages in `node_modules` are combined into a single module so the imports keep working after the files are renamed. Exports that are never imported are removed when the js is minified. Modules loaded with a dynamic `import()` and web workers created with `new Worker(new URL("./worker.js", import.meta.url))` are bundled into their own files
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").bundle());
+    /// ```
+    #[allow(unused)]
+    pub const fn bundle(self) -> Self {
+        Self
+    }
+
+    /// Replace a global in the js with a js expression when the js is compiled
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The name can be an identifier or a member expression like `process.env.NODE_ENV`. The value is parsed as a js expression, so strings need their own quotes. A value read with `env!("NAME")` is inserted as a js string. Branches that become dead after the globals are replaced are removed when the js is minified
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").define("DEBUG", "false").define("API_BASE", "'https://example.com'"));
+    /// ```
+    #[allow(unused)]
+    pub const fn define(self, name: &'static str, value: &'static str) -> Self {
+        Self
+    }
+
+    /// Load the js as an ES module instead of a classic script. `.mjs` files are always loaded as modules
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Modules are compiled as modules and preloaded with `<link rel="modulepreload">` instead of `<link rel="preload" as="script">`
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").module());
+    /// ```
+    #[allow(unused)]
+    pub const fn module(self) -> Self {
+        Self
+    }
+
+    /// Map a bare specifier to the js in the import map. This also loads the js as an ES module
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Other modules can then import the js with the specifier, like `import { debounce } from "lodash"`, even though the js is served at a hashed location. The import map is generated from every js asset in the application
+    ///
+    /// ```rust
+    /// const _: &str = manganis::mg!(js("assets/script.js").import_map("script"));
+    /// ```
+    #[allow(unused)]
+    pub const fn import_map(self, specifier: &'static str) -> Self {
+        Self
+    }
+
     /// Make the js preloaded
     ///
     /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -325,11 +945,186 @@ impl JsonAssetBuilder {
 /// ```rust
 /// const _: &str = manganis::mg!(css("https://sindresorhus.com/github-markdown-css/github-markdown.css").preload());
 /// ```
+/// Sass and SCSS stylesheets are compiled to css before they are processed:
+/// ```rust
+/// const _: &str = manganis::mg!(css("assets/theme.scss"));
+/// ```
 #[allow(unused)]
 pub const fn css(path: &'static str) -> CssAssetBuilder {
     CssAssetBuilder
 }
 
+/// A builder for a css module asset. This must be used in the [`mg!`] macro.
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+pub struct CssModuleAssetBuilder;
+
+impl CssModuleAssetBuilder {
+    /// Sets whether the css should be minified (default: true)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").minify(false));
+    /// ```
+    #[allow(unused)]
+    pub const fn minify(self, minify: bool) -> Self {
+        Self
+    }
+
+    /// Sets the browsers the css should support with a browserslist query (default: the css targets in the config)
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").targets("> 0.5%, last 2 versions, not dead"));
+    /// ```
+    #[allow(unused)]
+    pub const fn targets(self, query: &'static str) -> Self {
+        Self
+    }
+
+    /// Inline every stylesheet the css imports into one stylesheet
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").bundle());
+    /// ```
+    #[allow(unused)]
+    pub const fn bundle(self) -> Self {
+        Self
+    }
+
+    /// Inlines the files the css references with `url()` as data urls if they are smaller than the given number of bytes. Urls with a query or fragment and files without a known mime type are never inlined
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// Inlining small icons, cursors and fonts saves a request for each of them. Larger files are still collected as separate assets
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").inline_below(4096));
+    /// ```
+    #[allow(unused)]
+    pub const fn inline_below(self, bytes: u64) -> Self {
+        Self
+    }
+
+    /// Generate a source map for the minified css
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// The source map is written next to the css and linked with a `sourceMappingURL` comment so errors and the browser's developer tools point at the original code
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").source_map());
+    /// ```
+    #[allow(unused)]
+    pub const fn source_map(self) -> Self {
+        Self
+    }
+
+    /// Make the css preloaded
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").preload());
+    /// ```
+    #[allow(unused)]
+    pub const fn preload(self) -> Self {
+        Self
+    }
+
+    /// Make the css URL encoded
+    ///
+    /// > **Note**: This will do nothing outside of the `mg!` macro
+    ///
+    /// ```rust
+    /// const _: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css").url_encoded());
+    /// ```
+    #[allow(unused)]
+    pub const fn url_encoded(self) -> Self {
+        Self
+    }
+}
+
+/// Create a css module asset from the local path or url to the css
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// Every class, id and animation name in a css module is renamed with a hash of the package and the path of the stylesheet, so the styles of a component library never conflict with styles from other stylesheets. The macro returns a [`CssModuleAsset`] that maps the classes in the stylesheet to the scoped class names:
+/// ```rust
+/// const BUTTON: manganis::CssModuleAsset = manganis::mg!(css_module("assets/button.css"));
+/// assert!(BUTTON.get("primary").is_some());
+/// ```
+/// Or declare the css module as a struct with a const for each class. Characters that can't be used in a rust identifier are replaced with `_`, so `.icon-button` becomes `ICONS::icon_button`. Using a class that is not in the stylesheet is a compile error:
+/// ```rust
+/// manganis::mg!(pub struct BUTTON = css_module("assets/button.css"));
+/// const PRIMARY: &str = BUTTON::primary;
+/// ```
+#[allow(unused)]
+pub const fn css_module(path: &'static str) -> CssModuleAssetBuilder {
+    CssModuleAssetBuilder
+}
+
+/// A builder for an inline css or js snippet. This must be used in the [`mg!`] macro.
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+pub struct SnippetAssetBuilder;
+
+/// Collect a snippet of css declared next to the code that uses it
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The CLI concatenates the css snippets from every crate into one minified stylesheet served at [`css_snippets`]. Snippets are ordered by the name of the crate they are declared in, snippets from the same crate keep the order they are collected in, and identical snippets are only included once. The macro returns the location the stylesheet is served at:
+/// ```rust
+/// const SNIPPETS: &str = manganis::mg!(css_inline(r#".button { padding: 4px; }"#));
+/// ```
+#[allow(unused)]
+pub const fn css_inline(snippet: &'static str) -> SnippetAssetBuilder {
+    SnippetAssetBuilder
+}
+
+/// Collect a snippet of js declared next to the code that uses it
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The CLI concatenates the js snippets from every crate into one minified classic script served at [`js_snippets`]. Snippets are ordered by the name of the crate they are declared in, snippets from the same crate keep the order they are collected in, and identical snippets are only included once. Each snippet runs in its own function, so its declarations, including functions and `var`s, don't conflict with other snippets. Assign to `window` to share a value between snippets. The macro returns the location the script is served at:
+/// ```rust
+/// const SNIPPETS: &str = manganis::mg!(js_inline(r#"document.body.classList.add("loaded");"#));
+/// ```
+#[allow(unused)]
+pub const fn js_inline(snippet: &'static str) -> SnippetAssetBuilder {
+    SnippetAssetBuilder
+}
+
+/// Get the location the stylesheet every css snippet declared with [`css_inline`] is bundled into is served at
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The location is resolved like the location of any other asset, so it includes the base path the assets are served from. The stylesheet is only written if the application declares at least one css snippet:
+/// ```rust
+/// const SNIPPETS: &str = manganis::mg!(css_snippets());
+/// ```
+#[allow(unused)]
+pub const fn css_snippets() -> SnippetAssetBuilder {
+    SnippetAssetBuilder
+}
+
+/// Get the location the script every js snippet declared with [`js_inline`] is bundled into is served at
+///
+/// > **Note**: This will do nothing outside of the `mg!` macro
+///
+/// The location is resolved like the location of any other asset, so it includes the base path the assets are served from. The script is only written if the application declares at least one js snippet:
+/// ```rust
+/// const SNIPPETS: &str = manganis::mg!(js_snippets());
+/// ```
+#[allow(unused)]
+pub const fn js_snippets() -> SnippetAssetBuilder {
+    SnippetAssetBuilder
+}
+
 /// A builder for a font asset. This must be used in the `mg!` macro.
 ///
 /// > **Note**: This will do nothing outside of the `mg!` macro
@@ -452,6 +1247,23 @@ pub const fn folder(path: &'static str) -> &'static str {
     path
 }
 
+/// Compares two strings in a const context
+const fn str_eq(a: &str, b: &str) -> bool {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
 /// A trait for something that can be used in the `mg!` macro
 ///
 /// > **Note**: These types will do nothing outside of the `mg!` macro
@@ -463,13 +1275,58 @@ mod __private {
     pub trait Sealed {}
 
     impl Sealed for ImageAssetBuilder {}
+    impl Sealed for IconAssetBuilder {}
+    impl Sealed for AtlasAssetBuilder {}
+    impl Sealed for IconSpriteAssetBuilder {}
     impl Sealed for FontAssetBuilder {}
     impl Sealed for JsAssetBuilder {}
     impl Sealed for JsonAssetBuilder {}
     impl Sealed for CssAssetBuilder {}
+    impl Sealed for CssModuleAssetBuilder {}
+    impl Sealed for SnippetAssetBuilder {}
     impl Sealed for &'static str {}
 }
 
 impl ForMgMacro for ImageAssetBuilder {}
+impl ForMgMacro for IconAssetBuilder {}
+impl ForMgMacro for AtlasAssetBuilder {}
+impl ForMgMacro for IconSpriteAssetBuilder {}
+impl ForMgMacro for CssModuleAssetBuilder {}
 impl ForMgMacro for FontAssetBuilder {}
+impl ForMgMacro for SnippetAssetBuilder {}
 impl ForMgMacro for &'static str {}
+
+#[cfg(all(test, feature = "html"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picture_lists_the_sources_before_the_fallback() {
+        const VARIANTS: &[ImageVariant] = &[
+            ImageVariant::new("/assets/photo-1.avif", "image/avif"),
+            ImageVariant::new("/assets/photo-2.webp", "image/webp"),
+            ImageVariant::new("/assets/photo-3.jpg", "image/jpeg"),
+        ];
+        let image = ImageAsset::new("/assets/photo-3.jpg").with_variants(VARIANTS);
+
+        assert_eq!(
+            image.picture("A \"quoted\" photo"),
+            concat!(
+                "<picture>",
+                r#"<source type="image/avif" srcset="/assets/photo-1.avif">"#,
+                r#"<source type="image/webp" srcset="/assets/photo-2.webp">"#,
+                r#"<img src="/assets/photo-3.jpg" alt="A &quot;quoted&quot; photo"></picture>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn picture_without_variants_is_only_the_image() {
+        let image = ImageAsset::new("/assets/logo.png");
+
+        assert_eq!(
+            image.picture("logo"),
+            r#"<picture><img src="/assets/logo.png" alt="logo"></picture>"#
+        );
+    }
+}
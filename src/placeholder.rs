@@ -0,0 +1,506 @@
+use std::f32::consts::PI;
+
+/// A small placeholder for an image that is generated at compile time with [`crate::ImageAssetBuilder::placeholder`]
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Hash)]
+pub enum ImagePlaceholder {
+    /// A base64 encoded [thumbhash](https://evanw.github.io/thumbhash/)
+    ThumbHash(&'static str),
+    /// A [blurhash](https://blurha.sh/) and the size it should be decoded at to keep the aspect ratio of the image
+    BlurHash {
+        /// The blurhash string
+        hash: &'static str,
+        /// The width the blurhash should be decoded at
+        width: u32,
+        /// The height the blurhash should be decoded at
+        height: u32,
+    },
+    /// The most common color in the image as a hex color like `#1a2b3c`
+    DominantColor(&'static str),
+}
+
+impl ImagePlaceholder {
+    /// Decodes the placeholder into a png data url that can be used as the source of an image while the full image loads
+    pub fn data_url(&self) -> Option<String> {
+        let (width, height, rgba) = match *self {
+            Self::ThumbHash(hash) => decode_thumbhash(&base64_decode(hash)?)?,
+            Self::BlurHash {
+                hash,
+                width,
+                height,
+            } => (width, height, decode_blurhash(hash, width, height)?),
+            Self::DominantColor(color) => (1, 1, parse_hex_color(color)?.to_vec()),
+        };
+        let png = encode_png(width, height, &rgba);
+        Some(format!("data:image/png;base64,{}", base64_encode(&png)))
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(triple >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in encoded.bytes().take_while(|byte| *byte != b'=') {
+        let value = BASE64_ALPHABET.iter().position(|c| *c == byte)? as u32;
+        buffer = buffer << 6 | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+/// Decodes a thumbhash into a small image. This follows the reference implementation at <https://github.com/evanw/thumbhash>
+fn decode_thumbhash(hash: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    if hash.len() < 5 {
+        return None;
+    }
+
+    // Read the header
+    let header24 = hash[0] as u32 | (hash[1] as u32) << 8 | (hash[2] as u32) << 16;
+    let header16 = hash[3] as u32 | (hash[4] as u32) << 8;
+    let l_dc = (header24 & 63) as f32 / 63.0;
+    let p_dc = ((header24 >> 6) & 63) as f32 / 31.5 - 1.0;
+    let q_dc = ((header24 >> 12) & 63) as f32 / 31.5 - 1.0;
+    let l_scale = ((header24 >> 18) & 31) as f32 / 31.0;
+    let has_alpha = header24 >> 23 != 0;
+    let p_scale = ((header16 >> 3) & 63) as f32 / 63.0;
+    let q_scale = ((header16 >> 9) & 63) as f32 / 63.0;
+    let is_landscape = header16 >> 15 != 0;
+    let l_max = if has_alpha { 5 } else { 7 };
+    let encoded_lx = if is_landscape { l_max } else { header16 & 7 } as usize;
+    let encoded_ly = if is_landscape { header16 & 7 } else { l_max } as usize;
+    let lx = encoded_lx.max(3);
+    let ly = encoded_ly.max(3);
+    let a_dc = if has_alpha {
+        (*hash.get(5)? & 15) as f32 / 15.0
+    } else {
+        1.0
+    };
+    let a_scale = if has_alpha {
+        (*hash.get(5)? >> 4) as f32 / 15.0
+    } else {
+        0.0
+    };
+
+    // Read the AC coefficients
+    let ac_start = if has_alpha { 6 } else { 5 };
+    let mut ac_index = 0;
+    let mut decode_channel = |nx: usize, ny: usize, scale: f32| -> Option<Vec<f32>> {
+        let mut ac = Vec::new();
+        for cy in 0..ny {
+            let mut cx = if cy > 0 { 0 } else { 1 };
+            while cx * ny < nx * (ny - cy) {
+                let nibble = (hash.get(ac_start + (ac_index >> 1))? >> ((ac_index & 1) * 4)) & 15;
+                ac.push((nibble as f32 / 7.5 - 1.0) * scale);
+                ac_index += 1;
+                cx += 1;
+            }
+        }
+        Some(ac)
+    };
+    let l_ac = decode_channel(lx, ly, l_scale)?;
+    let p_ac = decode_channel(3, 3, p_scale * 1.25)?;
+    let q_ac = decode_channel(3, 3, q_scale * 1.25)?;
+    let a_ac = if has_alpha {
+        decode_channel(5, 5, a_scale)?
+    } else {
+        Vec::new()
+    };
+
+    // Decode using the DCT into RGB
+    let ratio = encoded_lx as f32 / encoded_ly.max(1) as f32;
+    let width = (if ratio > 1.0 { 32.0 } else { 32.0 * ratio }).round() as usize;
+    let height = (if ratio > 1.0 { 32.0 / ratio } else { 32.0 }).round() as usize;
+    let (width, height) = (width.max(1), height.max(1));
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    let mut fx = vec![0.0; lx.max(if has_alpha { 5 } else { 3 })];
+    let mut fy = vec![0.0; ly.max(if has_alpha { 5 } else { 3 })];
+    for y in 0..height {
+        for x in 0..width {
+            let (mut l, mut p, mut q, mut a) = (l_dc, p_dc, q_dc, a_dc);
+
+            // Precompute the coefficients
+            for (cx, fx) in fx.iter_mut().enumerate() {
+                *fx = (PI / width as f32 * (x as f32 + 0.5) * cx as f32).cos();
+            }
+            for (cy, fy) in fy.iter_mut().enumerate() {
+                *fy = (PI / height as f32 * (y as f32 + 0.5) * cy as f32).cos();
+            }
+
+            // Decode L
+            let mut j = 0;
+            for (cy, fy) in fy.iter().take(ly).enumerate() {
+                let mut cx = if cy > 0 { 0 } else { 1 };
+                let fy2 = fy * 2.0;
+                while cx * ly < lx * (ly - cy) {
+                    l += l_ac[j] * fx[cx] * fy2;
+                    j += 1;
+                    cx += 1;
+                }
+            }
+
+            // Decode P and Q
+            let mut j = 0;
+            for (cy, fy) in fy.iter().take(3).enumerate() {
+                let mut cx = if cy > 0 { 0 } else { 1 };
+                let fy2 = fy * 2.0;
+                while cx < 3 - cy {
+                    let f = fx[cx] * fy2;
+                    p += p_ac[j] * f;
+                    q += q_ac[j] * f;
+                    j += 1;
+                    cx += 1;
+                }
+            }
+
+            // Decode A
+            if has_alpha {
+                let mut j = 0;
+                for (cy, fy) in fy.iter().take(5).enumerate() {
+                    let mut cx = if cy > 0 { 0 } else { 1 };
+                    let fy2 = fy * 2.0;
+                    while cx < 5 - cy {
+                        a += a_ac[j] * fx[cx] * fy2;
+                        j += 1;
+                        cx += 1;
+                    }
+                }
+            }
+
+            // Convert to RGB
+            let b = l - 2.0 / 3.0 * p;
+            let r = (3.0 * l - b + q) / 2.0;
+            let g = r - q;
+            for channel in [r, g, b, a] {
+                rgba.push((channel * 255.0).clamp(0.0, 255.0) as u8);
+            }
+        }
+    }
+
+    Some((width as u32, height as u32, rgba))
+}
+
+const BASE83_ALPHABET: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+fn base83_decode(encoded: &str) -> Option<u32> {
+    encoded.bytes().try_fold(0u32, |value, byte| {
+        let digit = BASE83_ALPHABET.iter().position(|c| *c == byte)? as u32;
+        Some(value * 83 + digit)
+    })
+}
+
+fn srgb_to_linear(value: u32) -> f32 {
+    let value = value as f32 / 255.0;
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    if value <= 0.0031308 {
+        (value * 12.92 * 255.0 + 0.5) as u8
+    } else {
+        ((1.055 * value.powf(1.0 / 2.4) - 0.055) * 255.0 + 0.5) as u8
+    }
+}
+
+/// Decodes a blurhash into an image of the given size. This follows the reference implementation at <https://github.com/woltapp/blurhash>
+fn decode_blurhash(hash: &str, width: u32, height: u32) -> Option<Vec<u8>> {
+    if !hash.is_ascii() || hash.len() < 6 {
+        return None;
+    }
+
+    let size_flag = base83_decode(&hash[0..1])?;
+    let components_x = (size_flag % 9 + 1) as usize;
+    let components_y = (size_flag / 9 + 1) as usize;
+    if hash.len() != 4 + 2 * components_x * components_y {
+        return None;
+    }
+    let maximum_value = (base83_decode(&hash[1..2])? + 1) as f32 / 166.0;
+
+    let mut colors = Vec::with_capacity(components_x * components_y);
+    let dc = base83_decode(&hash[2..6])?;
+    colors.push([
+        srgb_to_linear(dc >> 16),
+        srgb_to_linear((dc >> 8) & 255),
+        srgb_to_linear(dc & 255),
+    ]);
+    for i in 1..components_x * components_y {
+        let ac = base83_decode(&hash[4 + i * 2..6 + i * 2])?;
+        let quantized = [ac / (19 * 19), (ac / 19) % 19, ac % 19];
+        colors.push(quantized.map(|value| {
+            let value = (value as f32 - 9.0) / 9.0;
+            value.signum() * value.abs().powi(2) * maximum_value
+        }));
+    }
+
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut pixel = [0.0; 3];
+            for j in 0..components_y {
+                for i in 0..components_x {
+                    let basis = (PI * x as f32 * i as f32 / width as f32).cos()
+                        * (PI * y as f32 * j as f32 / height as f32).cos();
+                    let color = colors[i + j * components_x];
+                    for (pixel, color) in pixel.iter_mut().zip(color) {
+                        *pixel += color * basis;
+                    }
+                }
+            }
+            rgba.extend(pixel.map(linear_to_srgb));
+            rgba.push(u8::MAX);
+        }
+    }
+
+    Some(rgba)
+}
+
+fn parse_hex_color(color: &str) -> Option<[u8; 4]> {
+    let color = color.strip_prefix('#')?;
+    if color.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(color.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?, u8::MAX])
+}
+
+/// Encodes an rgba image as an uncompressed png. The placeholder images are tiny, so compressing them isn't worth the extra code
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    fn write_chunk(png: &mut Vec<u8>, ty: &[u8; 4], data: &[u8]) {
+        png.extend((data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend(ty);
+        png.extend(data);
+        let crc = crc32(&png[start..]);
+        png.extend(crc.to_be_bytes());
+    }
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend(width.to_be_bytes());
+    header.extend(height.to_be_bytes());
+    // 8 bit rgba with the default compression, filter, and interlace methods
+    header.extend([8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // Each scanline starts with the filter type
+    let mut scanlines = Vec::with_capacity(rgba.len() + height as usize);
+    for row in rgba.chunks_exact(width as usize * 4) {
+        scanlines.push(0);
+        scanlines.extend(row);
+    }
+
+    // Wrap the scanlines in a zlib stream with uncompressed deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<_> = scanlines.chunks(u16::MAX as usize).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        let last = i + 1 == blocks.len();
+        zlib.push(last as u8);
+        let len = block.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(adler32(&scanlines).to_be_bytes());
+    write_chunk(&mut png, b"IDAT", &zlib);
+
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xEDB88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    b << 16 | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use manganis_common::{AssetSource, ImageOptions, ImageType, Placeholder, PlaceholderType};
+
+    /// Generates a placeholder with the compile time encoder for a solid color image
+    fn generate(ty: PlaceholderType, width: u32, height: u32, color: [u8; 4]) -> Placeholder {
+        // The file is removed when it is dropped
+        let file = tempfile::Builder::new()
+            .prefix("manganis-placeholder-")
+            .suffix(".png")
+            .tempfile()
+            .unwrap();
+        image::RgbaImage::from_pixel(width, height, image::Rgba(color))
+            .save(file.path())
+            .unwrap();
+        ty.generate(
+            &AssetSource::Local(file.path().to_path_buf()),
+            &ImageOptions::new(ImageType::Png, None),
+        )
+        .unwrap()
+    }
+
+    fn leak(value: String) -> &'static str {
+        Box::leak(value.into_boxed_str())
+    }
+
+    /// Converts a placeholder from the compile time representation to the runtime representation the macro generates
+    fn runtime_placeholder(placeholder: Placeholder) -> ImagePlaceholder {
+        match placeholder {
+            Placeholder::ThumbHash(hash) => ImagePlaceholder::ThumbHash(leak(hash)),
+            Placeholder::BlurHash {
+                hash,
+                width,
+                height,
+            } => ImagePlaceholder::BlurHash {
+                hash: leak(hash),
+                width,
+                height,
+            },
+            Placeholder::DominantColor(color) => ImagePlaceholder::DominantColor(leak(color)),
+        }
+    }
+
+    /// Decodes the png in a data url, checking the signature and the size in the header against the decoded image
+    fn decode_data_url(placeholder: ImagePlaceholder) -> image::RgbaImage {
+        let data_url = placeholder.data_url().unwrap();
+        let png = base64_decode(data_url.strip_prefix("data:image/png;base64,").unwrap()).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+
+        let image = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(image.dimensions(), (width, height));
+        image
+    }
+
+    fn assert_color_near(pixel: image::Rgba<u8>, color: [u8; 4], tolerance: u8) {
+        for (actual, expected) in pixel.0.iter().zip(color) {
+            assert!(
+                actual.abs_diff(expected) <= tolerance,
+                "{:?} is not close to {color:?}",
+                pixel.0
+            );
+        }
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        for data in [&b""[..], b"a", b"ab", b"abc", b"\x00\xff\x10\x80"] {
+            assert_eq!(base64_decode(&base64_encode(data)).unwrap(), data);
+        }
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_decode("not base64!"), None);
+    }
+
+    #[test]
+    fn thumbhash_round_trip() {
+        let color = [200, 40, 40, 255];
+        let image = decode_data_url(runtime_placeholder(generate(
+            PlaceholderType::ThumbHash,
+            64,
+            32,
+            color,
+        )));
+
+        // The thumbhash stores an approximation of the aspect ratio of the image
+        let (width, height) = image.dimensions();
+        assert_eq!(width, 32);
+        assert!((16..=20).contains(&height));
+        assert_color_near(*image.get_pixel(width / 2, height / 2), color, 12);
+    }
+
+    #[test]
+    fn blurhash_round_trip() {
+        let color = [30, 90, 160, 255];
+        let placeholder = generate(PlaceholderType::BlurHash, 20, 40, color);
+        let Placeholder::BlurHash { width, height, .. } = placeholder else {
+            panic!("expected a blurhash");
+        };
+        let image = decode_data_url(runtime_placeholder(placeholder));
+
+        assert_eq!(image.dimensions(), (width, height));
+        assert_eq!(height, 32);
+        assert_eq!(width, 16);
+        assert_color_near(*image.get_pixel(width / 2, height / 2), color, 4);
+    }
+
+    #[test]
+    fn dominant_color_round_trip() {
+        let color = [18, 52, 86, 255];
+        let placeholder = generate(PlaceholderType::DominantColor, 8, 8, color);
+        assert_eq!(
+            placeholder,
+            Placeholder::DominantColor("#123456".to_string())
+        );
+
+        let image = decode_data_url(runtime_placeholder(placeholder));
+        assert_eq!(image.dimensions(), (1, 1));
+        assert_eq!(image.get_pixel(0, 0).0, color);
+    }
+
+    #[test]
+    fn invalid_placeholders_are_rejected() {
+        assert_eq!(ImagePlaceholder::ThumbHash("AAA").data_url(), None);
+        assert_eq!(ImagePlaceholder::DominantColor("#12345").data_url(), None);
+        assert_eq!(
+            ImagePlaceholder::BlurHash {
+                hash: "too short",
+                width: 4,
+                height: 4
+            }
+            .data_url(),
+            None
+        );
+    }
+}
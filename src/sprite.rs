@@ -0,0 +1,60 @@
+/// An svg icon sprite asset that is built by the [`crate::mg!`] macro with [`crate::icon_sprite`]. Icon sprites declared as a struct dereference to the asset:
+/// ```rust
+/// manganis::mg!(struct ICONS = icon_sprite("assets/icons"));
+/// let asset: &manganis::IconSpriteAsset = &ICONS;
+/// ```
+#[derive(Debug, PartialEq, PartialOrd, Clone, Hash)]
+pub struct IconSpriteAsset {
+    /// The path to the sprite
+    path: &'static str,
+    /// The ids of the symbols in the sprite
+    ids: &'static [&'static str],
+}
+
+impl IconSpriteAsset {
+    /// Creates a new icon sprite asset
+    pub const fn new(path: &'static str, ids: &'static [&'static str]) -> Self {
+        Self { path, ids }
+    }
+
+    /// Returns the path to the sprite
+    pub const fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Returns the ids of the symbols in the sprite
+    pub const fn ids(&self) -> &'static [&'static str] {
+        self.ids
+    }
+
+    /// Returns true if the sprite contains a symbol with the id
+    pub const fn contains(&self, id: &str) -> bool {
+        let mut i = 0;
+        while i < self.ids.len() {
+            if crate::str_eq(self.ids[i], id) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// Returns the url of the symbol with the id to use in the href of a `<use>` element
+    pub fn href(&self, id: &str) -> String {
+        format!("{}#{}", self.path, id)
+    }
+}
+
+impl std::ops::Deref for IconSpriteAsset {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.path
+    }
+}
+
+impl std::fmt::Display for IconSpriteAsset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.path.fmt(f)
+    }
+}
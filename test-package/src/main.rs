@@ -6,6 +6,10 @@ use test_package_dependency::*;
 
 const TEXT_FILE: &str = manganis::mg!("/test-package-dependency/src/asset.txt");
 const VIDEO_FILE: &str = manganis::mg!("/test.mp4");
+// The same snippet in two crates is only bundled once
+const CSS_SNIPPET_COPY: &str = manganis::mg!(css_inline(".snippet { color: red; }"));
+// Every js snippet runs in its own block, so this doesn't conflict with the snippet in the dependency
+const OTHER_JS_SNIPPET: &str = manganis::mg!(js_inline("const snippet = 2;"));
 
 const ALL_ASSETS: &[&str] = &[
     VIDEO_FILE,
@@ -22,12 +26,26 @@ const ALL_ASSETS: &[&str] = &[
     RESIZED_AVIF_ASSET.path(),
     WEBP_ASSET.path(),
     RESIZED_WEBP_ASSET.path(),
+    AUTO_ASSET.path(),
+    MULTI_FORMAT_ASSET.path(),
+    TRANSFORMED_ASSET.path(),
+    ICO_ASSET.path(),
+    ICON_SET.favicon(),
+    ATLAS.sheets()[0],
+    ICON_SPRITE.path(),
+    CSS_MODULE.path(),
+    SCSS_ASSET,
+    TS_ASSET,
     ROBOTO_FONT,
     COMFORTAA_FONT,
     ROBOTO_FONT_LIGHT_FONT,
     SCRIPT,
     DATA,
     FOLDER,
+    CSS_SNIPPET,
+    CSS_SNIPPET_COPY,
+    JS_SNIPPET,
+    OTHER_JS_SNIPPET,
 ];
 
 fn main() {
@@ -5,3 +5,5 @@ pub const TEXT_ASSET: &str = manganis::mg!("./src/asset.txt");
 pub const IMAGE_ASSET: &str =
     manganis::mg!("https://rustacean.net/assets/rustacean-flat-happy.png");
 pub const HTML_ASSET: &str = manganis::mg!("https://github.com/DioxusLabs/dioxus");
+pub const CSS_SNIPPET: &str = manganis::mg!(css_inline(".snippet { color: red; }"));
+pub const JS_SNIPPET: &str = manganis::mg!(js_inline("const snippet = 1;"));
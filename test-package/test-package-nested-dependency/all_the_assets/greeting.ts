@@ -0,0 +1,7 @@
+interface Greeting {
+    name: string;
+}
+
+export function greet({ name }: Greeting): string {
+    return `Hello, ${name}!`;
+}
@@ -1,5 +1,7 @@
 const _: &str = manganis::classes!("flex flex-row p-4");
 pub const CSS_ASSET: &str = manganis::mg!(file("./all_the_assets/style.css"));
+pub const SCSS_ASSET: &str = manganis::mg!(file("./all_the_assets/theme.scss"));
+pub const TS_ASSET: &str = manganis::mg!(file("./all_the_assets/greeting.ts"));
 pub const PNG_ASSET: &str = manganis::mg!(file("./all_the_assets/rustacean-flat-gesture.png"));
 pub const RESIZED_PNG_ASSET: manganis::ImageAsset =
     manganis::mg!(image("./all_the_assets/rustacean-flat-gesture.png").size(52, 52));
@@ -21,3 +23,32 @@ pub const RESIZED_WEBP_ASSET: manganis::ImageAsset =
     manganis::mg!(image("./all_the_assets/rustacean-flat-gesture.png")
         .format(ImageType::Webp)
         .size(52, 52));
+pub const AUTO_ASSET: manganis::ImageAsset =
+    manganis::mg!(image("./all_the_assets/rustacean-flat-gesture.png").format(ImageType::Auto));
+pub const MULTI_FORMAT_ASSET: manganis::ImageAsset = manganis::mg!(image(
+    "./all_the_assets/rustacean-flat-gesture.png"
+)
+.formats([ImageType::Avif, ImageType::Webp, ImageType::Jpg]));
+pub const TRANSFORMED_ASSET: manganis::ImageAsset =
+    manganis::mg!(image("./all_the_assets/rustacean-flat-gesture.png")
+        .rotate(90)
+        .crop(0, 0, 64, 64)
+        .grayscale()
+        .tint(255, 0, 0, 0.25)
+        .format(ImageType::Png));
+pub const ICO_ASSET: manganis::ImageAsset =
+    manganis::mg!(image("./all_the_assets/rustacean-flat-gesture.png")
+        .format(ImageType::Ico)
+        .size(32, 32));
+pub const ICON_SET: manganis::IconAsset =
+    manganis::mg!(icon("./all_the_assets/rustacean-flat-gesture.png").background(0, 0, 0));
+pub const ATLAS: manganis::AtlasAsset =
+    manganis::mg!(atlas("./all_the_assets").max_size(1024, 1024));
+pub const ICON_SPRITE: manganis::IconSpriteAsset =
+    manganis::mg!(icon_sprite("./all_the_assets/icons"));
+pub const CSS_MODULE: manganis::CssModuleAsset =
+    manganis::mg!(css_module("./all_the_assets/style.css"));
+manganis::mg!(pub struct STYLE = css_module("./all_the_assets/style.css"));
+pub const FOO_CLASS: &str = STYLE::foo;
+manganis::mg!(pub struct ICONS = icon_sprite("./all_the_assets/icons"));
+pub const HOME_ICON: &str = ICONS::home;